@@ -0,0 +1,130 @@
+use asset_library::{
+    archive::*,
+    vfs::{
+        archive_mount_point::ArchiveMountPoint, physical_mount_point::VfsPhysicalMountPoint,
+        VfsMountPoint,
+    },
+    AssetDescriptor,
+};
+use criterion::*;
+use std::{collections::BTreeMap, fs::File, path::PathBuf};
+
+/// Builds an archive with `file_count` small files and mounts it.
+fn mount_archive(file_count: usize) -> ArchiveMountPoint {
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    path.push("../tmp/bench");
+    std::fs::create_dir_all(&path).unwrap();
+    path.push(format!("lookup_{}.harchive", file_count));
+
+    let mut mount_point = AssetArchiveBuilder::new(File::create(&path).unwrap())
+        .unwrap()
+        .add_mount_point("bench", 0)
+        .unwrap();
+    for i in 0..file_count {
+        mount_point = mount_point
+            .write_file(
+                format!("meshes/mesh_{}", i),
+                "yaml",
+                &i.to_le_bytes(),
+                AssetArchiveCompressionFormat::None,
+            )
+            .unwrap();
+    }
+    mount_point.finish().finish().unwrap();
+
+    let archive = AssetArchive::read_from_file(&path).unwrap();
+    ArchiveMountPoint::from_archive(&archive).pop().unwrap()
+}
+
+/// Writes `file_count` small files and an index file listing all of them into a directory and mounts it.
+fn mount_directory(file_count: usize) -> VfsPhysicalMountPoint {
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    path.push(format!("../tmp/bench/lookup_{}", file_count));
+    let _ = std::fs::remove_dir_all(&path);
+    std::fs::create_dir_all(path.join("meshes")).unwrap();
+
+    let mut files = Vec::with_capacity(file_count);
+    for i in 0..file_count {
+        let identifier = format!("meshes/mesh_{}", i);
+        std::fs::write(path.join(format!("{}.yaml", identifier)), i.to_le_bytes()).unwrap();
+        files.push(AssetDescriptor::new(
+            "bench".into(),
+            identifier,
+            "yaml".into(),
+        ));
+    }
+    let mut index = BTreeMap::new();
+    index.insert("files", files);
+    std::fs::write(
+        path.join("index.yaml"),
+        serde_yaml::to_string(&index).unwrap(),
+    )
+    .unwrap();
+
+    let mount = VfsPhysicalMountPoint::new(&"bench", &path).unwrap();
+    assert!(mount.asset_index().is_some());
+    mount
+}
+
+fn lookup(c: &mut Criterion) {
+    let mut group = c.benchmark_group("archive_mount_point_lookup");
+    for file_count in [100, 1_000, 10_000, 100_000] {
+        let mount = mount_archive(file_count);
+        // The last file is the worst case for a linear search.
+        let identifier = format!("meshes/mesh_{}", file_count - 1);
+        let mut buffer = Vec::new();
+
+        group.bench_with_input(
+            BenchmarkId::new("has_file", file_count),
+            &identifier,
+            |b, identifier| b.iter(|| mount.has_file(black_box(identifier))),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("load_asset_into", file_count),
+            &identifier,
+            |b, identifier| {
+                b.iter(|| {
+                    buffer.clear();
+                    mount.load_asset_into(black_box(identifier), &mut buffer)
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+fn index_lookup(c: &mut Criterion) {
+    let mut group = c.benchmark_group("physical_mount_point_lookup");
+    // Every file is written to disk, larger directories take too long to set up.
+    for file_count in [100, 1_000, 10_000] {
+        let mount = mount_directory(file_count);
+        let index = mount.asset_index().unwrap();
+        let identifier = format!("meshes/mesh_{}", file_count - 1);
+        let mut buffer = Vec::new();
+
+        group.bench_with_input(
+            BenchmarkId::new("asset_index_file", file_count),
+            &identifier,
+            |b, identifier| b.iter(|| index.file(black_box(identifier))),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("has_file", file_count),
+            &identifier,
+            |b, identifier| b.iter(|| mount.has_file(black_box(identifier))),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("load_asset_into", file_count),
+            &identifier,
+            |b, identifier| {
+                b.iter(|| {
+                    buffer.clear();
+                    mount.load_asset_into(black_box(identifier), &mut buffer)
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, lookup, index_lookup);
+criterion_main!(benches);
@@ -1,5 +1,12 @@
 use super::*;
-use std::io::{BufWriter, Seek, SeekFrom, Write};
+use crate::AssetReference;
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    io::{BufWriter, Seek, SeekFrom, Write},
+};
+use uuid::Uuid;
+use xxhash_rust::xxh3::{xxh3_128, xxh3_64};
 
 #[derive(Debug)]
 pub struct AssetArchiveMountPointBuilder {
@@ -7,6 +14,8 @@ pub struct AssetArchiveMountPointBuilder {
     version: u64,
     archive_builder: AssetArchiveBuilder,
     written_files: Vec<AssetArchiveFileHeader>,
+    removed_files: Vec<String>,
+    dictionary: Option<AssetArchiveDictionary>,
 }
 
 impl AssetArchiveMountPointBuilder {
@@ -20,91 +29,260 @@ impl AssetArchiveMountPointBuilder {
             mount_point: mount_point.as_ref().to_lowercase(),
             version,
             written_files: Vec::with_capacity(16),
+            removed_files: Vec::new(),
+            dictionary: None,
         }
     }
 
+    /// Trains a zstd dictionary over `samples`, usually all files which are written into the mount point.
+    /// Files written with `ZSTDDictionary` afterwards are compressed using it.
+    // The builder is returned with the error so callers can recover it, it is moved and not boxed.
+    #[allow(clippy::result_large_err)]
+    pub fn train_dictionary<S: AsRef<[u8]>>(
+        self,
+        samples: &[S],
+        max_size: usize,
+    ) -> Result<Self, (Self, AssetArchiveError)> {
+        match AssetArchiveDictionary::train(samples, max_size) {
+            Ok(dictionary) => Ok(self.with_dictionary(dictionary)),
+            Err(e) => Err((self, e)),
+        }
+    }
+
+    /// Uses a previously trained dictionary for files written with `ZSTDDictionary`.
+    pub fn with_dictionary(mut self, dictionary: AssetArchiveDictionary) -> Self {
+        self.dictionary = Some(dictionary);
+        self
+    }
+
+    #[allow(clippy::result_large_err)]
     pub fn write_file(
-        mut self,
+        self,
         identifier: impl AsRef<str>,
         format: impl AsRef<str>,
         uncompressed_blob: &[u8],
         compression_format: AssetArchiveCompressionFormat,
     ) -> Result<Self, (Self, AssetArchiveError)> {
-        use AssetArchiveCompressionFormat::{None, LZ4};
-        match compression_format {
-            None => {
-                match self.archive_builder.writer.write(uncompressed_blob) {
-                    Ok(v) => v,
-                    Err(e) => return Err((self, AssetArchiveError::Io(e))),
-                };
-                self.written_files.push(AssetArchiveFileHeader::new(
-                    identifier.as_ref().to_lowercase(),
-                    format.as_ref().to_lowercase(),
-                    self.archive_builder.offset,
-                    uncompressed_blob.len() as u64,
-                    uncompressed_blob.len() as u64,
-                    None,
-                ));
-                self.archive_builder.offset += uncompressed_blob.len() as u64;
-                Ok(self)
+        self.write_file_with_dependencies(
+            identifier,
+            format,
+            &[],
+            uncompressed_blob,
+            compression_format,
+        )
+    }
+
+    /// Writes a file which requires the given assets, see `AssetSystem::dependency_order`.
+    #[allow(clippy::result_large_err)]
+    pub fn write_file_with_dependencies(
+        self,
+        identifier: impl AsRef<str>,
+        format: impl AsRef<str>,
+        dependencies: &[AssetReference],
+        uncompressed_blob: &[u8],
+        compression_format: AssetArchiveCompressionFormat,
+    ) -> Result<Self, (Self, AssetArchiveError)> {
+        match self.prepare_file(
+            identifier,
+            format,
+            dependencies,
+            uncompressed_blob,
+            compression_format,
+        ) {
+            Ok(file) => self.write_prepared_file(file),
+            Err(e) => Err((self, e)),
+        }
+    }
+
+    /// Compresses and encrypts a file without writing it.
+    /// Files can be prepared from multiple threads at once and written in a fixed order afterwards.
+    pub fn prepare_file<'a>(
+        &self,
+        identifier: impl AsRef<str>,
+        format: impl AsRef<str>,
+        dependencies: &[AssetReference],
+        uncompressed_blob: &'a [u8],
+        compression_format: AssetArchiveCompressionFormat,
+    ) -> Result<AssetArchivePreparedFile<'a>, AssetArchiveError> {
+        use AssetArchiveCompressionFormat::{LZ4Chunked, None, ZSTDDictionary, LZ4, ZSTD};
+        let level = self.archive_builder.compression_level;
+        let compressed = match compression_format {
+            None => Cow::Borrowed(uncompressed_blob),
+            LZ4 => Cow::Owned(lz4_flex::compress(uncompressed_blob)),
+            LZ4Chunked => Cow::Owned(compress_lz4_chunked(uncompressed_blob, LZ4_CHUNK_SIZE)),
+            ZSTD => Cow::Owned(zstd::bulk::compress(uncompressed_blob, level)?),
+            ZSTDDictionary => match &self.dictionary {
+                Some(dictionary) => Cow::Owned(dictionary.compress(uncompressed_blob, level)?),
+                Option::None => return Err(AssetArchiveError::MissingDictionary),
+            },
+        };
+        let (stored, encryption) = match &self.archive_builder.key {
+            Some(key) => {
+                let (encrypted, encryption) = encrypt(
+                    key,
+                    &self.mount_point,
+                    self.version,
+                    &identifier.as_ref().to_lowercase(),
+                    &format.as_ref().to_lowercase(),
+                    &compressed,
+                )?;
+                (Cow::Owned(encrypted), encryption)
             }
-            LZ4 => {
-                let compressed = lz4_flex::compress(uncompressed_blob);
-                match self.archive_builder.writer.write(&compressed) {
-                    Ok(v) => v,
-                    Err(e) => return Err((self, AssetArchiveError::Io(e))),
-                };
-                self.written_files.push(AssetArchiveFileHeader::new(
-                    identifier.as_ref().to_lowercase(),
-                    format.as_ref().to_lowercase(),
-                    self.archive_builder.offset,
-                    compressed.len() as u64,
-                    uncompressed_blob.len() as u64,
-                    LZ4,
-                ));
-                self.archive_builder.offset += compressed.len() as u64;
-                Ok(self)
+            Option::None => (compressed, AssetArchiveEncryption::None),
+        };
+        Ok(AssetArchivePreparedFile {
+            identifier: identifier.as_ref().to_lowercase(),
+            format: format.as_ref().to_lowercase(),
+            dependencies: dependencies.to_vec(),
+            stored,
+            uncompressed_size: uncompressed_blob.len() as u64,
+            compression_format,
+            encryption,
+        })
+    }
+
+    /// Writes a file returned by `prepare_file`.
+    /// If the archive already stores the same bytes, the file header points at them instead of storing them again.
+    #[allow(clippy::result_large_err)]
+    pub fn write_prepared_file(
+        mut self,
+        file: AssetArchivePreparedFile<'_>,
+    ) -> Result<Self, (Self, AssetArchiveError)> {
+        let key = (xxh3_128(&file.stored), file.stored.len() as u64);
+        let builder = &mut self.archive_builder;
+        let offset = match builder.stored_blobs.get(&key) {
+            Some(stored) => {
+                builder
+                    .report
+                    .deduplicated
+                    .push(AssetArchiveDeduplicatedFile {
+                        mount_point: self.mount_point.clone(),
+                        identifier: file.identifier.clone(),
+                        original_mount_point: stored.mount_point.clone(),
+                        original_identifier: stored.identifier.clone(),
+                        bytes: file.stored.len() as u64,
+                    });
+                stored.offset
             }
-            AssetArchiveCompressionFormat::ZSTD => {
-                let compressed = match zstd::bulk::compress(uncompressed_blob, 0) {
-                    Ok(v) => v,
-                    Err(e) => return Err((self, AssetArchiveError::Io(e))),
-                };
-                match self.archive_builder.writer.write(&compressed) {
-                    Ok(v) => v,
-                    Err(e) => return Err((self, AssetArchiveError::Io(e))),
-                };
-                self.written_files.push(AssetArchiveFileHeader::new(
-                    identifier.as_ref().to_lowercase(),
-                    format.as_ref().to_lowercase(),
-                    self.archive_builder.offset,
-                    compressed.len() as u64,
-                    uncompressed_blob.len() as u64,
-                    AssetArchiveCompressionFormat::ZSTD,
-                ));
-                self.archive_builder.offset += compressed.len() as u64;
-                Ok(self)
+            None => {
+                if let Err(e) = builder.writer.write_all(&file.stored) {
+                    return Err((self, AssetArchiveError::Io(e)));
+                }
+                let offset = builder.offset;
+                builder.stored_blobs.insert(
+                    key,
+                    StoredBlob {
+                        offset,
+                        mount_point: self.mount_point.clone(),
+                        identifier: file.identifier.clone(),
+                    },
+                );
+                builder.offset += file.stored.len() as u64;
+                builder.report.stored_bytes += file.stored.len() as u64;
+                offset
             }
-        }
+        };
+        builder.report.files += 1;
+        self.written_files.push(
+            AssetArchiveFileHeader::new(
+                file.identifier,
+                file.format,
+                offset,
+                file.stored.len() as u64,
+                file.uncompressed_size,
+                xxh3_64(&file.stored),
+                file.compression_format,
+            )
+            .with_dependencies(file.dependencies)
+            .with_encryption(file.encryption),
+        );
+        Ok(self)
+    }
+
+    /// Returns the report of the archive written so far, see `AssetArchiveBuilder::report`.
+    pub fn report(&self) -> &AssetArchiveBuildReport {
+        &self.archive_builder.report
+    }
+
+    /// Marks a file as removed, hiding it in lower versions of the mount point.
+    pub fn remove_file(mut self, identifier: impl AsRef<str>) -> Self {
+        self.removed_files.push(identifier.as_ref().to_lowercase());
+        self
     }
 
     pub fn finish(mut self) -> AssetArchiveBuilder {
-        self.archive_builder
-            .written_mounts
-            .push(AssetArchiveMountPointHeader::new(
+        self.archive_builder.written_mounts.push(
+            AssetArchiveMountPointHeader::new(
                 self.version,
                 self.mount_point,
                 self.written_files,
-            ));
+                self.removed_files,
+            )
+            .with_dictionary(self.dictionary),
+        );
         self.archive_builder
     }
 }
 
+/// A compressed and possibly encrypted file, see `AssetArchiveMountPointBuilder::prepare_file`.
+#[derive(Debug)]
+pub struct AssetArchivePreparedFile<'a> {
+    identifier: String,
+    format: String,
+    dependencies: Vec<AssetReference>,
+    stored: Cow<'a, [u8]>,
+    uncompressed_size: u64,
+    compression_format: AssetArchiveCompressionFormat,
+    encryption: AssetArchiveEncryption,
+}
+
+/// Statistics of an archive build, see `AssetArchiveBuilder::report`.
+#[derive(Debug, Default, Clone)]
+pub struct AssetArchiveBuildReport {
+    /// Number of written files, including deduplicated ones.
+    pub files: usize,
+    /// Bytes stored for the contents of files, excluding the header.
+    pub stored_bytes: u64,
+    /// Files whose bytes were already stored for another file.
+    pub deduplicated: Vec<AssetArchiveDeduplicatedFile>,
+}
+
+impl AssetArchiveBuildReport {
+    /// Bytes which were not stored thanks to deduplication.
+    pub fn deduplicated_bytes(&self) -> u64 {
+        self.deduplicated.iter().map(|f| f.bytes).sum()
+    }
+}
+
+/// A file which shares the stored bytes of a file written before it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssetArchiveDeduplicatedFile {
+    pub mount_point: String,
+    pub identifier: String,
+    pub original_mount_point: String,
+    pub original_identifier: String,
+    pub bytes: u64,
+}
+
+/// Bytes stored in the archive, identified by their hash and length.
+#[derive(Debug)]
+struct StoredBlob {
+    offset: u64,
+    mount_point: String,
+    identifier: String,
+}
+
 #[derive(Debug)]
 pub struct AssetArchiveBuilder {
     writer: BufWriter<File>,
     written_mounts: Vec<AssetArchiveMountPointHeader>,
     offset: u64,
+    uuid: Uuid,
+    key: Option<AssetArchiveKey>,
+    compression_level: i32,
+    content_uuid: bool,
+    stored_blobs: HashMap<(u128, u64), StoredBlob>,
+    report: AssetArchiveBuildReport,
 }
 
 impl AssetArchiveBuilder {
@@ -114,9 +292,54 @@ impl AssetArchiveBuilder {
             writer: BufWriter::new(file),
             written_mounts: Vec::with_capacity(16),
             offset: 0,
+            uuid: Uuid::new_v4(),
+            key: None,
+            compression_level: 0,
+            content_uuid: false,
+            stored_blobs: HashMap::new(),
+            report: AssetArchiveBuildReport::default(),
         })
     }
 
+    /// Sets the zstd compression level, 0 selects zstd's default level.
+    pub fn with_compression_level(mut self, level: i32) -> Self {
+        self.compression_level = level;
+        self
+    }
+
+    pub fn compression_level(&self) -> i32 {
+        self.compression_level
+    }
+
+    /// Encrypts all files written after this call using `key`, see `AssetArchiveEncryption`.
+    /// Readers have to provide the key for the archive's UUID, see `KeyProvider`.
+    pub fn encrypt_with(mut self, key: AssetArchiveKey) -> Self {
+        self.key = Some(key);
+        self
+    }
+
+    /// Derives the UUID from the archive's contents instead of generating a random one.
+    /// Mount points and files are sorted in the header, so building the same inputs produces identical archives.
+    /// Encrypted archives are never identical, every file is encrypted with a random nonce.
+    pub fn with_content_uuid(mut self) -> Self {
+        self.content_uuid = true;
+        self
+    }
+
+    /// Returns the number of files and bytes written so far, including the files deduplicated by content.
+    /// Files with equal stored bytes are stored once, encrypted files are never equal.
+    pub fn report(&self) -> &AssetArchiveBuildReport {
+        &self.report
+    }
+
+    /// Returns the UUID which is written into the archive header.
+    /// With `with_content_uuid` the UUID is only known once the archive is finished, see `finish`.
+    pub fn uuid(&self) -> &Uuid {
+        &self.uuid
+    }
+
+    // The builder is returned with the error so callers can recover it, it is moved and not boxed.
+    #[allow(clippy::result_large_err)]
     pub fn add_mount_point(
         self,
         mount_point: impl AsRef<str>,
@@ -138,17 +361,24 @@ impl AssetArchiveBuilder {
         ))
     }
 
-    pub fn finish(self) -> Result<(), AssetArchiveError> {
-        let header = AssetArchiveHeader::new(self.written_mounts);
+    /// Writes the header and returns the UUID of the archive.
+    pub fn finish(self) -> Result<Uuid, AssetArchiveError> {
+        let mut header = AssetArchiveHeader::with_uuid(self.uuid, self.written_mounts);
+        if self.content_uuid {
+            header.sort();
+            header.set_uuid(Uuid::nil());
+            header.set_uuid(Uuid::from_u128(xxh3_128(&serde_cbor::to_vec(&header)?)));
+        }
         let mut writer = self.writer;
         let cbor_header = serde_cbor::to_vec(&header)?;
         let compressed_header = zstd::bulk::compress(&cbor_header, 0)?;
-        writer.write(&compressed_header)?;
-        let uncompressed_size_bytes = u64::to_le_bytes(cbor_header.len() as u64);
-        writer.write(&uncompressed_size_bytes)?;
-        let compressed_size_bytes = u64::to_le_bytes(compressed_header.len() as u64);
-        writer.write(&compressed_size_bytes)?;
+        writer.write_all(&compressed_header)?;
+        writer.write_all(&u64::to_le_bytes(cbor_header.len() as u64))?;
+        writer.write_all(&u64::to_le_bytes(xxh3_64(&compressed_header)))?;
+        writer.write_all(&u64::to_le_bytes(compressed_header.len() as u64))?;
+        writer.write_all(&u32::to_le_bytes(ASSET_ARCHIVE_VERSION))?;
+        writer.write_all(&ASSET_ARCHIVE_MAGIC)?;
         writer.flush()?;
-        Ok(())
+        Ok(*header.uuid())
     }
 }
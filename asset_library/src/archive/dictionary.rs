@@ -0,0 +1,75 @@
+use ::serde::*;
+use std::sync::Arc;
+use zstd::dict::DecoderDictionary;
+
+use super::AssetArchiveError;
+
+/// A zstd dictionary, trained over the files of a mount point.
+/// Small files share most of their structure, compressing them with a shared dictionary improves ratios considerably.
+/// Cloning is cheap, the dictionary is shared.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(from = "serde_bytes::ByteBuf", into = "serde_bytes::ByteBuf")]
+pub struct AssetArchiveDictionary {
+    bytes: Arc<Vec<u8>>,
+    /// Prepared once, loading a dictionary for every decompressed file is expensive.
+    decoder: Arc<DecoderDictionary<'static>>,
+}
+
+impl AssetArchiveDictionary {
+    /// Default maximum size of trained dictionaries.
+    pub const DEFAULT_SIZE: usize = 112 * 1024;
+
+    pub fn new(bytes: Vec<u8>) -> Self {
+        Self {
+            decoder: Arc::new(DecoderDictionary::copy(&bytes)),
+            bytes: Arc::new(bytes),
+        }
+    }
+
+    /// Trains a dictionary of at most `max_size` bytes.
+    /// Fails if there are not enough samples to train a dictionary of useful size.
+    pub fn train<S: AsRef<[u8]>>(
+        samples: &[S],
+        max_size: usize,
+    ) -> Result<Self, AssetArchiveError> {
+        Ok(Self::new(zstd::dict::from_samples(samples, max_size)?))
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub(crate) fn compress(&self, bytes: &[u8], level: i32) -> Result<Vec<u8>, AssetArchiveError> {
+        Ok(zstd::bulk::Compressor::with_dictionary(level, &self.bytes)?.compress(bytes)?)
+    }
+
+    pub(crate) fn decompress_into(
+        &self,
+        stored: &[u8],
+        uncompressed_size: usize,
+        buffer: &mut Vec<u8>,
+    ) -> Result<(), AssetArchiveError> {
+        buffer.resize(uncompressed_size, 0);
+        zstd::bulk::Decompressor::with_prepared_dictionary(&self.decoder)?
+            .decompress_to_buffer(stored, buffer)?;
+        Ok(())
+    }
+}
+
+impl std::fmt::Debug for AssetArchiveDictionary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "AssetArchiveDictionary({} bytes)", self.bytes.len())
+    }
+}
+
+impl From<serde_bytes::ByteBuf> for AssetArchiveDictionary {
+    fn from(bytes: serde_bytes::ByteBuf) -> Self {
+        Self::new(bytes.into_vec())
+    }
+}
+
+impl From<AssetArchiveDictionary> for serde_bytes::ByteBuf {
+    fn from(dictionary: AssetArchiveDictionary) -> Self {
+        serde_bytes::ByteBuf::from(dictionary.bytes.as_ref().clone())
+    }
+}
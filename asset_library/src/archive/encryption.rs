@@ -0,0 +1,141 @@
+use ::serde::*;
+use chacha20poly1305::{
+    aead::{Aead, AeadCore, KeyInit, OsRng, Payload},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use std::borrow::Cow;
+use uuid::Uuid;
+
+use super::{AssetArchiveError, AssetArchiveFileHeader};
+
+/// A 256 bit key used to encrypt the files of an archive.
+#[derive(Clone, PartialEq, Eq)]
+pub struct AssetArchiveKey([u8; 32]);
+
+impl AssetArchiveKey {
+    pub const SIZE: usize = 32;
+
+    pub fn new(bytes: [u8; 32]) -> Self {
+        Self(bytes)
+    }
+
+    /// Generates a random key.
+    pub fn generate() -> Self {
+        Self(ChaCha20Poly1305::generate_key(&mut OsRng).into())
+    }
+
+    pub fn bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+}
+
+impl std::fmt::Debug for AssetArchiveKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Keys must not end up in logs.
+        write!(f, "AssetArchiveKey(..)")
+    }
+}
+
+/// Supplies the keys of encrypted archives by archive UUID, see `AssetArchiveHeader::uuid`.
+pub trait KeyProvider: Send + Sync {
+    /// Returns the key of the archive, or `None` if the archive is not encrypted or its key is unknown.
+    fn key(&self, archive: &Uuid) -> Option<AssetArchiveKey>;
+}
+
+impl<F: Fn(&Uuid) -> Option<AssetArchiveKey> + Send + Sync> KeyProvider for F {
+    fn key(&self, archive: &Uuid) -> Option<AssetArchiveKey> {
+        self(archive)
+    }
+}
+
+/// Provides no keys, for loading archives which are not encrypted.
+pub struct NoKeys;
+
+impl KeyProvider for NoKeys {
+    fn key(&self, _archive: &Uuid) -> Option<AssetArchiveKey> {
+        None
+    }
+}
+
+/// Encryption of a stored file, which is applied after compression.
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum AssetArchiveEncryption {
+    #[default]
+    None,
+    /// Encrypted and authenticated with ChaCha20-Poly1305, using a random nonce per file.
+    /// The mount point, its version, the identifier and the format are authenticated as well.
+    /// A file can not be swapped for another file, the same file of another mount point
+    /// or the same file of an older version of the mount point encrypted with the same key.
+    ChaCha20Poly1305 { nonce: [u8; 12] },
+}
+
+/// Data which is authenticated together with the file contents: the mount point, its version,
+/// the identifier and the format of the file.
+fn associated_data(mount_point: &str, version: u64, identifier: &str, format: &str) -> Vec<u8> {
+    let mut data = Vec::with_capacity(mount_point.len() + identifier.len() + format.len() + 10);
+    data.extend_from_slice(mount_point.as_bytes());
+    data.push(0);
+    data.extend_from_slice(&version.to_le_bytes());
+    data.extend_from_slice(identifier.as_bytes());
+    data.push(0);
+    data.extend_from_slice(format.as_bytes());
+    data
+}
+
+pub(crate) fn encrypt(
+    key: &AssetArchiveKey,
+    mount_point: &str,
+    version: u64,
+    identifier: &str,
+    format: &str,
+    bytes: &[u8],
+) -> Result<(Vec<u8>, AssetArchiveEncryption), AssetArchiveError> {
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(key.bytes()));
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let encrypted = cipher
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: bytes,
+                aad: &associated_data(mount_point, version, identifier, format),
+            },
+        )
+        .map_err(|_| AssetArchiveError::EncryptionFailed)?;
+    Ok((
+        encrypted,
+        AssetArchiveEncryption::ChaCha20Poly1305 {
+            nonce: nonce.into(),
+        },
+    ))
+}
+
+/// Decrypts and authenticates the stored bytes of a file, unencrypted files are returned as they are.
+pub(crate) fn decrypt<'a>(
+    key: Option<&AssetArchiveKey>,
+    header: &AssetArchiveFileHeader,
+    stored: Cow<'a, [u8]>,
+) -> Result<Cow<'a, [u8]>, AssetArchiveError> {
+    match header.encryption() {
+        AssetArchiveEncryption::None => Ok(stored),
+        AssetArchiveEncryption::ChaCha20Poly1305 { nonce } => {
+            let key = key.ok_or(AssetArchiveError::MissingKey)?;
+            let (mount_point, version) = header.mount();
+            let aad = associated_data(
+                mount_point,
+                version,
+                header.asset_identifier(),
+                header.asset_format(),
+            );
+            ChaCha20Poly1305::new(Key::from_slice(key.bytes()))
+                .decrypt(
+                    Nonce::from_slice(nonce),
+                    Payload {
+                        msg: &stored,
+                        aad: &aad,
+                    },
+                )
+                .map(Cow::Owned)
+                .map_err(|_| AssetArchiveError::DecryptionFailed)
+        }
+    }
+}
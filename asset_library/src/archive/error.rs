@@ -8,6 +8,18 @@ pub enum AssetArchiveError {
     Decompress(DecompressError),
     DeserializeError(serde_cbor::Error),
     InvalidMountPoint,
+    InvalidFileRange,
+    ChecksumMismatch,
+    InvalidVersion,
+    EncryptionFailed,
+    /// The file is encrypted, but the key of the archive was not provided.
+    MissingKey,
+    /// The file could not be authenticated, either the key is wrong or the file was modified.
+    DecryptionFailed,
+    /// The file is compressed with a dictionary, but its mount point has none.
+    MissingDictionary,
+    /// The archive was written with another layout version, see `ASSET_ARCHIVE_VERSION`.
+    UnsupportedVersion(u32),
 }
 
 impl Display for AssetArchiveError {
@@ -18,6 +30,21 @@ impl Display for AssetArchiveError {
             Self::Decompress(e) => write!(f, "{}", e),
             Self::DeserializeError(e) => write!(f, "{}", e),
             Self::InvalidMountPoint => write!(f, "Invalid mount point."),
+            Self::InvalidFileRange => write!(f, "File lies outside of the archive."),
+            Self::ChecksumMismatch => write!(f, "Checksum mismatch, the archive is corrupt."),
+            Self::InvalidVersion => write!(f, "Invalid mount point version."),
+            Self::EncryptionFailed => write!(f, "File could not be encrypted."),
+            Self::MissingKey => write!(f, "File is encrypted, but no key was provided."),
+            Self::DecryptionFailed => write!(
+                f,
+                "File could not be decrypted, the key is wrong or the archive is corrupt."
+            ),
+            Self::MissingDictionary => write!(f, "Compression dictionary is missing."),
+            Self::UnsupportedVersion(version) => write!(
+                f,
+                "Archive version {} is not supported, the archive needs to be rebuilt.",
+                version
+            ),
         }
     }
 }
@@ -44,3 +71,11 @@ impl From<serde_cbor::Error> for AssetArchiveError {
         Self::DeserializeError(err)
     }
 }
+
+/// A file which failed verification, see `AssetArchive::verify`.
+#[derive(Debug)]
+pub struct AssetArchiveCorruptFile {
+    pub mount_point: String,
+    pub identifier: String,
+    pub error: AssetArchiveError,
+}
@@ -1,6 +1,9 @@
 use ::serde::*;
 use uuid::*;
 
+use super::{AssetArchiveDictionary, AssetArchiveEncryption};
+use crate::AssetReference;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AssetArchiveHeader {
     uuid: Uuid,
@@ -9,16 +12,53 @@ pub struct AssetArchiveHeader {
 
 impl AssetArchiveHeader {
     pub fn new(mount_points: Vec<AssetArchiveMountPointHeader>) -> Self {
-        Self {
-            mount_points,
-            uuid: Uuid::new_v4(),
-        }
+        Self::with_uuid(Uuid::new_v4(), mount_points)
+    }
+
+    pub fn with_uuid(uuid: Uuid, mount_points: Vec<AssetArchiveMountPointHeader>) -> Self {
+        Self { uuid, mount_points }
+    }
+
+    /// Get a reference to the asset archive header's uuid.
+    pub fn uuid(&self) -> &Uuid {
+        &self.uuid
     }
 
     /// Get a reference to the asset archive header's mount points.
     pub fn mount_points(&self) -> &[AssetArchiveMountPointHeader] {
         self.mount_points.as_slice()
     }
+
+    pub(crate) fn set_uuid(&mut self, uuid: Uuid) {
+        self.uuid = uuid;
+    }
+
+    /// Sorts mount points and their files by name, so archives with equal contents serialize equally.
+    pub(crate) fn sort(&mut self) {
+        self.mount_points
+            .sort_by(|a, b| a.mount_point.cmp(&b.mount_point));
+        for mount_point in self.mount_points.iter_mut() {
+            mount_point
+                .assets
+                .sort_by(|a, b| a.asset_identifier.cmp(&b.asset_identifier));
+            mount_point.removed_assets.sort();
+        }
+    }
+
+    /// Hands the name, version and dictionary of each mount point to its files,
+    /// so they can be decrypted and decompressed on their own.
+    pub(crate) fn attach_mount_points(&mut self) {
+        for mount_point in self.mount_points.iter_mut() {
+            let dictionary = &mount_point.dictionary;
+            for asset in mount_point.assets.iter_mut() {
+                asset.mount_point = mount_point.mount_point.clone();
+                asset.mount_version = mount_point.version;
+                if asset.compression_format == AssetArchiveCompressionFormat::ZSTDDictionary {
+                    asset.dictionary = dictionary.clone();
+                }
+            }
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -26,17 +66,35 @@ pub struct AssetArchiveMountPointHeader {
     version: u64,
     mount_point: String,
     assets: Vec<AssetArchiveFileHeader>,
+    /// Identifiers which are deleted by this mount point, hiding them in lower versions of the mount point.
+    #[serde(default)]
+    removed_assets: Vec<String>,
+    /// Dictionary used by files compressed with `ZSTDDictionary`.
+    #[serde(default)]
+    dictionary: Option<AssetArchiveDictionary>,
 }
 
 impl AssetArchiveMountPointHeader {
-    pub fn new(version: u64, mount_point: String, assets: Vec<AssetArchiveFileHeader>) -> Self {
+    pub fn new(
+        version: u64,
+        mount_point: String,
+        assets: Vec<AssetArchiveFileHeader>,
+        removed_assets: Vec<String>,
+    ) -> Self {
         Self {
             version,
             mount_point: mount_point.to_lowercase(),
             assets,
+            removed_assets: removed_assets.iter().map(|e| e.to_lowercase()).collect(),
+            dictionary: None,
         }
     }
 
+    pub fn with_dictionary(mut self, dictionary: Option<AssetArchiveDictionary>) -> Self {
+        self.dictionary = dictionary;
+        self
+    }
+
     /// Get a reference to the asset archive mount point header's version.
     pub fn version(&self) -> &u64 {
         &self.version
@@ -51,6 +109,16 @@ impl AssetArchiveMountPointHeader {
     pub fn assets(&self) -> &[AssetArchiveFileHeader] {
         self.assets.as_slice()
     }
+
+    /// Get a reference to the asset archive mount point header's removed assets.
+    pub fn removed_assets(&self) -> &[String] {
+        self.removed_assets.as_slice()
+    }
+
+    /// Get a reference to the asset archive mount point header's dictionary.
+    pub fn dictionary(&self) -> Option<&AssetArchiveDictionary> {
+        self.dictionary.as_ref()
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -60,7 +128,23 @@ pub struct AssetArchiveFileHeader {
     offset: u64,
     compressed_size: u64,
     uncompressed_size: u64,
+    /// Hash of the stored (possibly compressed) bytes. (Uses xxh3_64)
+    compressed_hash: u64,
     compression_format: AssetArchiveCompressionFormat,
+    /// Assets which are required to use this asset.
+    #[serde(default)]
+    dependencies: Vec<AssetReference>,
+    #[serde(default)]
+    encryption: AssetArchiveEncryption,
+    /// The dictionary of the mount point, attached when the archive is read.
+    #[serde(skip)]
+    dictionary: Option<AssetArchiveDictionary>,
+    /// The mount point listing the file, attached when the archive is read.
+    #[serde(skip)]
+    mount_point: String,
+    /// The version of the mount point listing the file, attached when the archive is read.
+    #[serde(skip)]
+    mount_version: u64,
 }
 
 impl AssetArchiveFileHeader {
@@ -70,6 +154,7 @@ impl AssetArchiveFileHeader {
         offset: u64,
         compressed_size: u64,
         uncompressed_size: u64,
+        compressed_hash: u64,
         compression_format: AssetArchiveCompressionFormat,
     ) -> Self {
         Self {
@@ -78,10 +163,26 @@ impl AssetArchiveFileHeader {
             offset,
             compressed_size,
             uncompressed_size,
+            compressed_hash,
             compression_format,
+            dependencies: vec![],
+            encryption: AssetArchiveEncryption::None,
+            dictionary: None,
+            mount_point: String::new(),
+            mount_version: 0,
         }
     }
 
+    pub fn with_encryption(mut self, encryption: AssetArchiveEncryption) -> Self {
+        self.encryption = encryption;
+        self
+    }
+
+    pub fn with_dependencies(mut self, dependencies: Vec<AssetReference>) -> Self {
+        self.dependencies = dependencies;
+        self
+    }
+
     /// Get a reference to the asset archive file header's offset.
     pub fn offset(&self) -> &u64 {
         &self.offset
@@ -97,6 +198,11 @@ impl AssetArchiveFileHeader {
         &self.uncompressed_size
     }
 
+    /// Get a reference to the asset archive file header's compressed hash.
+    pub fn compressed_hash(&self) -> &u64 {
+        &self.compressed_hash
+    }
+
     /// Get a reference to the asset archive file header's compression format.
     pub fn compression_format(&self) -> &AssetArchiveCompressionFormat {
         &self.compression_format
@@ -111,6 +217,25 @@ impl AssetArchiveFileHeader {
     pub fn asset_format(&self) -> &str {
         self.asset_format.as_str()
     }
+
+    /// Get a reference to the asset archive file header's dependencies.
+    pub fn dependencies(&self) -> &[AssetReference] {
+        self.dependencies.as_slice()
+    }
+
+    /// Get a reference to the asset archive file header's encryption.
+    pub fn encryption(&self) -> &AssetArchiveEncryption {
+        &self.encryption
+    }
+
+    /// Returns the dictionary required to decompress the file, if it was compressed with `ZSTDDictionary`.
+    pub fn dictionary(&self) -> Option<&AssetArchiveDictionary> {
+        self.dictionary.as_ref()
+    }
+    /// Returns the mount point and version the file was read from, they are authenticated if the file is encrypted.
+    pub(crate) fn mount(&self) -> (&str, u64) {
+        (&self.mount_point, self.mount_version)
+    }
 }
 
 #[repr(u8)]
@@ -119,4 +244,9 @@ pub enum AssetArchiveCompressionFormat {
     None = 0,
     LZ4 = 1,
     ZSTD = 2,
+    /// ZSTD using the dictionary of the mount point, see `AssetArchiveMountPointBuilder::train_dictionary`.
+    ZSTDDictionary = 3,
+    /// LZ4 compressed in chunks of `LZ4_CHUNK_SIZE` bytes, streams of these files seek without decompressing
+    /// the whole file. See `AssetArchiveReader::open_file`.
+    LZ4Chunked = 4,
 }
@@ -2,60 +2,192 @@ use std::{
     fs::*,
     io::{BufReader, Read, Seek, SeekFrom},
     path::{Path, PathBuf},
+    sync::Arc,
 };
 
 pub mod builder;
+pub mod dictionary;
+pub mod encryption;
 pub mod error;
 pub mod header;
+pub mod reader;
+pub mod stream;
 
 pub use builder::*;
+pub use dictionary::*;
+pub use encryption::*;
 pub use error::*;
 pub use header::*;
-use lz4_flex::decompress_into;
+pub use reader::*;
+pub use stream::*;
+use xxhash_rust::xxh3::xxh3_64;
+
+/// The last bytes of every asset archive.
+pub const ASSET_ARCHIVE_MAGIC: [u8; 4] = *b"HARC";
+/// Version of the archive layout, stored in the trailer in front of the magic value.
+pub const ASSET_ARCHIVE_VERSION: u32 = 2;
+/// Size of the trailer at the end of an archive, including the magic value.
+pub(crate) const ASSET_ARCHIVE_TRAILER_SIZE: u64 = 32;
+const ZSTD_FRAME_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
 
 // AssetArchive is a type storing multiple potentially compressed assets into a single archive.
 #[derive(Debug)]
 pub struct AssetArchive {
     header: AssetArchiveHeader,
-    path: PathBuf,
+    reader: Arc<AssetArchiveReader>,
 }
 
 impl AssetArchive {
     /// Reads an asset archive from a file. Only succeeds in case the provided file can be interpreted as an archive.
+    /// The file is memory mapped and stays mapped for as long as the archive or one of its readers is alive.
     pub fn read_from_file<P: AsRef<Path>>(path: P) -> Result<AssetArchive, AssetArchiveError> {
-        let file = std::fs::File::open(path.as_ref())?;
-        let reader = std::io::BufReader::new(&file);
-        let header = Self::read_header(reader)?;
+        Self::from_reader(AssetArchiveReader::open(path.as_ref())?)
+    }
+
+    /// Reads an asset archive embedded into the binary, e.g. using `include_bytes!`.
+    pub fn read_from_static(bytes: &'static [u8]) -> Result<AssetArchive, AssetArchiveError> {
+        Self::from_reader(AssetArchiveReader::from_static(bytes))
+    }
+
+    /// Reads an asset archive from any `Read + Seek` source, see `AssetArchiveReader::from_reader_range`
+    /// for archives stored inside of a larger file.
+    pub fn read_from_reader(
+        source: impl AssetArchiveSource,
+    ) -> Result<AssetArchive, AssetArchiveError> {
+        Self::from_reader(AssetArchiveReader::from_reader(source)?)
+    }
+
+    /// Reads the header of the archive provided by `reader`.
+    /// Checksum verification and keys of the reader are kept.
+    pub fn from_reader(reader: AssetArchiveReader) -> Result<AssetArchive, AssetArchiveError> {
+        let header = Self::read_header(&reader)?;
         Ok(Self {
             header,
-            path: PathBuf::from(path.as_ref()),
+            reader: Arc::new(reader),
         })
     }
 
-    fn read_header(
-        mut reader: std::io::BufReader<&std::fs::File>,
-    ) -> Result<AssetArchiveHeader, AssetArchiveError> {
-        // last 8 bytes are compressed header size in LE byte order
-        let mut compressed_header_size: [u8; 8] = [0; 8];
-        reader.seek(SeekFrom::End(-8))?;
-        reader.read_exact(&mut compressed_header_size)?;
-        let compressed_header_size = u64::from_le_bytes(compressed_header_size);
-        // second 8 last bytes are uncompressed header size in LE byte order
-        let mut uncompressed_header_size: [u8; 8] = [0; 8];
-        reader.seek(SeekFrom::End(-16))?;
-        reader.read_exact(&mut uncompressed_header_size)?;
-        let uncompressed_header_size = u64::from_le_bytes(uncompressed_header_size);
-        // The next `compressed_header_size` bytes are the bytes of the CBOR encoded header.
-        let mut compressed_header = Vec::with_capacity(compressed_header_size as usize);
-        unsafe { compressed_header.set_len(compressed_header_size as usize) };
-        reader.seek(SeekFrom::End(-16 - compressed_header_size as i64))?;
-        reader.read_exact(&mut compressed_header)?;
+    /// Reads an asset archive from a file and verifies the checksum of every file that is read from it.
+    pub fn read_from_file_verified<P: AsRef<Path>>(
+        path: P,
+    ) -> Result<AssetArchive, AssetArchiveError> {
+        Self::read_from_file_with_options(path, &NoKeys, true)
+    }
+
+    /// Reads an asset archive from a file, encrypted files are decrypted using the key of the archive's UUID.
+    pub fn read_from_file_with_keys<P: AsRef<Path>>(
+        path: P,
+        keys: &dyn KeyProvider,
+    ) -> Result<AssetArchive, AssetArchiveError> {
+        Self::read_from_file_with_options(path, keys, false)
+    }
+
+    /// Reads an asset archive from a file, see `read_from_file_with_keys` and `read_from_file_verified`.
+    pub fn read_from_file_with_options<P: AsRef<Path>>(
+        path: P,
+        keys: &dyn KeyProvider,
+        verify_checksums: bool,
+    ) -> Result<AssetArchive, AssetArchiveError> {
+        let mut reader = AssetArchiveReader::open(path.as_ref())?;
+        reader.set_verify_checksums(verify_checksums);
+        let mut archive = Self::from_reader(reader)?;
+        let key = keys.key(archive.header.uuid());
+        if let Some(reader) = Arc::get_mut(&mut archive.reader) {
+            reader.set_key(key);
+        }
+        Ok(archive)
+    }
+
+    fn read_header(reader: &AssetArchiveReader) -> Result<AssetArchiveHeader, AssetArchiveError> {
+        // The archive ends with a trailer of three u64 values and a u32 in LE byte order, followed by the magic value:
+        // the uncompressed header size, the xxh3 hash of the compressed header, the compressed header size
+        // and the version of the archive layout.
+        let archive_size = reader.len();
+        if archive_size < ASSET_ARCHIVE_TRAILER_SIZE
+            || *reader.read_range(archive_size - 4, 4)? != ASSET_ARCHIVE_MAGIC
+        {
+            return match Self::is_legacy_archive(reader)? {
+                true => Err(AssetArchiveError::UnsupportedVersion(1)),
+                false => Err(AssetArchiveError::InvalidFileRange),
+            };
+        }
+        let trailer_offset = archive_size - ASSET_ARCHIVE_TRAILER_SIZE;
+        let trailer = reader.read_range(trailer_offset, ASSET_ARCHIVE_TRAILER_SIZE)?;
+        let read_u64 = |index: usize| {
+            let mut bytes: [u8; 8] = [0; 8];
+            bytes.copy_from_slice(&trailer[index * 8..(index + 1) * 8]);
+            u64::from_le_bytes(bytes)
+        };
+        let mut version: [u8; 4] = [0; 4];
+        version.copy_from_slice(&trailer[24..28]);
+        let version = u32::from_le_bytes(version);
+        if version != ASSET_ARCHIVE_VERSION {
+            return Err(AssetArchiveError::UnsupportedVersion(version));
+        }
+        let uncompressed_header_size = read_u64(0);
+        let header_hash = read_u64(1);
+        let compressed_header_size = read_u64(2);
+        // Guards against allocating huge buffers for files which are not archives.
+        if compressed_header_size > trailer_offset {
+            return Err(AssetArchiveError::InvalidFileRange);
+        }
+        // The `compressed_header_size` bytes in front of the trailer are the bytes of the CBOR encoded header.
+        let compressed_header = reader.read_range(
+            trailer_offset - compressed_header_size,
+            compressed_header_size,
+        )?;
+        if xxh3_64(&compressed_header) != header_hash {
+            return Err(AssetArchiveError::ChecksumMismatch);
+        }
         let uncompressed_header =
             zstd::bulk::decompress(&compressed_header, uncompressed_header_size as usize)?;
-        let header = serde_cbor::from_slice::<AssetArchiveHeader>(&uncompressed_header)?;
+        let mut header = serde_cbor::from_slice::<AssetArchiveHeader>(&uncompressed_header)?;
+        header.attach_mount_points();
         Ok(header)
     }
 
+    /// Archives of version 1 end with the uncompressed and the compressed header size, without a magic value.
+    /// They are detected by the zstd frame the compressed header starts with.
+    fn is_legacy_archive(reader: &AssetArchiveReader) -> Result<bool, AssetArchiveError> {
+        let archive_size = reader.len();
+        if archive_size < 16 {
+            return Ok(false);
+        }
+        let mut compressed_header_size: [u8; 8] = [0; 8];
+        compressed_header_size.copy_from_slice(&reader.read_range(archive_size - 8, 8)?);
+        let compressed_header_size = u64::from_le_bytes(compressed_header_size);
+        if compressed_header_size < 4 || compressed_header_size > archive_size - 16 {
+            return Ok(false);
+        }
+        let frame = reader.read_range(archive_size - 16 - compressed_header_size, 4)?;
+        Ok(*frame == ZSTD_FRAME_MAGIC)
+    }
+
+    /// Checks the checksum of every file in the archive.
+    /// Returns all files which are corrupt, instead of stopping at the first one.
+    pub fn verify(&self) -> Result<(), Vec<AssetArchiveCorruptFile>> {
+        let corrupt_files = self
+            .header
+            .mount_points()
+            .iter()
+            .flat_map(|m| m.assets().iter().map(move |a| (m, a)))
+            .filter_map(
+                |(mount_point, asset)| match self.reader.verify_file(asset) {
+                    Ok(()) => None,
+                    Err(error) => Some(AssetArchiveCorruptFile {
+                        mount_point: mount_point.mount_point().to_string(),
+                        identifier: asset.asset_identifier().to_string(),
+                        error,
+                    }),
+                },
+            )
+            .collect::<Vec<_>>();
+        match corrupt_files.is_empty() {
+            true => Ok(()),
+            false => Err(corrupt_files),
+        }
+    }
+
     /// Get a reference to the asset archive's header.
     pub fn header(&self) -> &AssetArchiveHeader {
         &self.header
@@ -77,81 +209,82 @@ impl AssetArchive {
         header: &AssetArchiveFileHeader,
     ) -> Result<Vec<u8>, AssetArchiveError> {
         let mut buf = Vec::new();
-        Self::read_file_into(&self.path, header, &mut buf)?;
+        self.reader.read_file_into(header, &mut buf)?;
         Ok(buf)
     }
 
+    /// Get a reference to the asset archive's reader.
+    pub fn reader(&self) -> &Arc<AssetArchiveReader> {
+        &self.reader
+    }
+
+    // Reads a blob from the archive at the provided path. Opens the file on every call,
+    // use `reader` instead when reading multiple files from the same archive.
+    // Encrypted files can only be read using the archive's reader.
     pub fn read_file_into(
         path: impl AsRef<Path>,
         header: &AssetArchiveFileHeader,
-        mut buffer: &mut Vec<u8>,
+        buffer: &mut Vec<u8>,
+    ) -> Result<(), AssetArchiveError> {
+        Self::read_file_into_with_options(path, header, false, buffer)
+    }
+
+    /// Like `read_file_into`, checks the file against its checksum before decompressing it if `verify_checksums` is set.
+    pub fn read_file_into_with_options(
+        path: impl AsRef<Path>,
+        header: &AssetArchiveFileHeader,
+        verify_checksums: bool,
+        buffer: &mut Vec<u8>,
     ) -> Result<(), AssetArchiveError> {
+        if *header.encryption() != AssetArchiveEncryption::None {
+            return Err(AssetArchiveError::MissingKey);
+        }
         let file = File::open(path.as_ref())?;
         let mut reader = BufReader::new(file);
         reader.seek(SeekFrom::Start(*header.offset()))?;
 
+        let mut stored = vec![0; *header.compressed_size() as usize];
+        reader.read_exact(&mut stored)?;
+        if verify_checksums && xxh3_64(&stored) != *header.compressed_hash() {
+            return Err(AssetArchiveError::ChecksumMismatch);
+        }
+        Self::decompress_into(header, &stored, buffer)
+    }
+
+    /// Decompresses the stored bytes of a file into the provided buffer.
+    pub fn decompress_into(
+        header: &AssetArchiveFileHeader,
+        stored: &[u8],
+        buffer: &mut Vec<u8>,
+    ) -> Result<(), AssetArchiveError> {
         match header.compression_format() {
             AssetArchiveCompressionFormat::None => {
-                buffer.resize(*header.compressed_size() as usize, 0);
-                reader.read_exact(&mut buffer)?;
-                Ok(())
+                buffer.clear();
+                buffer.extend_from_slice(stored);
             }
             AssetArchiveCompressionFormat::LZ4 => {
-                let mut temp_buffer = Vec::with_capacity(*header.compressed_size() as usize);
-                unsafe { temp_buffer.set_len(*header.compressed_size() as usize) };
-                reader.read_exact(&mut temp_buffer)?;
                 buffer.resize(*header.uncompressed_size() as usize, 0);
-                lz4_flex::decompress_into(&temp_buffer, &mut buffer)?;
-                Ok(())
+                lz4_flex::decompress_into(stored, buffer)?;
             }
             AssetArchiveCompressionFormat::ZSTD => {
-                let mut temp_buffer = Vec::with_capacity(*header.compressed_size() as usize);
-                unsafe { temp_buffer.set_len(*header.compressed_size() as usize) };
-                reader.read_exact(&mut temp_buffer)?;
                 buffer.resize(*header.uncompressed_size() as usize, 0);
-                zstd::bulk::decompress_to_buffer(&temp_buffer, &mut buffer)?;
-                Ok(())
+                zstd::bulk::decompress_to_buffer(stored, buffer)?;
+            }
+            AssetArchiveCompressionFormat::ZSTDDictionary => {
+                let dictionary = header
+                    .dictionary()
+                    .ok_or(AssetArchiveError::MissingDictionary)?;
+                dictionary.decompress_into(stored, *header.uncompressed_size() as usize, buffer)?;
+            }
+            AssetArchiveCompressionFormat::LZ4Chunked => {
+                decompress_lz4_chunked_into(stored, *header.uncompressed_size(), buffer)?;
             }
         }
+        Ok(())
     }
 
-    // pub async fn async_read_file_into(
-    //     path: impl AsRef<Path>,
-    //     header: &AssetArchiveFileHeader,
-    //     mut buffer: &mut Vec<u8>,
-    // ) -> Result<(), AssetArchiveError> {
-    //     use utils::smol::{fs::*, io::*};
-    //     let file = File::open(path.as_ref()).await?;
-    //     let mut reader = BufReader::new(file);
-    //     reader.seek(SeekFrom::Start(*header.offset())).await?;
-
-    //     match header.compression_format() {
-    //         AssetArchiveCompressionFormat::None => {
-    //             buffer.resize(*header.compressed_size() as usize, 0);
-    //             reader.read_exact(&mut buffer).await?;
-    //             Ok(())
-    //         }
-    //         AssetArchiveCompressionFormat::LZ4 => {
-    //             let mut temp_buffer = Vec::with_capacity(*header.compressed_size() as usize);
-    //             unsafe { temp_buffer.set_len(*header.compressed_size() as usize) };
-    //             reader.read_exact(&mut temp_buffer).await?;
-    //             buffer.resize(*header.uncompressed_size() as usize, 0);
-    //             decompress_into(&temp_buffer, &mut buffer)?;
-    //             Ok(())
-    //         }
-    //         AssetArchiveCompressionFormat::ZSTD => {
-    //             let mut temp_buffer = Vec::with_capacity(*header.compressed_size() as usize);
-    //             unsafe { temp_buffer.set_len(*header.compressed_size() as usize) };
-    //             reader.read_exact(&mut temp_buffer).await?;
-    //             buffer.resize(*header.uncompressed_size() as usize, 0);
-    //             zstd::bulk::decompress_to_buffer(&temp_buffer, &mut buffer)?;
-    //             Ok(())
-    //         }
-    //     }
-    // }
-
-    /// Get a reference to the asset archive's path.
-    pub fn path(&self) -> &PathBuf {
-        &self.path
+    /// Get a reference to the asset archive's path, or `None` if the archive is not read from a file.
+    pub fn path(&self) -> Option<&Path> {
+        self.reader.path()
     }
 }
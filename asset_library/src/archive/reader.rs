@@ -0,0 +1,212 @@
+use super::*;
+use memmap2::Mmap;
+use std::{borrow::Cow, sync::Mutex};
+use xxhash_rust::xxh3::xxh3_64;
+
+/// A `Read + Seek` source an archive can be read from, see `AssetArchiveReader::from_reader`.
+pub trait AssetArchiveSource: Read + Seek + Send + 'static {}
+
+impl<T: Read + Seek + Send + 'static> AssetArchiveSource for T {}
+
+enum ArchiveBytes {
+    Mapped(Mmap),
+    Static(&'static [u8]),
+    /// Reads are serialized, the source is seeked for every read.
+    Stream {
+        source: Mutex<Box<dyn AssetArchiveSource>>,
+        start: u64,
+        len: u64,
+    },
+}
+
+impl std::fmt::Debug for ArchiveBytes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Mapped(mmap) => write!(f, "Mapped({} bytes)", mmap.len()),
+            Self::Static(bytes) => write!(f, "Static({} bytes)", bytes.len()),
+            Self::Stream { start, len, .. } => write!(f, "Stream({}..{})", start, start + len),
+        }
+    }
+}
+
+/// Reads the files of an archive.
+/// Archive files are memory mapped, archives can also be read from static bytes or any `Read + Seek` source.
+/// The reader can be shared between threads, reads of memory mapped and static archives never block each other.
+#[derive(Debug)]
+pub struct AssetArchiveReader {
+    path: Option<PathBuf>,
+    bytes: ArchiveBytes,
+    verify_checksums: bool,
+    key: Option<AssetArchiveKey>,
+}
+
+impl AssetArchiveReader {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, AssetArchiveError> {
+        let file = File::open(path.as_ref())?;
+        // Safety: archives are treated as immutable while they are mounted.
+        // Modifying an archive file on disk while it is mapped is not supported.
+        let mmap = unsafe { Mmap::map(&file)? };
+        Ok(Self::new(
+            Some(path.as_ref().to_path_buf()),
+            ArchiveBytes::Mapped(mmap),
+        ))
+    }
+
+    /// Reads an archive embedded into the binary, e.g. using `include_bytes!`.
+    pub fn from_static(bytes: &'static [u8]) -> Self {
+        Self::new(None, ArchiveBytes::Static(bytes))
+    }
+
+    /// Reads an archive from a `Read + Seek` source, the archive spans the whole source.
+    pub fn from_reader(mut source: impl AssetArchiveSource) -> Result<Self, AssetArchiveError> {
+        let len = source.seek(SeekFrom::End(0))?;
+        Self::from_reader_range(source, 0, len)
+    }
+
+    /// Reads an archive which is stored in `len` bytes at `start` of a larger source.
+    pub fn from_reader_range(
+        source: impl AssetArchiveSource,
+        start: u64,
+        len: u64,
+    ) -> Result<Self, AssetArchiveError> {
+        start
+            .checked_add(len)
+            .ok_or(AssetArchiveError::InvalidFileRange)?;
+        Ok(Self::new(
+            None,
+            ArchiveBytes::Stream {
+                source: Mutex::new(Box::new(source)),
+                start,
+                len,
+            },
+        ))
+    }
+
+    fn new(path: Option<PathBuf>, bytes: ArchiveBytes) -> Self {
+        Self {
+            path,
+            bytes,
+            verify_checksums: false,
+            key: None,
+        }
+    }
+    /// Returns the path of the mapped archive file, or `None` if the archive is not read from a file.
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// If enabled, the checksum of every file is verified when it is read.
+    pub fn set_verify_checksums(&mut self, verify_checksums: bool) {
+        self.verify_checksums = verify_checksums;
+    }
+
+    pub fn verifies_checksums(&self) -> bool {
+        self.verify_checksums
+    }
+
+    /// Sets the key used to decrypt encrypted files.
+    pub fn set_key(&mut self, key: Option<AssetArchiveKey>) {
+        self.key = key;
+    }
+
+    pub fn has_key(&self) -> bool {
+        self.key.is_some()
+    }
+
+    /// Checks the stored bytes of a file against the checksum in its header.
+    pub fn verify_file(&self, header: &AssetArchiveFileHeader) -> Result<(), AssetArchiveError> {
+        match xxh3_64(&self.stored_file(header)?) == *header.compressed_hash() {
+            true => Ok(()),
+            false => Err(AssetArchiveError::ChecksumMismatch),
+        }
+    }
+
+    /// Returns the size of the archive in bytes.
+    pub fn len(&self) -> u64 {
+        match &self.bytes {
+            ArchiveBytes::Mapped(mmap) => mmap.len() as u64,
+            ArchiveBytes::Static(bytes) => bytes.len() as u64,
+            ArchiveBytes::Stream { len, .. } => *len,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns all bytes of the archive, or `None` if the archive is read from a stream.
+    pub fn bytes(&self) -> Option<&[u8]> {
+        match &self.bytes {
+            ArchiveBytes::Mapped(mmap) => Some(mmap),
+            ArchiveBytes::Static(bytes) => Some(bytes),
+            ArchiveBytes::Stream { .. } => None,
+        }
+    }
+
+    /// Reads `len` bytes at `offset`, without copying them if the archive is held in memory.
+    pub fn read_range(&self, offset: u64, len: u64) -> Result<Cow<'_, [u8]>, AssetArchiveError> {
+        let end = offset
+            .checked_add(len)
+            .filter(|end| *end <= self.len())
+            .ok_or(AssetArchiveError::InvalidFileRange)?;
+        match &self.bytes {
+            ArchiveBytes::Mapped(mmap) => Ok(Cow::Borrowed(&mmap[offset as usize..end as usize])),
+            ArchiveBytes::Static(bytes) => Ok(Cow::Borrowed(&bytes[offset as usize..end as usize])),
+            ArchiveBytes::Stream { source, start, .. } => {
+                let mut source = source
+                    .lock()
+                    .map_err(|e| std::io::Error::other(e.to_string()))?;
+                source.seek(SeekFrom::Start(start + offset))?;
+                let mut buffer = vec![0; len as usize];
+                source.read_exact(&mut buffer)?;
+                Ok(Cow::Owned(buffer))
+            }
+        }
+    }
+
+    /// Returns the stored (possibly compressed) bytes of a file, without copying them if the archive is held in memory.
+    pub fn stored_file(
+        &self,
+        header: &AssetArchiveFileHeader,
+    ) -> Result<Cow<'_, [u8]>, AssetArchiveError> {
+        self.read_range(*header.offset(), *header.compressed_size())
+    }
+
+    /// Returns the bytes of an uncompressed file without copying them.
+    /// Returns `None` if the file is compressed or encrypted, or the archive is read from a stream.
+    pub fn uncompressed_file(
+        &self,
+        header: &AssetArchiveFileHeader,
+    ) -> Result<Option<&[u8]>, AssetArchiveError> {
+        match (header.compression_format(), header.encryption()) {
+            (AssetArchiveCompressionFormat::None, AssetArchiveEncryption::None) => {
+                match self.stored_file(header)? {
+                    Cow::Borrowed(bytes) => Ok(Some(bytes)),
+                    Cow::Owned(_) => Ok(None),
+                }
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Returns the (possibly compressed) bytes of a file, decrypting them if the file is encrypted.
+    pub fn decrypted_file(
+        &self,
+        header: &AssetArchiveFileHeader,
+    ) -> Result<Cow<'_, [u8]>, AssetArchiveError> {
+        let stored = self.stored_file(header)?;
+        if self.verify_checksums && xxh3_64(&stored) != *header.compressed_hash() {
+            return Err(AssetArchiveError::ChecksumMismatch);
+        }
+        decrypt(self.key.as_ref(), header, stored)
+    }
+
+    /// Reads, decrypts and decompresses a file into the provided buffer.
+    pub fn read_file_into(
+        &self,
+        header: &AssetArchiveFileHeader,
+        buffer: &mut Vec<u8>,
+    ) -> Result<(), AssetArchiveError> {
+        AssetArchive::decompress_into(header, &self.decrypted_file(header)?, buffer)
+    }
+}
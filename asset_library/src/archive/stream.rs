@@ -0,0 +1,360 @@
+use super::*;
+use std::{
+    borrow::Cow,
+    io::{Cursor, Write},
+};
+use zstd::stream::read::Decoder;
+
+/// A file opened for streaming, see `AssetArchiveReader::open_file` and `VfsMountPoint::open_asset`.
+pub trait AssetStream: Read + Seek + Send {}
+
+impl<T: Read + Seek + Send> AssetStream for T {}
+
+/// Size of the uncompressed chunks of `LZ4Chunked` files.
+pub const LZ4_CHUNK_SIZE: u32 = 64 * 1024;
+
+/// Compresses `bytes` into independently compressed LZ4 blocks of `chunk_size` bytes.
+/// The stored file starts with the chunk size and chunk count, followed by the compressed size of every chunk
+/// and the compressed chunks. (All sizes are little endian `u32`)
+pub(crate) fn compress_lz4_chunked(bytes: &[u8], chunk_size: u32) -> Vec<u8> {
+    let chunks = bytes
+        .chunks(chunk_size as usize)
+        .map(lz4_flex::compress)
+        .collect::<Vec<_>>();
+    let mut stored =
+        Vec::with_capacity(8 + 4 * chunks.len() + chunks.iter().map(|c| c.len()).sum::<usize>());
+    stored.extend_from_slice(&chunk_size.to_le_bytes());
+    stored.extend_from_slice(&(chunks.len() as u32).to_le_bytes());
+    for chunk in chunks.iter() {
+        stored.extend_from_slice(&(chunk.len() as u32).to_le_bytes());
+    }
+    for chunk in chunks.iter() {
+        stored.extend_from_slice(chunk);
+    }
+    stored
+}
+
+/// Decompresses all chunks of an `LZ4Chunked` file into the provided buffer.
+pub(crate) fn decompress_lz4_chunked_into(
+    stored: &[u8],
+    uncompressed_size: u64,
+    buffer: &mut Vec<u8>,
+) -> Result<(), AssetArchiveError> {
+    let table = Lz4ChunkTable::parse(|offset, len| {
+        stored
+            .get(offset as usize..(offset + len) as usize)
+            .map(Cow::Borrowed)
+            .ok_or(AssetArchiveError::InvalidFileRange)
+    })?
+    .check(uncompressed_size, stored.len() as u64)?;
+    buffer.resize(uncompressed_size as usize, 0);
+    for (index, (offset, len)) in table.chunks.iter().enumerate() {
+        let range = table.uncompressed_range(index, uncompressed_size)?;
+        let chunk = stored
+            .get(*offset as usize..(offset + len) as usize)
+            .ok_or(AssetArchiveError::InvalidFileRange)?;
+        let decompressed = lz4_flex::decompress_into(
+            chunk,
+            &mut buffer[range.start as usize..range.end as usize],
+        )?;
+        if decompressed as u64 != range.end - range.start {
+            return Err(AssetArchiveError::InvalidFileRange);
+        }
+    }
+    Ok(())
+}
+
+/// Chunk size and the stored range of every chunk of an `LZ4Chunked` file.
+struct Lz4ChunkTable {
+    chunk_size: u64,
+    /// Offset relative to the start of the stored file and compressed size.
+    chunks: Vec<(u64, u64)>,
+}
+
+impl Lz4ChunkTable {
+    /// Parses the table, `read` returns `len` bytes at `offset` of the stored file.
+    fn parse<'a>(
+        read: impl Fn(u64, u64) -> Result<Cow<'a, [u8]>, AssetArchiveError>,
+    ) -> Result<Self, AssetArchiveError> {
+        let u32_at = |bytes: &[u8], i: usize| {
+            u32::from_le_bytes([bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]]) as u64
+        };
+        let head = read(0, 8)?;
+        let (chunk_size, count) = (u32_at(&head, 0), u32_at(&head, 4));
+        let sizes = read(8, 4 * count)?;
+        let mut offset = 8 + 4 * count;
+        let mut chunks = Vec::with_capacity(count as usize);
+        for i in 0..count as usize {
+            let len = u32_at(&sizes, 4 * i);
+            chunks.push((offset, len));
+            offset += len;
+        }
+        Ok(Self { chunk_size, chunks })
+    }
+
+    /// Checks that the chunks cover exactly `uncompressed_size` bytes and lie within the stored file.
+    fn check(self, uncompressed_size: u64, stored_size: u64) -> Result<Self, AssetArchiveError> {
+        let expected = match self.chunk_size {
+            0 => 0,
+            chunk_size => uncompressed_size.div_ceil(chunk_size),
+        };
+        let stored_end = self.chunks.last().map(|(o, l)| o + l).unwrap_or(0);
+        match self.chunks.len() as u64 == expected
+            && (expected > 0 || uncompressed_size == 0)
+            && stored_end <= stored_size
+        {
+            true => Ok(self),
+            false => Err(AssetArchiveError::InvalidFileRange),
+        }
+    }
+
+    fn uncompressed_range(
+        &self,
+        index: usize,
+        uncompressed_size: u64,
+    ) -> Result<std::ops::Range<u64>, AssetArchiveError> {
+        let start = index as u64 * self.chunk_size;
+        let end = (start + self.chunk_size).min(uncompressed_size);
+        match start < end {
+            true => Ok(start..end),
+            false => Err(AssetArchiveError::InvalidFileRange),
+        }
+    }
+}
+
+impl AssetArchiveReader {
+    /// Opens a file for streaming, without decompressing it as a whole.
+    /// Uncompressed files are read directly from the archive, `ZSTD` and `ZSTDDictionary` files are decompressed
+    /// while reading. Seeking backwards restarts their decompression, prefer `LZ4Chunked` for files which are
+    /// accessed randomly: only the chunk containing the position is decompressed.
+    /// `LZ4` and encrypted files are decompressed into memory when they are opened.
+    pub fn open_file(
+        self: &Arc<Self>,
+        header: &AssetArchiveFileHeader,
+    ) -> Result<Box<dyn AssetStream>, AssetArchiveError> {
+        if *header.encryption() != AssetArchiveEncryption::None {
+            let mut buffer = Vec::new();
+            self.read_file_into(header, &mut buffer)?;
+            return Ok(Box::new(Cursor::new(buffer)));
+        }
+        if self.verifies_checksums() {
+            self.verify_file(header)?;
+        }
+        let range = ArchiveRange {
+            reader: Arc::clone(self),
+            offset: *header.offset(),
+            len: *header.compressed_size(),
+            position: 0,
+        };
+        // Checks the stored range once, reads can then only fail if the source fails.
+        range
+            .offset
+            .checked_add(range.len)
+            .filter(|end| *end <= self.len())
+            .ok_or(AssetArchiveError::InvalidFileRange)?;
+
+        let len = *header.uncompressed_size();
+        match header.compression_format() {
+            AssetArchiveCompressionFormat::None => Ok(Box::new(range)),
+            AssetArchiveCompressionFormat::LZ4 => {
+                let mut buffer = Vec::new();
+                self.read_file_into(header, &mut buffer)?;
+                Ok(Box::new(Cursor::new(buffer)))
+            }
+            AssetArchiveCompressionFormat::ZSTD => Ok(Box::new(ZstdStream::new(range, None, len)?)),
+            AssetArchiveCompressionFormat::ZSTDDictionary => {
+                let dictionary = header
+                    .dictionary()
+                    .ok_or(AssetArchiveError::MissingDictionary)?;
+                Ok(Box::new(ZstdStream::new(
+                    range,
+                    Some(dictionary.clone()),
+                    len,
+                )?))
+            }
+            AssetArchiveCompressionFormat::LZ4Chunked => {
+                let table = Lz4ChunkTable::parse(|offset, len| {
+                    if offset + len > range.len {
+                        return Err(AssetArchiveError::InvalidFileRange);
+                    }
+                    self.read_range(range.offset + offset, len)
+                })?
+                .check(len, range.len)?;
+                Ok(Box::new(Lz4ChunkStream {
+                    range,
+                    table,
+                    len,
+                    loaded: None,
+                    chunk: Vec::new(),
+                    position: 0,
+                }))
+            }
+        }
+    }
+}
+
+fn seek_position(position: u64, len: u64, pos: SeekFrom) -> std::io::Result<u64> {
+    let target = match pos {
+        SeekFrom::Start(p) => Some(p),
+        SeekFrom::End(d) => len.checked_add_signed(d),
+        SeekFrom::Current(d) => position.checked_add_signed(d),
+    };
+    target.ok_or_else(|| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "Invalid seek to a negative or overflowing position.",
+        )
+    })
+}
+
+/// The stored bytes of a file.
+#[derive(Clone)]
+struct ArchiveRange {
+    reader: Arc<AssetArchiveReader>,
+    offset: u64,
+    len: u64,
+    position: u64,
+}
+
+impl Read for ArchiveRange {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = (buf.len() as u64).min(self.len.saturating_sub(self.position));
+        if n == 0 {
+            return Ok(0);
+        }
+        let bytes = self
+            .reader
+            .read_range(self.offset + self.position, n)
+            .map_err(std::io::Error::other)?;
+        buf[..n as usize].copy_from_slice(&bytes);
+        self.position += n;
+        Ok(n as usize)
+    }
+}
+
+impl Seek for ArchiveRange {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.position = seek_position(self.position, self.len, pos)?;
+        Ok(self.position)
+    }
+}
+
+struct ZstdStream {
+    range: ArchiveRange,
+    dictionary: Option<AssetArchiveDictionary>,
+    decoder: Decoder<'static, BufReader<ArchiveRange>>,
+    /// Uncompressed size of the file.
+    len: u64,
+    position: u64,
+}
+
+impl ZstdStream {
+    fn new(
+        range: ArchiveRange,
+        dictionary: Option<AssetArchiveDictionary>,
+        len: u64,
+    ) -> std::io::Result<Self> {
+        let decoder = Self::decoder(&range, dictionary.as_ref())?;
+        Ok(Self {
+            range,
+            dictionary,
+            decoder,
+            len,
+            position: 0,
+        })
+    }
+
+    fn decoder(
+        range: &ArchiveRange,
+        dictionary: Option<&AssetArchiveDictionary>,
+    ) -> std::io::Result<Decoder<'static, BufReader<ArchiveRange>>> {
+        let reader = BufReader::new(range.clone());
+        match dictionary {
+            Some(dictionary) => Decoder::with_dictionary(reader, dictionary.bytes()),
+            None => Decoder::with_buffer(reader),
+        }
+    }
+}
+
+impl Read for ZstdStream {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.position >= self.len {
+            return Ok(0);
+        }
+        let n = self.decoder.read(buf)?;
+        self.position += n as u64;
+        Ok(n)
+    }
+}
+
+impl Seek for ZstdStream {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let target = seek_position(self.position, self.len, pos)?;
+        if target < self.position {
+            self.decoder = Self::decoder(&self.range, self.dictionary.as_ref())?;
+            self.position = 0;
+        }
+        let skip = target.min(self.len).saturating_sub(self.position);
+        let skipped = std::io::copy(&mut (&mut self.decoder).take(skip), &mut std::io::sink())?;
+        if skipped != skip {
+            return Err(std::io::ErrorKind::UnexpectedEof.into());
+        }
+        self.position = target;
+        Ok(target)
+    }
+}
+
+struct Lz4ChunkStream {
+    range: ArchiveRange,
+    table: Lz4ChunkTable,
+    /// Uncompressed size of the file.
+    len: u64,
+    /// Index of the chunk held in `chunk`.
+    loaded: Option<usize>,
+    chunk: Vec<u8>,
+    position: u64,
+}
+
+impl Read for Lz4ChunkStream {
+    fn read(&mut self, mut buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.position >= self.len {
+            return Ok(0);
+        }
+        let index = (self.position / self.table.chunk_size) as usize;
+        if self.loaded != Some(index) {
+            let (offset, len) = *self
+                .table
+                .chunks
+                .get(index)
+                .ok_or(std::io::ErrorKind::UnexpectedEof)?;
+            let range = self
+                .table
+                .uncompressed_range(index, self.len)
+                .map_err(std::io::Error::other)?;
+            let stored = self
+                .range
+                .reader
+                .read_range(self.range.offset + offset, len)
+                .map_err(std::io::Error::other)?;
+            self.loaded = None;
+            self.chunk.resize((range.end - range.start) as usize, 0);
+            let decompressed = lz4_flex::decompress_into(&stored, &mut self.chunk)
+                .map_err(|e| std::io::Error::other(AssetArchiveError::from(e)))?;
+            if decompressed != self.chunk.len() {
+                return Err(std::io::Error::other(AssetArchiveError::InvalidFileRange));
+            }
+            self.loaded = Some(index);
+        }
+        let start = (self.position - index as u64 * self.table.chunk_size) as usize;
+        let n = buf.write(&self.chunk[start..])?;
+        self.position += n as u64;
+        Ok(n)
+    }
+}
+
+impl Seek for Lz4ChunkStream {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.position = seek_position(self.position, self.len, pos)?;
+        Ok(self.position)
+    }
+}
@@ -0,0 +1,406 @@
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    ops::Deref,
+    sync::{Arc, Condvar, Mutex, MutexGuard, Weak},
+};
+
+use serde::de::DeserializeOwned;
+
+use crate::{asset_system::*, vfs::base_identifier, *};
+
+/// A shared reference to a cached asset.
+/// The cache only evicts assets for which no handles exist anymore.
+pub struct AssetHandle<T> {
+    value: Arc<T>,
+}
+
+impl<T> AssetHandle<T> {
+    /// Creates a weak handle, which does not keep the asset alive.
+    pub fn downgrade(&self) -> WeakAssetHandle<T> {
+        WeakAssetHandle {
+            value: Arc::downgrade(&self.value),
+        }
+    }
+
+    /// Returns true if both handles refer to the same loaded asset.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.value, &other.value)
+    }
+}
+
+impl<T> Clone for AssetHandle<T> {
+    fn clone(&self) -> Self {
+        Self {
+            value: Arc::clone(&self.value),
+        }
+    }
+}
+
+impl<T> Deref for AssetHandle<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+pub struct WeakAssetHandle<T> {
+    value: Weak<T>,
+}
+
+impl<T> WeakAssetHandle<T> {
+    /// Returns a handle if the asset is still loaded.
+    pub fn upgrade(&self) -> Option<AssetHandle<T>> {
+        self.value.upgrade().map(|value| AssetHandle { value })
+    }
+}
+
+impl<T> Clone for WeakAssetHandle<T> {
+    fn clone(&self) -> Self {
+        Self {
+            value: Weak::clone(&self.value),
+        }
+    }
+}
+
+/// Counters of an asset kind, see `AssetCache::stats`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct AssetCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+}
+
+/// Assets are cached per type, the same asset can be loaded as different types.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct AssetKey {
+    mount_point: String,
+    identifier: String,
+    kind: TypeId,
+}
+
+struct CacheEntry {
+    value: Arc<dyn Any + Send + Sync>,
+    size: usize,
+    last_used: u64,
+}
+
+impl CacheEntry {
+    /// True if no handles to the asset exist outside of the cache.
+    fn is_unused(&self) -> bool {
+        Arc::strong_count(&self.value) == 1
+    }
+}
+
+/// Signals threads waiting for an asset which is loaded by another thread.
+#[derive(Default)]
+struct PendingLoad {
+    done: Mutex<bool>,
+    condvar: Condvar,
+}
+
+impl PendingLoad {
+    fn wait(&self) {
+        let mut done = match self.done.lock() {
+            Ok(v) => v,
+            Err(_) => return,
+        };
+        while !*done {
+            done = match self.condvar.wait(done) {
+                Ok(v) => v,
+                Err(_) => return,
+            };
+        }
+    }
+
+    fn finish(&self) {
+        if let Ok(mut done) = self.done.lock() {
+            *done = true;
+        }
+        self.condvar.notify_all();
+    }
+}
+
+/// Finishes a load started by `AssetCache::get`, even if the loader panics.
+/// Removes the slot unless the load stored the asset, otherwise threads waiting for it would block forever.
+struct PendingLoadGuard<'a> {
+    state: &'a Mutex<CacheState>,
+    key: AssetKey,
+    pending: Arc<PendingLoad>,
+}
+
+impl Drop for PendingLoadGuard<'_> {
+    fn drop(&mut self) {
+        let mut state = match self.state.lock() {
+            Ok(v) => v,
+            Err(e) => e.into_inner(),
+        };
+        if matches!(
+            state.slots.get(&self.key),
+            Some(CacheSlot::Loading(pending)) if Arc::ptr_eq(pending, &self.pending)
+        ) {
+            state.slots.remove(&self.key);
+        }
+        drop(state);
+        self.pending.finish();
+    }
+}
+
+enum CacheSlot {
+    Loading(Arc<PendingLoad>),
+    Loaded(CacheEntry),
+}
+
+#[derive(Default)]
+struct CacheState {
+    slots: HashMap<AssetKey, CacheSlot>,
+    stats: HashMap<TypeId, (&'static str, AssetCacheStats)>,
+    /// Sum of the sizes of all loaded entries.
+    bytes: usize,
+    /// Incremented on every access, used to find the least recently used entries.
+    clock: u64,
+}
+
+impl CacheState {
+    fn stats_mut<T: 'static>(&mut self) -> &mut AssetCacheStats {
+        &mut self
+            .stats
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| (std::any::type_name::<T>(), Default::default()))
+            .1
+    }
+
+    /// Evicts unused entries, least recently used first, until the cache fits into the budget.
+    fn evict(&mut self, budget: usize) {
+        while self.bytes > budget {
+            let lru = self
+                .slots
+                .iter()
+                .filter_map(|(key, slot)| match slot {
+                    CacheSlot::Loaded(entry) if entry.is_unused() => Some((key, entry.last_used)),
+                    _ => None,
+                })
+                .min_by_key(|(_, last_used)| *last_used)
+                .map(|(key, _)| key.clone());
+            let key = match lru {
+                Some(v) => v,
+                // All remaining entries are in use.
+                None => break,
+            };
+            if let Some(CacheSlot::Loaded(entry)) = self.slots.remove(&key) {
+                self.bytes -= entry.size;
+                if let Some((_, stats)) = self.stats.get_mut(&key.kind) {
+                    stats.evictions += 1;
+                }
+            }
+        }
+    }
+}
+
+/// Caches deserialized assets loaded from an [`AssetSystem`].
+/// Concurrent requests for the same asset are deduplicated, only one of them loads it.
+/// If the cached assets exceed the byte budget, unused assets are evicted in least recently used order.
+/// The size of an asset is approximated using its size in the asset system plus the size of its type.
+pub struct AssetCache {
+    system: Arc<AssetSystem>,
+    budget: Mutex<usize>,
+    state: Mutex<CacheState>,
+}
+
+impl AssetCache {
+    pub const DEFAULT_BUDGET: usize = 256 * 1024 * 1024;
+
+    pub fn new(system: Arc<AssetSystem>, budget: usize) -> Self {
+        Self {
+            system,
+            budget: Mutex::new(budget),
+            state: Default::default(),
+        }
+    }
+
+    pub fn asset_system(&self) -> &Arc<AssetSystem> {
+        &self.system
+    }
+
+    /// Returns the cached asset or loads it using `AssetSystem::load_asset_as_type_using_buffer`.
+    pub fn get<T: DeserializeOwned + Send + Sync + 'static>(
+        &self,
+        mount_point: impl AsRef<str>,
+        identifier: impl AsRef<str>,
+    ) -> Result<AssetHandle<T>, AssetSystemError> {
+        let key = AssetKey {
+            mount_point: mount_point.as_ref().to_lowercase(),
+            identifier: identifier.as_ref().to_lowercase(),
+            kind: TypeId::of::<T>(),
+        };
+
+        let pending = loop {
+            let mut state = self.lock_state()?;
+            state.clock += 1;
+            let clock = state.clock;
+            match state.slots.get_mut(&key) {
+                Some(CacheSlot::Loaded(entry)) => {
+                    entry.last_used = clock;
+                    let value = Arc::clone(&entry.value);
+                    state.stats_mut::<T>().hits += 1;
+                    return Self::handle(value);
+                }
+                Some(CacheSlot::Loading(pending)) => {
+                    let pending = Arc::clone(pending);
+                    drop(state);
+                    // Checks the slot again, the load might have failed.
+                    pending.wait();
+                }
+                None => {
+                    let pending = Arc::new(PendingLoad::default());
+                    state
+                        .slots
+                        .insert(key.clone(), CacheSlot::Loading(Arc::clone(&pending)));
+                    state.stats_mut::<T>().misses += 1;
+                    break pending;
+                }
+            }
+        };
+
+        let guard = PendingLoadGuard {
+            state: &self.state,
+            key: key.clone(),
+            pending,
+        };
+        let mut buffer = Vec::new();
+        let result = self.system.load_asset_as_type_using_buffer::<T, _, _>(
+            &key.mount_point,
+            &key.identifier,
+            &mut buffer,
+        );
+        let mut state = self.lock_state();
+        let result = match (&mut state, result) {
+            (Ok(state), Ok(value)) => {
+                let value: Arc<dyn Any + Send + Sync> = Arc::new(value);
+                // The asset was invalidated while loading if the slot was removed, the value might be outdated.
+                if matches!(
+                    state.slots.get(&key),
+                    Some(CacheSlot::Loading(pending)) if Arc::ptr_eq(pending, &guard.pending)
+                ) {
+                    let size = buffer.len() + std::mem::size_of::<T>();
+                    let last_used = state.clock;
+                    state.bytes += size;
+                    state.slots.insert(
+                        key,
+                        CacheSlot::Loaded(CacheEntry {
+                            value: Arc::clone(&value),
+                            size,
+                            last_used,
+                        }),
+                    );
+                    state.evict(self.budget());
+                }
+                Self::handle(value)
+            }
+            // The guard removes the slot of the failed load.
+            (Ok(_), Err(e)) => Err(e),
+            (Err(_), _) => Err(AssetSystemError::PoisonError),
+        };
+        drop(state);
+        drop(guard);
+        result
+    }
+
+    /// Removes an asset from the cache, for all types it was loaded as.
+    /// Invalidating a variant, e.g. `ui/strings@fr`, also removes the base asset `ui/strings`, which might have been served by it.
+    /// Existing handles keep the old asset alive, `get` loads it again.
+    /// Loads in progress return the old asset to their caller without caching it.
+    pub fn invalidate(&self, mount_point: impl AsRef<str>, identifier: impl AsRef<str>) {
+        let mount_point = mount_point.as_ref().to_lowercase();
+        let identifier = identifier.as_ref().to_lowercase();
+        let base = base_identifier(&identifier);
+        self.remove_where(|key| {
+            key.mount_point == mount_point
+                && (key.identifier == identifier || key.identifier == base)
+        });
+    }
+
+    /// Removes all assets from the cache, e.g. after the asset variants changed.
+    /// Existing handles keep the old assets alive, `get` loads them again, see `invalidate`.
+    pub fn clear(&self) {
+        self.remove_where(|_| true);
+    }
+
+    fn remove_where(&self, remove: impl Fn(&AssetKey) -> bool) {
+        let mut state = match self.lock_state() {
+            Ok(v) => v,
+            Err(_) => return,
+        };
+        let mut freed = 0;
+        state.slots.retain(|key, slot| {
+            if !remove(key) {
+                return true;
+            }
+            if let CacheSlot::Loaded(entry) = slot {
+                freed += entry.size;
+            }
+            // Loads in progress are not cached once they finish, waiting threads load the asset again.
+            false
+        });
+        state.bytes -= freed;
+    }
+
+    /// Evicts unused assets until the cache fits into the budget again.
+    /// Assets which are in use when they would be evicted are kept, this allows evicting them once they are dropped.
+    pub fn trim(&self) {
+        let budget = self.budget();
+        if let Ok(mut state) = self.lock_state() {
+            state.evict(budget);
+        }
+    }
+
+    pub fn budget(&self) -> usize {
+        self.budget.lock().map(|v| *v).unwrap_or_default()
+    }
+
+    /// Changes the budget and evicts assets if the cache does not fit into the new budget.
+    pub fn set_budget(&self, budget: usize) {
+        if let Ok(mut v) = self.budget.lock() {
+            *v = budget;
+        }
+        self.trim();
+    }
+
+    /// Returns the approximated size of all cached assets in bytes.
+    pub fn bytes(&self) -> usize {
+        self.lock_state().map(|s| s.bytes).unwrap_or_default()
+    }
+
+    /// Returns the counters of assets loaded as `T`.
+    pub fn stats<T: 'static>(&self) -> AssetCacheStats {
+        self.lock_state()
+            .ok()
+            .and_then(|s| s.stats.get(&TypeId::of::<T>()).map(|(_, stats)| *stats))
+            .unwrap_or_default()
+    }
+
+    /// Returns the counters of all asset kinds by type name.
+    pub fn all_stats(&self) -> Vec<(&'static str, AssetCacheStats)> {
+        match self.lock_state() {
+            Ok(state) => state.stats.values().cloned().collect(),
+            Err(_) => vec![],
+        }
+    }
+
+    fn handle<T: Send + Sync + 'static>(
+        value: Arc<dyn Any + Send + Sync>,
+    ) -> Result<AssetHandle<T>, AssetSystemError> {
+        value
+            .downcast::<T>()
+            .map(|value| AssetHandle { value })
+            .map_err(|_| AssetSystemError::InvalidAssetType)
+    }
+
+    fn lock_state(&self) -> Result<MutexGuard<'_, CacheState>, AssetSystemError> {
+        self.state.lock().map_err(|e| {
+            t_warn!("{}", e);
+            AssetSystemError::PoisonError
+        })
+    }
+}
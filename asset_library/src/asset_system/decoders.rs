@@ -0,0 +1,244 @@
+use std::{any::Any, collections::HashMap, error::Error};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use super::AssetSystemError;
+
+pub type DecodeError = Box<dyn Error + Send + Sync>;
+
+/// Decodes assets of a format supported by serde into any `DeserializeOwned` type.
+pub trait SerdeAssetFormat: Send + Sync + 'static {
+    /// Calls `visit` with a deserializer reading `bytes`.
+    fn deserialize<'de>(
+        &self,
+        bytes: &'de [u8],
+        visit: &mut dyn FnMut(
+            &mut dyn erased_serde::Deserializer<'de>,
+        ) -> Result<(), erased_serde::Error>,
+    ) -> Result<(), DecodeError>;
+
+    /// Encodes `value`, used to save assets. Formats which can only be read keep the default.
+    fn serialize(&self, _value: &dyn erased_serde::Serialize) -> Result<Vec<u8>, DecodeError> {
+        Err(Box::from("The format does not support saving assets."))
+    }
+}
+
+/// Decodes the raw bytes of an asset into a specific type, for formats which are not supported by serde.
+pub trait AssetDecoder: Send + Sync + 'static {
+    type Output: Send + 'static;
+    fn decode(&self, bytes: &[u8]) -> Result<Self::Output, DecodeError>;
+}
+
+/// Object safe version of `AssetDecoder`.
+trait AnyAssetDecoder: Send + Sync + 'static {
+    fn decode_any(&self, bytes: &[u8]) -> Result<Box<dyn Any + Send>, DecodeError>;
+}
+
+impl<D: AssetDecoder> AnyAssetDecoder for D {
+    fn decode_any(&self, bytes: &[u8]) -> Result<Box<dyn Any + Send>, DecodeError> {
+        Ok(Box::new(self.decode(bytes)?))
+    }
+}
+
+enum RegisteredDecoder {
+    Serde(Box<dyn SerdeAssetFormat>),
+    Raw(Box<dyn AnyAssetDecoder>),
+}
+
+/// Decoders by format string, formats are case insensitive.
+/// By default YAML, CBOR, JSON and TOML are registered. (JSON and TOML require their features to be enabled)
+pub struct AssetDecoderRegistry {
+    decoders: HashMap<String, RegisteredDecoder>,
+}
+
+impl Default for AssetDecoderRegistry {
+    fn default() -> Self {
+        let mut registry = Self {
+            decoders: Default::default(),
+        };
+        registry.register_serde_format("yaml", YamlFormat);
+        registry.register_serde_format("yml", YamlFormat);
+        registry.register_serde_format("cbor", CborFormat);
+        #[cfg(feature = "format_json")]
+        registry.register_serde_format("json", JsonFormat);
+        #[cfg(feature = "format_toml")]
+        registry.register_serde_format("toml", TomlFormat);
+        registry
+    }
+}
+
+impl AssetDecoderRegistry {
+    /// Registers a serde format, replacing any decoder registered for `format`.
+    pub fn register_serde_format(
+        &mut self,
+        format: impl AsRef<str>,
+        decoder: impl SerdeAssetFormat,
+    ) {
+        self.decoders.insert(
+            format.as_ref().to_lowercase(),
+            RegisteredDecoder::Serde(Box::new(decoder)),
+        );
+    }
+
+    /// Registers a raw decoder, replacing any decoder registered for `format`.
+    pub fn register_decoder(&mut self, format: impl AsRef<str>, decoder: impl AssetDecoder) {
+        self.decoders.insert(
+            format.as_ref().to_lowercase(),
+            RegisteredDecoder::Raw(Box::new(decoder)),
+        );
+    }
+
+    pub fn unregister(&mut self, format: impl AsRef<str>) -> bool {
+        self.decoders
+            .remove(&format.as_ref().to_lowercase())
+            .is_some()
+    }
+
+    pub fn has_format(&self, format: impl AsRef<str>) -> bool {
+        self.decoders.contains_key(&format.as_ref().to_lowercase())
+    }
+
+    /// Decodes an asset using the decoder registered for `format`.
+    /// Raw decoders must output `T`.
+    pub fn decode_as_type<T: DeserializeOwned + 'static>(
+        &self,
+        format: &str,
+        bytes: &[u8],
+    ) -> Result<T, AssetSystemError> {
+        match self.decoders.get(format) {
+            Some(RegisteredDecoder::Serde(decoder)) => {
+                let mut value = None;
+                decoder.deserialize(bytes, &mut |deserializer| {
+                    value = Some(erased_serde::deserialize::<T>(deserializer)?);
+                    Ok(())
+                })?;
+                value.ok_or(AssetSystemError::InvalidAssetType)
+            }
+            Some(RegisteredDecoder::Raw(decoder)) => Self::downcast(decoder.decode_any(bytes)?),
+            None => Err(AssetSystemError::UnknownAssetFormat),
+        }
+    }
+
+    /// Decodes an asset using the raw decoder registered for `format`, which must output `T`.
+    pub fn decode<T: 'static>(&self, format: &str, bytes: &[u8]) -> Result<T, AssetSystemError> {
+        match self.decoders.get(format) {
+            Some(RegisteredDecoder::Raw(decoder)) => Self::downcast(decoder.decode_any(bytes)?),
+            // Serde formats can only decode types implementing `DeserializeOwned`.
+            Some(RegisteredDecoder::Serde(_)) => Err(AssetSystemError::InvalidAssetType),
+            None => Err(AssetSystemError::UnknownAssetFormat),
+        }
+    }
+
+    /// Encodes `value` using the serde format registered for `format`.
+    pub fn encode_from_type<T: Serialize>(
+        &self,
+        format: &str,
+        value: &T,
+    ) -> Result<Vec<u8>, AssetSystemError> {
+        match self.decoders.get(format) {
+            Some(RegisteredDecoder::Serde(decoder)) => Ok(decoder.serialize(value)?),
+            // Raw decoders only read assets.
+            Some(RegisteredDecoder::Raw(_)) => Err(AssetSystemError::InvalidAssetType),
+            None => Err(AssetSystemError::UnknownAssetFormat),
+        }
+    }
+
+    fn downcast<T: 'static>(value: Box<dyn Any + Send>) -> Result<T, AssetSystemError> {
+        value
+            .downcast::<T>()
+            .map(|v| *v)
+            .map_err(|_| AssetSystemError::InvalidAssetType)
+    }
+}
+
+pub struct YamlFormat;
+
+impl SerdeAssetFormat for YamlFormat {
+    fn deserialize<'de>(
+        &self,
+        bytes: &'de [u8],
+        visit: &mut dyn FnMut(
+            &mut dyn erased_serde::Deserializer<'de>,
+        ) -> Result<(), erased_serde::Error>,
+    ) -> Result<(), DecodeError> {
+        let deserializer = serde_yaml::Deserializer::from_slice(bytes);
+        visit(&mut <dyn erased_serde::Deserializer>::erase(deserializer))?;
+        Ok(())
+    }
+
+    fn serialize(&self, value: &dyn erased_serde::Serialize) -> Result<Vec<u8>, DecodeError> {
+        Ok(serde_yaml::to_vec(value)?)
+    }
+}
+
+pub struct CborFormat;
+
+impl SerdeAssetFormat for CborFormat {
+    fn deserialize<'de>(
+        &self,
+        bytes: &'de [u8],
+        visit: &mut dyn FnMut(
+            &mut dyn erased_serde::Deserializer<'de>,
+        ) -> Result<(), erased_serde::Error>,
+    ) -> Result<(), DecodeError> {
+        let mut deserializer = serde_cbor::Deserializer::from_slice(bytes);
+        visit(&mut <dyn erased_serde::Deserializer>::erase(
+            &mut deserializer,
+        ))?;
+        deserializer.end()?;
+        Ok(())
+    }
+
+    fn serialize(&self, value: &dyn erased_serde::Serialize) -> Result<Vec<u8>, DecodeError> {
+        Ok(serde_cbor::to_vec(&value)?)
+    }
+}
+
+#[cfg(feature = "format_json")]
+pub struct JsonFormat;
+
+#[cfg(feature = "format_json")]
+impl SerdeAssetFormat for JsonFormat {
+    fn deserialize<'de>(
+        &self,
+        bytes: &'de [u8],
+        visit: &mut dyn FnMut(
+            &mut dyn erased_serde::Deserializer<'de>,
+        ) -> Result<(), erased_serde::Error>,
+    ) -> Result<(), DecodeError> {
+        let mut deserializer = serde_json::Deserializer::from_slice(bytes);
+        visit(&mut <dyn erased_serde::Deserializer>::erase(
+            &mut deserializer,
+        ))?;
+        deserializer.end()?;
+        Ok(())
+    }
+
+    fn serialize(&self, value: &dyn erased_serde::Serialize) -> Result<Vec<u8>, DecodeError> {
+        Ok(serde_json::to_vec_pretty(value)?)
+    }
+}
+
+#[cfg(feature = "format_toml")]
+pub struct TomlFormat;
+
+#[cfg(feature = "format_toml")]
+impl SerdeAssetFormat for TomlFormat {
+    fn deserialize<'de>(
+        &self,
+        bytes: &'de [u8],
+        visit: &mut dyn FnMut(
+            &mut dyn erased_serde::Deserializer<'de>,
+        ) -> Result<(), erased_serde::Error>,
+    ) -> Result<(), DecodeError> {
+        // Trailing characters are already rejected while deserializing.
+        let mut deserializer = toml::Deserializer::new(std::str::from_utf8(bytes)?);
+        visit(&mut <dyn erased_serde::Deserializer>::erase(
+            &mut deserializer,
+        ))?;
+        Ok(())
+    }
+    fn serialize(&self, value: &dyn erased_serde::Serialize) -> Result<Vec<u8>, DecodeError> {
+        Ok(toml::to_vec(value)?)
+    }
+}
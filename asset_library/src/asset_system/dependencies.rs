@@ -0,0 +1,113 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::{vfs::error::VfsError, AssetDescriptor, AssetReference};
+
+use super::AssetSystemError;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum VisitState {
+    Visiting,
+    Done,
+}
+
+/// Resolves the dependency closure of `root` using depth first search.
+/// Returns the descriptors in topological order, every asset follows its dependencies and `root` is last.
+pub(crate) fn dependency_order(
+    root: &AssetReference,
+    mut descriptor: impl FnMut(&AssetReference) -> Result<AssetDescriptor, AssetSystemError>,
+) -> Result<Vec<AssetDescriptor>, AssetSystemError> {
+    let mut order = vec![];
+    let mut states = HashMap::new();
+    let mut path = vec![];
+    let root_descriptor = descriptor(root)?;
+    visit(
+        root_descriptor,
+        &mut descriptor,
+        &mut states,
+        &mut path,
+        &mut order,
+    )?;
+    Ok(order)
+}
+
+fn visit(
+    asset: AssetDescriptor,
+    descriptor: &mut impl FnMut(&AssetReference) -> Result<AssetDescriptor, AssetSystemError>,
+    states: &mut HashMap<AssetReference, VisitState>,
+    path: &mut Vec<AssetReference>,
+    order: &mut Vec<AssetDescriptor>,
+) -> Result<(), AssetSystemError> {
+    let reference = asset.reference();
+    states.insert(reference.clone(), VisitState::Visiting);
+    path.push(reference.clone());
+    for dependency in asset.dependencies() {
+        let dependency = AssetReference::new(dependency.mount(), dependency.identifier());
+        match states.get(&dependency) {
+            Some(VisitState::Done) => continue,
+            Some(VisitState::Visiting) => {
+                // The cycle starts where the dependency was entered and ends with the dependency again.
+                let start = path.iter().position(|r| *r == dependency).unwrap_or(0);
+                let mut cycle = path[start..].to_vec();
+                cycle.push(dependency);
+                return Err(AssetSystemError::DependencyCycle(cycle));
+            }
+            None => (),
+        }
+        let dependency_descriptor = match descriptor(&dependency) {
+            Ok(v) => v,
+            Err(AssetSystemError::Vfs(VfsError::FileNotFound))
+            | Err(AssetSystemError::Vfs(VfsError::MountpointNotFound)) => {
+                return Err(AssetSystemError::MissingDependency {
+                    asset: reference,
+                    dependency,
+                })
+            }
+            Err(e) => return Err(e),
+        };
+        visit(dependency_descriptor, descriptor, states, path, order)?;
+    }
+    path.pop();
+    states.insert(reference, VisitState::Done);
+    order.push(asset);
+    Ok(())
+}
+
+/// Returns the assets of `assets` which depend on `target`, ordered by mount point and identifier.
+/// If `transitive` is true, assets which depend on `target` through other assets are included as well.
+pub(crate) fn dependents(
+    assets: Vec<AssetDescriptor>,
+    target: &AssetReference,
+    transitive: bool,
+) -> Vec<AssetDescriptor> {
+    let mut reverse: HashMap<AssetReference, Vec<usize>> = HashMap::new();
+    for (index, asset) in assets.iter().enumerate() {
+        for dependency in asset.dependencies() {
+            reverse
+                .entry(AssetReference::new(
+                    dependency.mount(),
+                    dependency.identifier(),
+                ))
+                .or_default()
+                .push(index);
+        }
+    }
+
+    let mut found = HashSet::new();
+    let mut queue = VecDeque::from([target.clone()]);
+    while let Some(reference) = queue.pop_front() {
+        for index in reverse.get(&reference).into_iter().flatten() {
+            if found.insert(*index) && transitive {
+                queue.push_back(assets[*index].reference());
+            }
+        }
+    }
+
+    let mut dependents = assets
+        .into_iter()
+        .enumerate()
+        .filter(|(index, asset)| found.contains(index) && asset.reference() != *target)
+        .map(|(_, asset)| asset)
+        .collect::<Vec<_>>();
+    dependents.sort_by(|a, b| (a.mount(), a.identifier()).cmp(&(b.mount(), b.identifier())));
+    dependents
+}
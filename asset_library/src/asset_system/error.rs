@@ -1,16 +1,23 @@
 use std::{error::Error, fmt::Display};
 
-use crate::{archive::AssetArchiveError, vfs::error::VfsError};
+use crate::{archive::AssetArchiveError, vfs::error::VfsError, AssetReference};
 
 #[derive(Debug)]
 pub enum AssetSystemError {
     Vfs(VfsError),
     Io(std::io::Error),
     UnknownAssetFormat,
-    Other(Box<dyn Error>),
+    InvalidAssetType,
+    Other(Box<dyn Error + Send + Sync>),
     Archive(AssetArchiveError),
     PoisonError,
     NotMounted,
+    /// The assets depend on each other, the first asset is repeated at the end.
+    DependencyCycle(Vec<AssetReference>),
+    MissingDependency {
+        asset: AssetReference,
+        dependency: AssetReference,
+    },
 }
 impl Error for AssetSystemError {}
 impl Display for AssetSystemError {
@@ -21,11 +28,28 @@ impl Display for AssetSystemError {
             AssetSystemError::Other(e) => e.fmt(f),
             AssetSystemError::Archive(e) => e.fmt(f),
             AssetSystemError::UnknownAssetFormat => write!(f, "Unknown asset format."),
+            AssetSystemError::InvalidAssetType => {
+                write!(
+                    f,
+                    "Asset format can not be decoded into the requested type."
+                )
+            }
             AssetSystemError::PoisonError => write!(
                 f,
                 "Poisoning occured! A thread has paniced and killed the asset system."
             ),
             AssetSystemError::NotMounted => write!(f, "Mountpoint was not mounted."),
+            AssetSystemError::DependencyCycle(cycle) => {
+                let cycle = cycle.iter().map(|r| r.to_string()).collect::<Vec<_>>();
+                write!(f, "Asset dependency cycle: {}.", cycle.join(" -> "))
+            }
+            AssetSystemError::MissingDependency { asset, dependency } => {
+                write!(
+                    f,
+                    "Asset {} depends on missing asset {}.",
+                    asset, dependency
+                )
+            }
         }
     }
 }
@@ -39,8 +63,8 @@ impl From<VfsError> for AssetSystemError {
         Self::Vfs(e)
     }
 }
-impl From<Box<dyn Error>> for AssetSystemError {
-    fn from(e: Box<dyn Error>) -> Self {
+impl From<Box<dyn Error + Send + Sync>> for AssetSystemError {
+    fn from(e: Box<dyn Error + Send + Sync>) -> Self {
         Self::Other(e)
     }
 }
@@ -0,0 +1,14 @@
+/// Sent whenever a watched asset was added, modified or removed, or an asset was saved or deleted.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AssetDidChange {
+    pub mount: String,
+    pub identifier: String,
+}
+
+/// Sent whenever the active asset variants change, e.g. when the player selects another language.
+/// Assets which have variants need to be loaded again.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AssetVariantsDidChange {
+    /// The new variants, most specific first.
+    pub variants: Vec<String>,
+}
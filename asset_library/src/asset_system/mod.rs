@@ -1,6 +1,22 @@
-use std::{fs::read_dir, path::Path, sync::RwLock};
+use std::{
+    fs::read_dir,
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, RwLock, RwLockReadGuard, RwLockWriteGuard,
+    },
+    time::Duration,
+};
+mod decoders;
+mod dependencies;
 mod error;
-use serde::de::DeserializeOwned;
+mod messages;
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::task::JoinHandle;
+use utils::dispatcher::Dispatcher;
+
+pub use decoders::*;
+pub use messages::*;
 
 use crate::{
     archive::*,
@@ -9,29 +25,37 @@ use crate::{
     *,
 };
 
-use self::error::AssetSystemError;
+pub use self::error::AssetSystemError;
 
 // TODO: Move the RwLock into the virtual file system!
 
+pub type AssetChangeListener = dyn Fn(&AssetDidChange) + Send + Sync;
+pub type AssetVariantsListener = dyn Fn(&AssetVariantsDidChange) + Send + Sync;
+
 pub struct AssetSystem {
     vfs: RwLock<VirtualFileSystem>,
+    decoders: RwLock<AssetDecoderRegistry>,
+    change_listeners: Arc<RwLock<Vec<Box<AssetChangeListener>>>>,
+    variants_listeners: RwLock<Vec<Box<AssetVariantsListener>>>,
+    verify_archive_checksums: AtomicBool,
 }
 
 impl Default for AssetSystem {
     fn default() -> Self {
         Self {
             vfs: Default::default(),
+            decoders: Default::default(),
+            change_listeners: Default::default(),
+            variants_listeners: Default::default(),
+            verify_archive_checksums: AtomicBool::new(false),
         }
     }
 }
 
-unsafe impl Sync for AssetSystem {}
-
 impl AssetSystem {
     /// Deserializes an asset into the provided type, allocates internal byte buffer temprorarily.
-    /// The asset system only supports YAML, CBOR, TOML and JSON formats.
-    // Might required specific features to be enabled.
-    pub fn load_asset_as_type<T1: DeserializeOwned, T2: AsRef<str>, T3: AsRef<str>>(
+    /// The decoder is selected by the asset's format, see `register_serde_format` and `register_decoder`.
+    pub fn load_asset_as_type<T1: DeserializeOwned + 'static, T2: AsRef<str>, T3: AsRef<str>>(
         &self,
         mount_point: T2,
         identifier: T3,
@@ -41,37 +65,145 @@ impl AssetSystem {
     }
 
     /// Deserializes an asses into the provided type, using the provided buffer as intermediate.
-    pub fn load_asset_as_type_using_buffer<T1: DeserializeOwned, T2: AsRef<str>, T3: AsRef<str>>(
+    pub fn load_asset_as_type_using_buffer<
+        T1: DeserializeOwned + 'static,
+        T2: AsRef<str>,
+        T3: AsRef<str>,
+    >(
         &self,
         mount_point: T2,
         identifier: T3,
         buffer: &mut Vec<u8>,
     ) -> Result<T1, AssetSystemError> {
         let descriptor = self.load_asset_as_blob_into(&mount_point, &identifier, buffer)?;
+        self.deserialize(&descriptor, buffer)
+    }
 
-        match descriptor.format() {
-            "yaml" | "yml" => {
-                serde_yaml::from_slice(&buffer).map_err(|e| AssetSystemError::Other(Box::from(e)))
-            }
-            "cbor" => {
-                serde_cbor::from_slice(&buffer).map_err(|e| AssetSystemError::Other(Box::from(e)))
-            }
-            #[cfg(feature = "format_json")]
-            "json" => {
-                serde_json::from_slice(&buffer).map_err(|e| AssetSystemError::Other(Box::from(e)))
-            }
-            #[cfg(feature = "format_toml")]
-            "toml" => toml::from_slice(&buffer).map_err(|e| AssetSystemError::Other(Box::from(e))),
-            _ => {
-                t_warn!(
-                    "Tried to load asset {} with unknown format {} from {}.",
-                    identifier.as_ref(),
-                    descriptor.format(),
-                    mount_point.as_ref()
-                );
-                Err(AssetSystemError::UnknownAssetFormat)
-            }
+    /// Decodes an asset using the raw decoder registered for its format, see `register_decoder`.
+    /// The decoder must output `T`.
+    pub fn load_asset_decoded<T: 'static>(
+        &self,
+        mount_point: impl AsRef<str>,
+        identifier: impl AsRef<str>,
+    ) -> Result<T, AssetSystemError> {
+        let mut buffer = Vec::new();
+        let descriptor = self.load_asset_as_blob_into(&mount_point, &identifier, &mut buffer)?;
+        self.read_decoders()?.decode(descriptor.format(), &buffer)
+    }
+
+    /// Registers a serde based decoder for assets of `format`.
+    /// Replaces the decoder which was registered for the format before, including the default ones.
+    pub fn register_serde_format(
+        &self,
+        format: impl AsRef<str>,
+        decoder: impl SerdeAssetFormat,
+    ) -> Result<(), AssetSystemError> {
+        self.write_decoders()?
+            .register_serde_format(format, decoder);
+        Ok(())
+    }
+
+    /// Registers a raw decoder for assets of `format`.
+    /// Replaces the decoder which was registered for the format before, including the default ones.
+    pub fn register_decoder(
+        &self,
+        format: impl AsRef<str>,
+        decoder: impl AssetDecoder,
+    ) -> Result<(), AssetSystemError> {
+        self.write_decoders()?.register_decoder(format, decoder);
+        Ok(())
+    }
+
+    /// Loads an asset on the dispatcher and deserializes it into the provided type.
+    /// See `load_asset_as_blob_async` for how the work is distributed.
+    pub fn load_asset_as_type_async<T: DeserializeOwned + Send + 'static>(
+        self: &Arc<Self>,
+        dispatcher: &Arc<Dispatcher>,
+        mount_point: impl AsRef<str>,
+        identifier: impl AsRef<str>,
+    ) -> JoinHandle<Result<T, AssetSystemError>> {
+        let system = Arc::clone(self);
+        self.load_asset_async(
+            dispatcher,
+            mount_point,
+            identifier,
+            move |descriptor, buffer| system.deserialize(&descriptor, &buffer),
+        )
+    }
+
+    /// Loads an asset on the dispatcher.
+    /// File I/O runs on the async runtime's blocking threads, decompression runs on the worker thread pool.
+    pub fn load_asset_as_blob_async(
+        self: &Arc<Self>,
+        dispatcher: &Arc<Dispatcher>,
+        mount_point: impl AsRef<str>,
+        identifier: impl AsRef<str>,
+    ) -> JoinHandle<Result<(Vec<u8>, AssetDescriptor), AssetSystemError>> {
+        self.load_asset_async(dispatcher, mount_point, identifier, |descriptor, buffer| {
+            Ok((buffer, descriptor))
+        })
+    }
+
+    /// Reads the stored asset on the async runtime, then decompresses it and calls `process` on the worker thread pool.
+    fn load_asset_async<R: Send + 'static>(
+        self: &Arc<Self>,
+        dispatcher: &Arc<Dispatcher>,
+        mount_point: impl AsRef<str>,
+        identifier: impl AsRef<str>,
+        process: impl FnOnce(AssetDescriptor, Vec<u8>) -> Result<R, AssetSystemError> + Send + 'static,
+    ) -> JoinHandle<Result<R, AssetSystemError>> {
+        let system = Arc::clone(self);
+        let workers = Arc::clone(dispatcher);
+        let mount_point = mount_point.as_ref().to_string();
+        let identifier = identifier.as_ref().to_string();
+
+        dispatcher.spawn_async(async move {
+            let (stored, descriptor, file_header) = workers
+                .spawn_async_blocking(move || {
+                    let mut buffer = Vec::new();
+                    let vfs = system.read_vfs()?;
+                    let (descriptor, file_header) =
+                        vfs.read_stored_file_into(mount_point, identifier, &mut buffer)?;
+                    Ok::<_, AssetSystemError>((buffer, descriptor, file_header))
+                })
+                .await
+                .map_err(|e| AssetSystemError::Other(Box::from(e)))??;
+
+            workers
+                .spawn_with_result(move || {
+                    let buffer = match file_header {
+                        Some(file_header) => {
+                            let mut buffer = Vec::new();
+                            AssetArchive::decompress_into(&file_header, &stored, &mut buffer)?;
+                            buffer
+                        }
+                        None => stored,
+                    };
+                    process(descriptor, buffer)
+                })
+                .await
+                .map_err(|e| AssetSystemError::Other(Box::from(e)))?
+        })
+    }
+
+    /// Deserializes a loaded asset using the decoder registered for its format.
+    fn deserialize<T: DeserializeOwned + 'static>(
+        &self,
+        descriptor: &AssetDescriptor,
+        buffer: &[u8],
+    ) -> Result<T, AssetSystemError> {
+        let result = self
+            .read_decoders()?
+            .decode_as_type(descriptor.format(), buffer);
+        if let Err(AssetSystemError::UnknownAssetFormat) = result {
+            t_warn!(
+                "Tried to load asset {} with unknown format {} from {}.",
+                descriptor.identifier(),
+                descriptor.format(),
+                descriptor.mount()
+            );
         }
+        result
     }
 
     pub fn load_asset_as_blob_into(
@@ -80,25 +212,174 @@ impl AssetSystem {
         identifier: impl AsRef<str>,
         buffer: &mut Vec<u8>,
     ) -> Result<AssetDescriptor, AssetSystemError> {
-        let vfs = self.vfs.read().map_err(|e| {
-            t_warn!("{}", e);
-            AssetSystemError::PoisonError
-        })?;
+        let vfs = self.read_vfs()?;
 
         vfs.read_file_into(mount_point, identifier, buffer)
             .map_err(|e| e.into())
     }
 
+    /// Opens an asset for streaming instead of loading it into memory, e.g. for large audio or level data.
+    /// Archived files are decompressed while reading, see `AssetArchiveReader::open_file`.
+    pub fn open_asset(
+        &self,
+        mount_point: impl AsRef<str>,
+        identifier: impl AsRef<str>,
+    ) -> Result<Box<dyn AssetStream>, AssetSystemError> {
+        let vfs = self.read_vfs()?;
+        vfs.open_file(mount_point, identifier)
+            .map(|(_, stream)| stream)
+            .map_err(|e| e.into())
+    }
+
+    /// Serializes `value` using the serde format registered for `format` and saves it as an asset.
+    /// The asset is stored in the highest writable version of the mount point, see `mount_writable_directory`.
+    pub fn save_asset_as_type<T: Serialize>(
+        &self,
+        mount_point: impl AsRef<str>,
+        identifier: impl AsRef<str>,
+        format: impl AsRef<str>,
+        value: &T,
+    ) -> Result<AssetDescriptor, AssetSystemError> {
+        let format = format.as_ref().to_lowercase();
+        let bytes = self.read_decoders()?.encode_from_type(&format, value)?;
+        self.save_asset_as_blob(mount_point, identifier, format, &bytes)
+    }
+
+    /// Saves the bytes of an asset, replacing the asset if it exists.
+    /// Change listeners are notified about the saved asset.
+    pub fn save_asset_as_blob(
+        &self,
+        mount_point: impl AsRef<str>,
+        identifier: impl AsRef<str>,
+        format: impl AsRef<str>,
+        bytes: &[u8],
+    ) -> Result<AssetDescriptor, AssetSystemError> {
+        let descriptor = self
+            .read_vfs()?
+            .write_file(mount_point, identifier, format, bytes)?;
+        self.notify_change_listeners(&descriptor);
+        Ok(descriptor)
+    }
+
+    /// Deletes an asset from the highest writable version of the mount point.
+    pub fn delete_asset(
+        &self,
+        mount_point: impl AsRef<str>,
+        identifier: impl AsRef<str>,
+    ) -> Result<(), AssetSystemError> {
+        let vfs = self.read_vfs()?;
+        let descriptor = vfs.descriptor(&mount_point, &identifier)?;
+        vfs.delete_file(mount_point, identifier)?;
+        drop(vfs);
+        self.notify_change_listeners(&descriptor);
+        Ok(())
+    }
+
+    fn notify_change_listeners(&self, descriptor: &AssetDescriptor) {
+        let message = AssetDidChange {
+            mount: descriptor.mount().to_string(),
+            identifier: descriptor.identifier().to_string(),
+        };
+        match self.change_listeners.read() {
+            Ok(listeners) => listeners.iter().for_each(|l| l(&message)),
+            Err(e) => t_warn!("{}", e),
+        }
+    }
+
+    /// Registers a listener which is called whenever a watched asset changes.
+    /// Listeners are called from the watcher threads.
+    pub fn add_change_listener(&self, listener: impl Fn(&AssetDidChange) + Send + Sync + 'static) {
+        match self.change_listeners.write() {
+            Ok(mut listeners) => listeners.push(Box::new(listener)),
+            Err(e) => t_warn!("{}", e),
+        }
+    }
+
+    /// Returns the active variants, see `VirtualFileSystem::set_variants`.
+    pub fn variants(&self) -> Result<Vec<String>, AssetSystemError> {
+        Ok(self.read_vfs()?.variants().to_vec())
+    }
+
+    /// Changes the variants overlaying assets, most specific first, e.g. `["fr-FR", "fr"]` for French.
+    /// An empty list only loads the base assets, see `VirtualFileSystem::set_variants` for how assets are looked up.
+    /// Variants listeners are notified if the variants changed.
+    pub fn set_variants(
+        &self,
+        variants: impl IntoIterator<Item = impl AsRef<str>>,
+    ) -> Result<(), AssetSystemError> {
+        let mut vfs = self.write_vfs()?;
+        let previous = vfs.variants().to_vec();
+        vfs.set_variants(variants);
+        if vfs.variants() == previous.as_slice() {
+            return Ok(());
+        }
+        let message = AssetVariantsDidChange {
+            variants: vfs.variants().to_vec(),
+        };
+        drop(vfs);
+        t_info!("Asset variants changed to: {:?}", message.variants);
+        match self.variants_listeners.read() {
+            Ok(listeners) => listeners.iter().for_each(|l| l(&message)),
+            Err(e) => t_warn!("{}", e),
+        }
+        Ok(())
+    }
+
+    /// Registers a listener which is called whenever `set_variants` changes the variants.
+    pub fn add_variants_listener(
+        &self,
+        listener: impl Fn(&AssetVariantsDidChange) + Send + Sync + 'static,
+    ) {
+        match self.variants_listeners.write() {
+            Ok(mut listeners) => listeners.push(Box::new(listener)),
+            Err(e) => t_warn!("{}", e),
+        }
+    }
+
     pub fn load_files_from_directory(
         &self,
         directory: impl AsRef<Path>,
         mount_point: impl AsRef<str>,
     ) -> Result<(), AssetSystemError> {
         let mnt = VfsPhysicalMountPoint::new(&mount_point, &directory)?;
-        let mut vfs = self.vfs.write().map_err(|e| {
-            t_warn!("{}", e);
-            AssetSystemError::PoisonError
+        self.mount_directory(mnt, directory, mount_point)
+    }
+
+    /// Mounts a directory in which assets can be saved, e.g. the user data directory for save files.
+    /// The directory is created if it does not exist, see `save_asset_as_type`.
+    pub fn mount_writable_directory(
+        &self,
+        directory: impl AsRef<Path>,
+        mount_point: impl AsRef<str>,
+    ) -> Result<(), AssetSystemError> {
+        let mnt = VfsPhysicalMountPoint::new_writable(&mount_point, &directory)?;
+        self.mount_directory(mnt, directory, mount_point)
+    }
+
+    /// Mounts a directory and watches it for changes.
+    /// Changes are reported to all listeners registered using `add_change_listener`.
+    pub fn watch_files_from_directory(
+        &self,
+        directory: impl AsRef<Path>,
+        mount_point: impl AsRef<str>,
+        poll_interval: Duration,
+    ) -> Result<(), AssetSystemError> {
+        let mut mnt = VfsPhysicalMountPoint::new(&mount_point, &directory)?;
+        let listeners = Arc::clone(&self.change_listeners);
+        mnt.watch(poll_interval, move |message| match listeners.read() {
+            Ok(listeners) => listeners.iter().for_each(|l| l(&message)),
+            Err(e) => t_warn!("{}", e),
         })?;
+        self.mount_directory(mnt, directory, mount_point)
+    }
+
+    fn mount_directory(
+        &self,
+        mnt: VfsPhysicalMountPoint,
+        directory: impl AsRef<Path>,
+        mount_point: impl AsRef<str>,
+    ) -> Result<(), AssetSystemError> {
+        let mut vfs = self.write_vfs()?;
 
         if !vfs.mount(mnt) {
             t_warn!(
@@ -116,10 +397,34 @@ impl AssetSystem {
         Ok(())
     }
 
+    /// Mounts any mount point, e.g. a `MemoryMountPoint`.
+    pub fn mount(&self, mount_point: impl VfsMountPoint) -> Result<(), AssetSystemError> {
+        match self.write_vfs()?.mount(mount_point) {
+            true => Ok(()),
+            false => Err(AssetSystemError::NotMounted),
+        }
+    }
+
+    /// Enables checksum verification of every file read from archives which are loaded afterwards,
+    /// by `load_archives_from_directory` and `load_archive_from_file[_with_keys]`.
+    /// Corrupt files fail to load with `AssetArchiveError::ChecksumMismatch`, see `AssetArchiveReader::set_verify_checksums`.
+    /// Archives mounted using `mount_archive` keep the setting they were read with.
+    pub fn set_verify_archive_checksums(&self, verify_checksums: bool) {
+        self.verify_archive_checksums
+            .store(verify_checksums, Ordering::Relaxed);
+    }
+
+    pub fn verify_archive_checksums(&self) -> bool {
+        self.verify_archive_checksums.load(Ordering::Relaxed)
+    }
+
+    /// Mounts all archives in a directory with the given file extension.
+    /// Encrypted archives are decrypted using the keys supplied by `keys`, pass `NoKeys` if no archive is encrypted.
     pub fn load_archives_from_directory(
         &self,
         directory: impl AsRef<Path>,
         file_extension: impl AsRef<str>,
+        keys: &dyn KeyProvider,
     ) -> Result<(), AssetSystemError> {
         let dir = read_dir(directory.as_ref())?;
         let valid_dir_entries = dir
@@ -158,20 +463,8 @@ impl AssetSystem {
             .collect::<Vec<_>>();
 
         let mut counter = 0;
-        let mut vfs = self.vfs.write().map_err(|e| {
-            t_warn!("{}", e);
-            AssetSystemError::PoisonError
-        })?;
         for dir_entry in valid_dir_entries {
-            let archive = AssetArchive::read_from_file(dir_entry.path())?;
-            for mount_point in archive.header().mount_points() {
-                let physical_mount =
-                    ArchiveMountPoint::new(archive.path().into(), mount_point.clone());
-                if !vfs.mount(physical_mount) {
-                    t_warn!("Archive mount point was not mounted: {:#?}", archive.path());
-                }
-            }
-
+            self.load_archive_from_file_with_keys(dir_entry.path(), keys)?;
             counter += 1;
         }
         t_info!(
@@ -182,4 +475,208 @@ impl AssetSystem {
 
         Ok(())
     }
+
+    /// Mounts all mount points of an archive.
+    /// Mount points which are already mounted at the same version are skipped.
+    pub fn load_archive_from_file(&self, path: impl AsRef<Path>) -> Result<(), AssetSystemError> {
+        self.load_archive_from_file_with_keys(path, &NoKeys)
+    }
+
+    /// Mounts all mount points of an archive, see `load_archive_from_file`.
+    /// Encrypted files are decrypted using the key `keys` supplies for the archive's UUID.
+    pub fn load_archive_from_file_with_keys(
+        &self,
+        path: impl AsRef<Path>,
+        keys: &dyn KeyProvider,
+    ) -> Result<(), AssetSystemError> {
+        let archive =
+            AssetArchive::read_from_file_with_options(path, keys, self.verify_archive_checksums())?;
+        self.mount_archive(&archive)
+    }
+
+    /// Mounts all mount points of an archive which was read from any source,
+    /// e.g. an archive embedded into the binary using `AssetArchive::read_from_static`.
+    /// Mount points which are already mounted at the same version are skipped.
+    pub fn mount_archive(&self, archive: &AssetArchive) -> Result<(), AssetSystemError> {
+        let mut vfs = self.write_vfs()?;
+        for physical_mount in ArchiveMountPoint::from_archive(archive) {
+            if !vfs.mount(physical_mount) {
+                t_warn!("Archive mount point was not mounted: {:#?}", archive.path());
+            }
+        }
+        Ok(())
+    }
+
+    /// Unmounts the mount point with the given identifier and version.
+    pub fn unmount(
+        &self,
+        mount_point: impl AsRef<str>,
+        version: u64,
+    ) -> Result<(), AssetSystemError> {
+        match self.write_vfs()?.unmount(mount_point, version) {
+            true => Ok(()),
+            false => Err(AssetSystemError::NotMounted),
+        }
+    }
+
+    /// Unmounts all mount points of the archive at `path`.
+    pub fn unmount_archive(&self, path: impl AsRef<Path>) -> Result<(), AssetSystemError> {
+        match self.write_vfs()?.unmount_archive(path) {
+            0 => Err(AssetSystemError::NotMounted),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns all mounted mount points, see `VirtualFileSystem::mounted`.
+    pub fn mounted(&self) -> Result<Vec<VfsMountInfo>, AssetSystemError> {
+        Ok(self.read_vfs()?.mounted())
+    }
+
+    /// Returns the mount point which serves the asset, see `VirtualFileSystem::resolve`.
+    pub fn resolve(
+        &self,
+        mount_point: impl AsRef<str>,
+        identifier: impl AsRef<str>,
+    ) -> Result<Option<VfsMountInfo>, AssetSystemError> {
+        Ok(self.read_vfs()?.resolve(mount_point, identifier))
+    }
+
+    /// Returns the descriptors of all assets of a mount point, see `VirtualFileSystem::list`.
+    pub fn list(
+        &self,
+        mount_point: impl AsRef<str>,
+    ) -> Result<Vec<AssetDescriptor>, AssetSystemError> {
+        Ok(self.read_vfs()?.list(mount_point)?)
+    }
+
+    /// Returns the descriptors of all assets of a mount point with the given format.
+    pub fn list_with_format(
+        &self,
+        mount_point: impl AsRef<str>,
+        format: impl AsRef<str>,
+    ) -> Result<Vec<AssetDescriptor>, AssetSystemError> {
+        Ok(self.read_vfs()?.list_with_format(mount_point, format)?)
+    }
+
+    /// Returns the descriptors of all assets matching a glob pattern, see `VirtualFileSystem::glob`.
+    pub fn glob(
+        &self,
+        mount_point: impl AsRef<str>,
+        pattern: impl AsRef<str>,
+    ) -> Result<Vec<AssetDescriptor>, AssetSystemError> {
+        Ok(self.read_vfs()?.glob(mount_point, pattern)?)
+    }
+
+    /// Returns the direct dependencies of an asset.
+    pub fn dependencies(
+        &self,
+        mount_point: impl AsRef<str>,
+        identifier: impl AsRef<str>,
+    ) -> Result<Vec<AssetReference>, AssetSystemError> {
+        let descriptor = self.read_vfs()?.descriptor(mount_point, identifier)?;
+        Ok(descriptor.dependencies().to_vec())
+    }
+
+    /// Returns the descriptors of an asset and all of its direct and indirect dependencies.
+    /// Every asset follows its dependencies, the requested asset is last.
+    /// Fails if a dependency is missing or the dependencies contain a cycle.
+    pub fn dependency_order(
+        &self,
+        mount_point: impl AsRef<str>,
+        identifier: impl AsRef<str>,
+    ) -> Result<Vec<AssetDescriptor>, AssetSystemError> {
+        let vfs = self.read_vfs()?;
+        dependencies::dependency_order(&AssetReference::new(mount_point, identifier), |reference| {
+            Ok(vfs.descriptor(reference.mount(), reference.identifier())?)
+        })
+    }
+
+    /// Loads an asset and all of its dependencies in the order returned by `dependency_order`.
+    pub fn load_asset_with_dependencies(
+        &self,
+        mount_point: impl AsRef<str>,
+        identifier: impl AsRef<str>,
+    ) -> Result<Vec<(AssetDescriptor, Vec<u8>)>, AssetSystemError> {
+        let vfs = self.read_vfs()?;
+        let order = dependencies::dependency_order(
+            &AssetReference::new(mount_point, identifier),
+            |reference| Ok(vfs.descriptor(reference.mount(), reference.identifier())?),
+        )?;
+        order
+            .into_iter()
+            .map(|descriptor| {
+                let mut buffer = Vec::new();
+                vfs.read_file_into(descriptor.mount(), descriptor.identifier(), &mut buffer)?;
+                Ok((descriptor, buffer))
+            })
+            .collect()
+    }
+
+    /// Returns the mounted assets which directly depend on an asset.
+    pub fn dependents(
+        &self,
+        mount_point: impl AsRef<str>,
+        identifier: impl AsRef<str>,
+    ) -> Result<Vec<AssetDescriptor>, AssetSystemError> {
+        self.find_dependents(AssetReference::new(mount_point, identifier), false)
+    }
+
+    /// Returns the mounted assets which directly or indirectly depend on an asset.
+    /// These are the assets affected by a change of the asset, e.g. all materials using a reloaded shader.
+    pub fn transitive_dependents(
+        &self,
+        mount_point: impl AsRef<str>,
+        identifier: impl AsRef<str>,
+    ) -> Result<Vec<AssetDescriptor>, AssetSystemError> {
+        self.find_dependents(AssetReference::new(mount_point, identifier), true)
+    }
+
+    fn find_dependents(
+        &self,
+        target: AssetReference,
+        transitive: bool,
+    ) -> Result<Vec<AssetDescriptor>, AssetSystemError> {
+        let vfs = self.read_vfs()?;
+        let mut mount_points = vfs
+            .mounted()
+            .into_iter()
+            .map(|m| m.identifier)
+            .collect::<Vec<_>>();
+        mount_points.dedup();
+        let mut assets = vec![];
+        for mount_point in mount_points {
+            assets.extend(vfs.list(mount_point)?);
+        }
+        Ok(dependencies::dependents(assets, &target, transitive))
+    }
+
+    fn read_decoders(&self) -> Result<RwLockReadGuard<'_, AssetDecoderRegistry>, AssetSystemError> {
+        self.decoders.read().map_err(|e| {
+            t_warn!("{}", e);
+            AssetSystemError::PoisonError
+        })
+    }
+
+    fn write_decoders(
+        &self,
+    ) -> Result<RwLockWriteGuard<'_, AssetDecoderRegistry>, AssetSystemError> {
+        self.decoders.write().map_err(|e| {
+            t_warn!("{}", e);
+            AssetSystemError::PoisonError
+        })
+    }
+
+    fn read_vfs(&self) -> Result<RwLockReadGuard<'_, VirtualFileSystem>, AssetSystemError> {
+        self.vfs.read().map_err(|e| {
+            t_warn!("{}", e);
+            AssetSystemError::PoisonError
+        })
+    }
+
+    fn write_vfs(&self) -> Result<RwLockWriteGuard<'_, VirtualFileSystem>, AssetSystemError> {
+        self.vfs.write().map_err(|e| {
+            t_warn!("{}", e);
+            AssetSystemError::PoisonError
+        })
+    }
 }
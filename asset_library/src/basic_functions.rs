@@ -1,9 +1,15 @@
-use crate::archive::AssetArchiveCompressionFormat;
-use crate::archive::{AssetArchive, AssetArchiveBuilder, AssetArchiveMountPointBuilder};
+use crate::archive::{
+    AssetArchive, AssetArchiveBuildReport, AssetArchiveBuilder, AssetArchiveCompressionFormat,
+    AssetArchiveDictionary, AssetArchiveError, AssetArchiveMountPointBuilder,
+};
+use crate::dispatcher::Dispatcher;
+use crate::vfs::physical_mount_point::{AssetIndex, DEFAULT_INDEX_FILE_NAME};
+use crate::AssetReference;
 use std::fs;
 use std::fs::*;
 use std::io::*;
-use std::path::Path;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
 
 pub(crate) fn load_file_bin(path: impl AsRef<Path>) -> Result<Vec<u8>> {
     let mut file = File::open(path)?;
@@ -12,6 +18,42 @@ pub(crate) fn load_file_bin(path: impl AsRef<Path>) -> Result<Vec<u8>> {
     Ok(bytes)
 }
 
+/// Options of `archive_directory_with_options`.
+#[derive(Debug, Clone)]
+pub struct ArchiveDirectoryOptions {
+    pub version: u64,
+    pub compression_format: AssetArchiveCompressionFormat,
+    /// zstd compression level, 0 selects zstd's default level.
+    pub compression_level: i32,
+    /// Maximum size of the dictionary trained if `compression_format` is `ZSTDDictionary`.
+    pub dictionary_size: usize,
+    /// Number of threads compressing files, `None` uses all available cores.
+    pub threads: Option<NonZeroUsize>,
+    /// Derives the archive's UUID from its contents, see `AssetArchiveBuilder::with_content_uuid`.
+    pub content_uuid: bool,
+}
+
+impl Default for ArchiveDirectoryOptions {
+    fn default() -> Self {
+        Self {
+            version: 0,
+            compression_format: AssetArchiveCompressionFormat::ZSTD,
+            compression_level: 0,
+            dictionary_size: AssetArchiveDictionary::DEFAULT_SIZE,
+            threads: None,
+            content_uuid: false,
+        }
+    }
+}
+
+/// Archives all files of a directory, including its sub directories, into a single mount point.
+/// Files are identified by their path relative to `path`, see `collect_directory_files`.
+/// Dependencies declared in the directory's index file are written into the archive.
+///
+/// Sub directories used to be archived as mount points of their own, `config/vulkan.yaml` was written as
+/// `vulkan` into the mount point `assets.config`. It is now written as `config/vulkan` into `assets`,
+/// the same identifier a physical mount point of the directory uses. Loads of `("assets.config", "vulkan")`
+/// have to be changed to `("assets", "config/vulkan")` and archives built with the old layout rebuilt.
 pub fn archive_directory(
     path: impl AsRef<Path>,
     base_mount_point: impl AsRef<str>,
@@ -19,128 +61,311 @@ pub fn archive_directory(
     version: u64,
     compression_format: AssetArchiveCompressionFormat,
 ) -> std::result::Result<(), Box<dyn std::error::Error>> {
-    let directory = fs::read_dir(path)?;
-    let out_file = File::create(out)?;
-    let archive = AssetArchiveBuilder::new(out_file)?;
-    let archive = match add_dir_to_archive(
-        directory,
-        archive,
+    archive_directory_with_options(
+        path,
         base_mount_point,
-        version,
-        compression_format,
-    ) {
-        Ok(v) => v,
-        Err((a, e)) => {
-            println!("Error occured: {}", e);
-            a
-        }
-    };
-    archive.finish()?;
+        out,
+        &ArchiveDirectoryOptions {
+            version,
+            compression_format,
+            ..Default::default()
+        },
+    )?;
     Ok(())
 }
 
-pub fn add_dir_to_archive(
-    dir: ReadDir,
-    mut builder: AssetArchiveBuilder,
-    mount_point: impl AsRef<str>,
-    version: u64,
-    compression_format: AssetArchiveCompressionFormat,
-) -> std::result::Result<AssetArchiveBuilder, (AssetArchiveBuilder, Box<dyn std::error::Error>)> {
-    let mount_point = mount_point.as_ref();
+/// Archives a directory like `archive_directory`.
+/// With `ZSTDDictionary` a dictionary is trained over all files of the directory.
+/// Files are compressed in parallel and written in the order of their identifiers.
+/// Returns the build report, which lists the files whose contents were deduplicated.
+pub fn archive_directory_with_options(
+    path: impl AsRef<Path>,
+    base_mount_point: impl AsRef<str>,
+    out: impl AsRef<Path>,
+    options: &ArchiveDirectoryOptions,
+) -> std::result::Result<AssetArchiveBuildReport, Box<dyn std::error::Error>> {
+    let index = load_directory_index(path.as_ref())?;
+    let files = collect_directory_files(path)?
+        .into_iter()
+        .filter_map(|file| match fs::read(&file.path) {
+            Ok(v) => Some((file, v)),
+            Err(e) => {
+                println!("Could not read file: {} - {:#?}", e, file.path);
+                None
+            }
+        })
+        .collect::<Vec<_>>();
+    let mut builder = AssetArchiveBuilder::new(File::create(out)?)?
+        .with_compression_level(options.compression_level);
+    if options.content_uuid {
+        builder = builder.with_content_uuid();
+    }
+    let mnt_point = builder
+        .add_mount_point(base_mount_point, options.version)
+        .map_err(|(_, e)| e)?;
+    let samples = files.iter().map(|(_, bytes)| bytes).collect::<Vec<_>>();
+    let (mut mnt_point, compression_format) = train_dictionary(
+        mnt_point,
+        &samples,
+        options.compression_format,
+        options.dictionary_size,
+    );
 
-    let mut sub_dirs = vec![];
-    let mut files = vec![];
+    let one = NonZeroUsize::new(1).unwrap();
+    let dispatcher = Dispatcher::new(Some(one), one, options.threads, one)
+        .ok_or("Could not create the thread pool.")?;
+    let mut prepared_files = files.iter().map(|_| None).collect::<Vec<_>>();
+    dispatcher.scope(|scope| {
+        for ((file, buf), prepared) in files.iter().zip(prepared_files.iter_mut()) {
+            let mnt_point = &mnt_point;
+            let index = &index;
+            scope.spawn(move |_| {
+                *prepared = Some(mnt_point.prepare_file(
+                    &file.identifier,
+                    &file.format,
+                    &index_dependencies(index, &file.identifier),
+                    buf,
+                    compression_format,
+                ));
+            });
+        }
+    });
 
-    dir.filter_map(|e| match e {
-        Ok(v) => Some(v),
-        Err(_) => None,
-    })
-    .filter_map(|d| {
-        let name = match d.file_name().to_str() {
-            Some(v) => String::from(v),
-            None => return None,
+    for ((file, _), prepared) in files.iter().zip(prepared_files) {
+        let result = match prepared.expect("Every file is prepared within the scope.") {
+            Ok(prepared) => mnt_point.write_prepared_file(prepared),
+            Err(e) => Err((mnt_point, e)),
         };
-        Some((d, name))
-    })
-    .filter_map(|(d, n)| {
-        let md = match d.metadata() {
+        mnt_point = match result {
             Ok(v) => v,
-            Err(_) => return None,
-        };
-        Some((d, n, md))
-    })
-    .for_each(|(dir, name, md)| {
-        if md.is_dir() {
-            let fs_dir = match fs::read_dir(dir.path()) {
-                Ok(v) => v,
-                Err(_) => return,
-            };
+            Err((a, e)) => {
+                println!("Could not add file: {} - {}", e, file.identifier);
+                a
+            }
+        }
+    }
 
-            sub_dirs.push((fs_dir, dir, name, md));
-        } else if md.is_file() {
-            let fname = match dir.path().file_stem() {
-                Some(v) => match v.to_str() {
-                    Some(v) => String::from(v),
-                    None => return,
-                },
-                None => return,
-            };
+    let report = mnt_point.report().clone();
+    mnt_point.finish().finish()?;
+    Ok(report)
+}
 
-            files.push((dir, name, md, fname))
+/// Trains a dictionary if `compression_format` is `ZSTDDictionary`.
+/// Falls back to `ZSTD` if there are not enough samples to train a dictionary.
+fn train_dictionary<S: AsRef<[u8]>>(
+    mnt_point: AssetArchiveMountPointBuilder,
+    samples: &[S],
+    compression_format: AssetArchiveCompressionFormat,
+    dictionary_size: usize,
+) -> (AssetArchiveMountPointBuilder, AssetArchiveCompressionFormat) {
+    if compression_format != AssetArchiveCompressionFormat::ZSTDDictionary {
+        return (mnt_point, compression_format);
+    }
+    match mnt_point.train_dictionary(samples, dictionary_size) {
+        Ok(v) => (v, compression_format),
+        Err((v, e)) => {
+            println!("Could not train dictionary, using ZSTD instead: {}", e);
+            (v, AssetArchiveCompressionFormat::ZSTD)
         }
-    });
-
-    for (fs_dir, _, name, _) in sub_dirs {
-        let sub_mnt_point = if mount_point.is_empty() {
-            String::from(name)
-        } else {
-            String::from(mount_point) + &(String::from(".") + &name)
-        };
+    }
+}
 
-        builder =
-            match add_dir_to_archive(fs_dir, builder, sub_mnt_point, version, compression_format) {
-                Ok(v) => v,
-                Err((a, e)) => {
-                    println!("Error: {}", e);
-                    a
-                }
-            }
+/// Loads the index file of a directory, if it has one.
+fn load_directory_index(
+    path: &Path,
+) -> std::result::Result<Option<AssetIndex>, Box<dyn std::error::Error>> {
+    let index_file = path.join(DEFAULT_INDEX_FILE_NAME);
+    if !index_file.is_file() {
+        return Ok(None);
     }
+    Ok(Some(serde_yaml::from_slice(&fs::read(index_file)?)?))
+}
+
+fn index_dependencies(index: &Option<AssetIndex>, identifier: &str) -> Vec<AssetReference> {
+    index
+        .as_ref()
+        .and_then(|i| i.file(identifier))
+        .map(|f| f.dependencies().to_vec())
+        .unwrap_or_default()
+}
+
+/// A file found by `collect_directory_files`.
+pub struct DirectoryFile {
+    pub identifier: String,
+    pub format: String,
+    pub path: PathBuf,
+}
 
-    if files.is_empty() {
-        return Ok(builder);
+/// Recursively collects all files of a directory, sorted by identifier.
+/// The identifier of a file is its lower case path relative to `path` without extension, using `/` as separator.
+/// For example `meshes/Triangle.yaml` is identified as `meshes/triangle`.
+/// Physical mount points and archives built from a directory use the same identifiers.
+/// The index file of the directory, `index.yaml`, describes the other files and is not collected.
+/// Neither are temporary files of interrupted writes, see `temporary_path`.
+/// Fails if several files share an identifier, e.g. `a.yaml` and `a.png` or `Tex.png` and `tex.png`.
+pub fn collect_directory_files(path: impl AsRef<Path>) -> Result<Vec<DirectoryFile>> {
+    let files = collect_directory_sources(path)?;
+    if let Some(pair) = files
+        .windows(2)
+        .find(|pair| pair[0].identifier == pair[1].identifier)
+    {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "{:?} and {:?} are both identified as `{}`.",
+                pair[0].path, pair[1].path, pair[0].identifier
+            ),
+        ));
     }
+    Ok(files)
+}
 
-    let mut mnt_point = match builder.add_mount_point(mount_point, version) {
-        Ok(v) => v,
-        Err((a, e)) => return Err((a, Box::from(e))),
-    };
+/// Collects files like `collect_directory_files`, but keeps files which share an identifier.
+/// Imported files are identified by their importer, e.g. `triangle.vert` and `triangle.frag` are separate shaders.
+pub(crate) fn collect_directory_sources(path: impl AsRef<Path>) -> Result<Vec<DirectoryFile>> {
+    let mut files = vec![];
+    collect_files(path.as_ref(), "", &mut files)?;
+    files.sort_by(|a, b| a.identifier.cmp(&b.identifier));
+    Ok(files)
+}
 
-    for (dir, name, _, fname) in files {
-        let buf = match fs::read(dir.path()) {
+/// Returns the path a file is written to before it is renamed to `path`, so it is never read partially written.
+/// The temporary file is hidden next to `path`, e.g. `.triangle.yaml.tmp`.
+pub(crate) fn temporary_path(path: &Path) -> Option<PathBuf> {
+    let file_name = path.file_name()?.to_str()?;
+    Some(path.with_file_name(format!(".{}.tmp", file_name)))
+}
+
+fn is_temporary_file(file_name: &str) -> bool {
+    file_name.starts_with('.') && file_name.ends_with(".tmp")
+}
+
+fn collect_files(directory: &Path, prefix: &str, files: &mut Vec<DirectoryFile>) -> Result<()> {
+    for entry in fs::read_dir(directory)?.filter_map(|e| e.ok()) {
+        let md = match entry.metadata() {
             Ok(v) => v,
-            Err(e) => {
-                println!("Could not read file: {} - {}", e, name);
+            Err(_) => continue,
+        };
+        let path = entry.path();
+        if md.is_dir() {
+            let name = match entry.file_name().to_str() {
+                Some(v) => v.to_lowercase(),
+                None => continue,
+            };
+            collect_files(&path, &format!("{}{}/", prefix, name), files)?;
+        } else if md.is_file() {
+            if prefix.is_empty() && entry.file_name() == DEFAULT_INDEX_FILE_NAME {
                 continue;
             }
-        };
-        let format = match dir.path().extension() {
-            Some(v) => match v.to_str() {
-                Some(v) => String::from(v),
+            if entry.file_name().to_str().is_some_and(is_temporary_file) {
+                continue;
+            }
+            let identifier = match path.file_stem().and_then(|v| v.to_str()) {
+                Some(v) => format!("{}{}", prefix, v.to_lowercase()),
+                None => continue,
+            };
+            let format = match path.extension().and_then(|v| v.to_str()) {
+                Some(v) => v.to_lowercase(),
                 None => String::from(""),
-            },
-            None => String::from(""),
-        };
+            };
+            files.push(DirectoryFile {
+                identifier,
+                format,
+                path,
+            });
+        }
+    }
+    Ok(())
+}
 
-        mnt_point = match mnt_point.write_file(&fname, format, &buf, compression_format) {
-            Ok(v) => v,
-            Err((a, e)) => {
-                println!("Could not add file: {} - {}", e, name);
-                a
+/// Builds a patch archive which contains only the files of `path` that were added or changed compared to `base`.
+/// Files which exist in `base` but no longer in `path` are written as removed files.
+/// The patched mount point is written at `version`, which must be higher than the version of the mount point in `base`.
+/// Mounting the patch archive on top of `base` provides the contents of `path`.
+pub fn archive_directory_patch(
+    base: &AssetArchive,
+    path: impl AsRef<Path>,
+    base_mount_point: impl AsRef<str>,
+    out: impl AsRef<Path>,
+    version: u64,
+    compression_format: AssetArchiveCompressionFormat,
+) -> std::result::Result<(), Box<dyn std::error::Error>> {
+    let mount_point = base_mount_point.as_ref().to_lowercase();
+    let index = load_directory_index(path.as_ref())?;
+    let files = collect_directory_files(path)?;
+    let base_mount = base
+        .header()
+        .mount_points()
+        .iter()
+        .find(|m| m.mount_point() == mount_point);
+    if let Some(base_mount) = base_mount {
+        if *base_mount.version() >= version {
+            return Err(Box::from(AssetArchiveError::InvalidVersion));
+        }
+    }
+
+    let mut changed_files = vec![];
+    for file in files.iter() {
+        let bytes = fs::read(&file.path)?;
+        let dependencies = index_dependencies(&index, &file.identifier);
+        let base_asset = base_mount.and_then(|m| {
+            m.assets()
+                .iter()
+                .find(|a| a.asset_identifier() == file.identifier)
+        });
+        let changed = match base_asset {
+            Some(asset) => {
+                asset.asset_format() != file.format
+                    || asset.dependencies() != dependencies.as_slice()
+                    || base.read_file_from(asset)? != bytes
             }
+            None => true,
+        };
+        if changed {
+            changed_files.push((file, dependencies, bytes));
         }
     }
+    let removed_files = match base_mount {
+        Some(m) => m
+            .assets()
+            .iter()
+            .map(|a| a.asset_identifier())
+            .filter(|identifier| !files.iter().any(|f| f.identifier == *identifier))
+            .collect::<Vec<_>>(),
+        None => vec![],
+    };
 
-    builder = mnt_point.finish();
-    Ok(builder)
+    let mut builder = AssetArchiveBuilder::new(File::create(out)?)?;
+    if !changed_files.is_empty() || !removed_files.is_empty() {
+        let mnt_point = builder
+            .add_mount_point(&mount_point, version)
+            .map_err(|(_, e)| e)?;
+        let samples = changed_files
+            .iter()
+            .map(|(_, _, bytes)| bytes)
+            .collect::<Vec<_>>();
+        let (mut mnt_point, compression_format) = train_dictionary(
+            mnt_point,
+            &samples,
+            compression_format,
+            AssetArchiveDictionary::DEFAULT_SIZE,
+        );
+        for (file, dependencies, bytes) in changed_files {
+            mnt_point = mnt_point
+                .write_file_with_dependencies(
+                    &file.identifier,
+                    &file.format,
+                    &dependencies,
+                    &bytes,
+                    compression_format,
+                )
+                .map_err(|(_, e)| e)?;
+        }
+        for identifier in removed_files {
+            mnt_point = mnt_point.remove_file(identifier);
+        }
+        builder = mnt_point.finish();
+    }
+    builder.finish()?;
+    Ok(())
 }
@@ -1,12 +1,15 @@
-use std::usize;
+use std::{fmt::Display, usize};
 
 use serde::*;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct AssetDescriptor {
     mount: String,
     identifier: String,
     format: String,
+    /// Assets which are required to use this asset.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    dependencies: Vec<AssetReference>,
 }
 
 impl AssetDescriptor {
@@ -15,9 +18,15 @@ impl AssetDescriptor {
             mount: mount.to_lowercase(),
             identifier: identifier.to_lowercase(),
             format: format.to_lowercase(),
+            dependencies: vec![],
         }
     }
 
+    pub fn with_dependencies(mut self, dependencies: Vec<AssetReference>) -> Self {
+        self.dependencies = dependencies;
+        self
+    }
+
     /// Get a reference to the asset descriptor's identifier.
     pub fn identifier(&self) -> &str {
         self.identifier.as_str()
@@ -32,4 +41,46 @@ impl AssetDescriptor {
     pub fn format(&self) -> &str {
         self.format.as_str()
     }
+
+    /// Get a reference to the asset descriptor's dependencies.
+    pub fn dependencies(&self) -> &[AssetReference] {
+        self.dependencies.as_slice()
+    }
+
+    /// Returns a reference to the described asset.
+    pub fn reference(&self) -> AssetReference {
+        AssetReference::new(&self.mount, &self.identifier)
+    }
+}
+
+/// Refers to an asset by mount point and identifier, e.g. a dependency of another asset.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct AssetReference {
+    mount: String,
+    identifier: String,
+}
+
+impl AssetReference {
+    pub fn new(mount: impl AsRef<str>, identifier: impl AsRef<str>) -> Self {
+        Self {
+            mount: mount.as_ref().to_lowercase(),
+            identifier: identifier.as_ref().to_lowercase(),
+        }
+    }
+
+    /// Get a reference to the asset reference's mount.
+    pub fn mount(&self) -> &str {
+        self.mount.as_str()
+    }
+
+    /// Get a reference to the asset reference's identifier.
+    pub fn identifier(&self) -> &str {
+        self.identifier.as_str()
+    }
+}
+
+impl Display for AssetReference {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.mount, self.identifier)
+    }
 }
@@ -0,0 +1,103 @@
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use shaderc::{CompileOptions, Compiler, ResolvedInclude, ShaderKind};
+
+use super::{AssetImporter, ImportError, ImportSource};
+
+/// Extensions of GLSL sources, the extension selects the shader stage.
+pub const GLSL_EXTENSIONS: [&str; 6] = ["vert", "frag", "comp", "geom", "tesc", "tese"];
+
+/// Compiles GLSL sources into SPIR-V.
+/// The stage is part of the output identifier, `triangle.vert` is imported as `triangle_vert.spv`.
+/// Includes are resolved relative to the including file.
+pub struct GlslImporter;
+
+impl AssetImporter for GlslImporter {
+    fn name(&self) -> &str {
+        "glsl_spirv"
+    }
+
+    fn version(&self) -> u32 {
+        1
+    }
+
+    fn output_format(&self, _source: &ImportSource) -> String {
+        String::from("spv")
+    }
+
+    fn output_identifier(&self, source: &ImportSource) -> String {
+        format!("{}_{}", source.identifier, source.format)
+    }
+
+    fn source_dependencies(&self, source: &ImportSource) -> Result<Vec<PathBuf>, ImportError> {
+        let mut dependencies = Vec::new();
+        let mut visited = HashSet::new();
+        let mut pending = includes(source.path, std::str::from_utf8(source.bytes)?);
+        while let Some(path) = pending.pop() {
+            if !visited.insert(path.clone()) {
+                continue;
+            }
+            pending.extend(includes(&path, &fs::read_to_string(&path)?));
+            dependencies.push(path);
+        }
+        Ok(dependencies)
+    }
+
+    fn import(&self, source: &ImportSource) -> Result<Vec<u8>, ImportError> {
+        let kind = match source.format {
+            "vert" => ShaderKind::Vertex,
+            "frag" => ShaderKind::Fragment,
+            "comp" => ShaderKind::Compute,
+            "geom" => ShaderKind::Geometry,
+            "tesc" => ShaderKind::TessControl,
+            "tese" => ShaderKind::TessEvaluation,
+            _ => return Err(Box::from("Unknown shader stage.")),
+        };
+        let compiler = Compiler::new().ok_or("Could not create the shader compiler.")?;
+        let mut options = CompileOptions::new().ok_or("Could not create the compile options.")?;
+        options.set_include_callback(|name, _, requesting, _| {
+            let path = include_path(Path::new(requesting), name);
+            match fs::read_to_string(&path) {
+                Ok(content) => Ok(ResolvedInclude {
+                    resolved_name: path.to_string_lossy().into_owned(),
+                    content,
+                }),
+                Err(e) => Err(format!("{}: {:?}", e, path)),
+            }
+        });
+        let artifact = compiler.compile_into_spirv(
+            std::str::from_utf8(source.bytes)?,
+            kind,
+            &source.path.to_string_lossy(),
+            "main",
+            Some(&options),
+        )?;
+        Ok(artifact.as_binary_u8().to_vec())
+    }
+}
+
+/// Paths of the files included by a GLSL source, `#include "name"` and `#include <name>`.
+fn includes(path: &Path, source: &str) -> Vec<PathBuf> {
+    source
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("#include"))
+        .filter_map(|name| {
+            let name = name.trim();
+            name.strip_prefix('"')
+                .and_then(|n| n.strip_suffix('"'))
+                .or_else(|| name.strip_prefix('<').and_then(|n| n.strip_suffix('>')))
+        })
+        .map(|name| include_path(path, name))
+        .collect()
+}
+
+fn include_path(requesting: &Path, name: &str) -> PathBuf {
+    requesting
+        .parent()
+        .map(|p| p.join(name))
+        .unwrap_or_else(|| PathBuf::from(name))
+}
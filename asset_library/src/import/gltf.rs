@@ -0,0 +1,168 @@
+use std::path::PathBuf;
+
+use ::gltf::{
+    accessor::{DataType, Dimensions},
+    buffer::Source,
+    mesh::Mode,
+    Gltf, Semantic,
+};
+use mesh::{
+    Accessor, Attribute, AttributePurpose, Buffer, BufferElementFormat, BufferView, Mesh,
+    Primitive, PrimitiveRenderingMode,
+};
+
+use super::{AssetImporter, ImportError, ImportSource};
+
+/// Converts the meshes of glTF and GLB files into a single `mesh::Mesh`, written as CBOR.
+/// The primitives of all meshes are merged, their vertex data is tightly packed into one buffer.
+/// Materials, images, skins and animations are not imported.
+pub struct GltfMeshImporter;
+
+impl AssetImporter for GltfMeshImporter {
+    fn name(&self) -> &str {
+        "gltf_mesh"
+    }
+
+    fn version(&self) -> u32 {
+        1
+    }
+
+    fn output_format(&self, _source: &ImportSource) -> String {
+        String::from("cbor")
+    }
+
+    fn source_dependencies(&self, source: &ImportSource) -> Result<Vec<PathBuf>, ImportError> {
+        let gltf = Gltf::from_slice(source.bytes)?;
+        let base = source.path.parent().map(PathBuf::from).unwrap_or_default();
+        Ok(gltf
+            .buffers()
+            .filter_map(|b| match b.source() {
+                Source::Uri(uri) if !uri.starts_with("data:") => Some(base.join(uri)),
+                _ => None,
+            })
+            .collect())
+    }
+
+    fn import(&self, source: &ImportSource) -> Result<Vec<u8>, ImportError> {
+        let Gltf { document, blob } = Gltf::from_slice(source.bytes)?;
+        let buffers = ::gltf::import_buffers(&document, source.path.parent(), blob)?;
+
+        let mut data = Vec::new();
+        let mut views = Vec::new();
+        let mut accessors = Vec::new();
+        let mut primitives = Vec::new();
+        for mesh in document.meshes() {
+            for primitive in mesh.primitives() {
+                let mut pack = |accessor: ::gltf::Accessor| -> Result<u32, ImportError> {
+                    let view = accessor
+                        .view()
+                        .ok_or("Sparse accessors are not supported.")?;
+                    let buffer = &buffers[view.buffer().index()].0;
+                    let element_size = accessor.size();
+                    let stride = view.stride().unwrap_or(element_size);
+                    let start = view.offset() + accessor.offset();
+                    let offset = data.len();
+                    for i in 0..accessor.count() {
+                        let element = start + i * stride;
+                        data.extend_from_slice(
+                            buffer
+                                .get(element..element + element_size)
+                                .ok_or("Accessor lies outside of its buffer.")?,
+                        );
+                    }
+                    views.push(BufferView::new(
+                        0,
+                        offset as u32,
+                        (data.len() - offset) as u32,
+                        element_size as u32,
+                    ));
+                    accessors.push(Accessor::new(
+                        views.len() as u32 - 1,
+                        0,
+                        accessor.count() as u32,
+                        element_format(&accessor)?,
+                    ));
+                    Ok(accessors.len() as u32 - 1)
+                };
+
+                let mut attributes = Vec::new();
+                for (semantic, accessor) in primitive.attributes() {
+                    attributes.push(Attribute::new(pack(accessor)?, purpose(&semantic)));
+                }
+                let indices = primitive.indices().map(&mut pack).transpose()?;
+                primitives.push(Primitive::new(
+                    attributes,
+                    indices,
+                    rendering_mode(primitive.mode()),
+                ));
+            }
+        }
+        if primitives.is_empty() {
+            return Err(Box::from("The file contains no meshes."));
+        }
+
+        let mesh = Mesh::new(vec![Buffer::new(data)], views, accessors, primitives);
+        Ok(serde_cbor::to_vec(&mesh)?)
+    }
+}
+
+fn element_format(accessor: &::gltf::Accessor) -> Result<BufferElementFormat, ImportError> {
+    use BufferElementFormat::*;
+    let format = match (accessor.dimensions(), accessor.data_type()) {
+        (Dimensions::Scalar, DataType::I8) => I8x1,
+        (Dimensions::Scalar, DataType::U8) => U8x1,
+        (Dimensions::Scalar, DataType::I16) => I16x1,
+        (Dimensions::Scalar, DataType::U16) => U16x1,
+        (Dimensions::Scalar, DataType::U32) => U32x1,
+        (Dimensions::Scalar, DataType::F32) => F32x1,
+        (Dimensions::Vec2, DataType::I8) => I8x2,
+        (Dimensions::Vec2, DataType::U8) => U8x2,
+        (Dimensions::Vec2, DataType::I16) => I16x2,
+        (Dimensions::Vec2, DataType::U16) => U16x2,
+        (Dimensions::Vec2, DataType::U32) => U32x2,
+        (Dimensions::Vec2, DataType::F32) => F32x2,
+        (Dimensions::Vec3, DataType::I8) => I8x3,
+        (Dimensions::Vec3, DataType::U8) => U8x3,
+        (Dimensions::Vec3, DataType::I16) => I16x3,
+        (Dimensions::Vec3, DataType::U16) => U16x3,
+        (Dimensions::Vec3, DataType::U32) => U32x3,
+        (Dimensions::Vec3, DataType::F32) => F32x3,
+        (Dimensions::Vec4, DataType::I8) => I8x4,
+        (Dimensions::Vec4, DataType::U8) => U8x4,
+        (Dimensions::Vec4, DataType::I16) => I16x4,
+        (Dimensions::Vec4, DataType::U16) => U16x4,
+        (Dimensions::Vec4, DataType::U32) => U32x4,
+        (Dimensions::Vec4, DataType::F32) => F32x4,
+        (Dimensions::Mat2, DataType::F32) => F32x4,
+        (Dimensions::Mat3, DataType::F32) => F32x9,
+        (Dimensions::Mat4, DataType::F32) => F32x16,
+        (dimensions, data_type) => {
+            return Err(format!("Unsupported accessor {:?} of {:?}.", dimensions, data_type).into())
+        }
+    };
+    Ok(format)
+}
+
+fn purpose(semantic: &Semantic) -> AttributePurpose {
+    match semantic {
+        Semantic::Positions => AttributePurpose::Position,
+        Semantic::Normals => AttributePurpose::Normals,
+        Semantic::Tangents => AttributePurpose::Tangents,
+        Semantic::Colors(_) => AttributePurpose::Colors,
+        Semantic::TexCoords(_) => AttributePurpose::TexCoords,
+        Semantic::Joints(_) => AttributePurpose::Undefined,
+        Semantic::Weights(_) => AttributePurpose::Undefined,
+    }
+}
+
+fn rendering_mode(mode: Mode) -> PrimitiveRenderingMode {
+    match mode {
+        Mode::Points => PrimitiveRenderingMode::Points,
+        Mode::Lines => PrimitiveRenderingMode::Lines,
+        Mode::LineLoop => PrimitiveRenderingMode::LineLoop,
+        Mode::LineStrip => PrimitiveRenderingMode::LineStrip,
+        Mode::Triangles => PrimitiveRenderingMode::Triangles,
+        Mode::TriangleStrip => PrimitiveRenderingMode::TriangleStrip,
+        Mode::TriangleFan => PrimitiveRenderingMode::TriangleFan,
+    }
+}
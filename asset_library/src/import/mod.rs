@@ -0,0 +1,301 @@
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    fmt::Display,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use xxhash_rust::xxh3::Xxh3;
+
+use crate::{
+    collect_directory_sources, temporary_path, vfs::physical_mount_point::DEFAULT_INDEX_FILE_NAME,
+};
+
+#[cfg(feature = "import_glsl")]
+pub mod glsl;
+#[cfg(feature = "import_gltf")]
+pub mod gltf;
+#[cfg(feature = "import_png")]
+pub mod png;
+pub mod texture;
+
+pub type ImportError = Box<dyn Error + Send + Sync>;
+
+/// A source file handed to an `AssetImporter`.
+pub struct ImportSource<'a> {
+    /// Identifier of the source file, see `collect_directory_files`.
+    pub identifier: &'a str,
+    /// Lower case extension of the source file.
+    pub format: &'a str,
+    pub path: &'a Path,
+    pub bytes: &'a [u8],
+}
+
+/// Converts source files of one or more extensions into the asset loaded at runtime, e.g. glTF files into meshes.
+/// Outputs are cached by `import_directory`, keyed by the hash of the source and the importer's name and version.
+pub trait AssetImporter: Send + Sync + 'static {
+    /// Name of the importer, part of the cache key.
+    fn name(&self) -> &str;
+
+    /// Increment whenever the output of the same source changes, cached outputs of older versions are not reused.
+    fn version(&self) -> u32;
+
+    /// Format of the imported asset.
+    fn output_format(&self, source: &ImportSource) -> String;
+
+    /// Identifier of the imported asset, by default the identifier of the source.
+    fn output_identifier(&self, source: &ImportSource) -> String {
+        source.identifier.to_string()
+    }
+
+    /// Other files read by `import`, e.g. external buffers of a glTF file.
+    /// Their contents are part of the cache key.
+    fn source_dependencies(&self, _source: &ImportSource) -> Result<Vec<PathBuf>, ImportError> {
+        Ok(Vec::new())
+    }
+
+    fn import(&self, source: &ImportSource) -> Result<Vec<u8>, ImportError>;
+}
+
+/// Importers by source extension, extensions are case insensitive.
+/// By default YAML files are passed through unchanged. The glTF, GLSL and PNG importers require
+/// the `import_gltf`, `import_glsl` and `import_png` features.
+pub struct AssetImporterRegistry {
+    importers: HashMap<String, Box<dyn AssetImporter>>,
+}
+
+impl Default for AssetImporterRegistry {
+    fn default() -> Self {
+        let mut registry = Self::empty();
+        registry.register("yaml", YamlPassthroughImporter);
+        registry.register("yml", YamlPassthroughImporter);
+        #[cfg(feature = "import_gltf")]
+        {
+            registry.register("gltf", gltf::GltfMeshImporter);
+            registry.register("glb", gltf::GltfMeshImporter);
+        }
+        #[cfg(feature = "import_glsl")]
+        for extension in glsl::GLSL_EXTENSIONS {
+            registry.register(extension, glsl::GlslImporter);
+        }
+        #[cfg(feature = "import_png")]
+        registry.register("png", png::PngTextureImporter);
+        registry
+    }
+}
+
+impl AssetImporterRegistry {
+    /// A registry without any importers, all files are copied unchanged.
+    pub fn empty() -> Self {
+        Self {
+            importers: Default::default(),
+        }
+    }
+
+    /// Registers an importer, replacing any importer registered for `extension`.
+    pub fn register(&mut self, extension: impl AsRef<str>, importer: impl AssetImporter) {
+        self.importers
+            .insert(extension.as_ref().to_lowercase(), Box::new(importer));
+    }
+
+    pub fn importer(&self, extension: &str) -> Option<&dyn AssetImporter> {
+        self.importers
+            .get(&extension.to_lowercase())
+            .map(|i| i.as_ref())
+    }
+}
+
+/// Validates YAML files and writes them unchanged, so syntax errors are reported when building instead of when loading.
+pub struct YamlPassthroughImporter;
+
+impl AssetImporter for YamlPassthroughImporter {
+    fn name(&self) -> &str {
+        "yaml_passthrough"
+    }
+
+    fn version(&self) -> u32 {
+        1
+    }
+
+    fn output_format(&self, source: &ImportSource) -> String {
+        source.format.to_string()
+    }
+
+    fn import(&self, source: &ImportSource) -> Result<Vec<u8>, ImportError> {
+        serde_yaml::from_slice::<serde_yaml::Value>(source.bytes)?;
+        Ok(source.bytes.to_vec())
+    }
+}
+
+/// A source file which could not be imported.
+#[derive(Debug)]
+pub struct AssetImportError {
+    pub path: PathBuf,
+    pub error: ImportError,
+}
+
+impl Display for AssetImportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Could not import {:?}: {}", self.path, self.error)
+    }
+}
+impl Error for AssetImportError {}
+
+/// Identifiers of the assets written by `import_directory`.
+#[derive(Debug, Clone, Default)]
+pub struct AssetImportReport {
+    /// Assets imported from their source.
+    pub imported: Vec<String>,
+    /// Assets taken from the cache, their sources did not change.
+    pub cached: Vec<String>,
+    /// Files without importer, copied unchanged.
+    pub copied: Vec<String>,
+    /// Assets of a previous import whose source no longer exists.
+    pub removed: Vec<String>,
+}
+
+/// Imports all files of a directory into `out`, which can then be archived with `archive_directory`.
+/// Files are imported by the importer registered for their extension, files without importer are copied unchanged.
+/// With a `cache` directory, the outputs of unchanged sources are reused instead of imported again.
+/// Cache entries are never evicted, delete the cache directory to reclaim its space.
+/// `out` is owned by the import: files in it which were not written by this import are deleted.
+/// The index file of `path` is copied unchanged.
+/// Unchanged outputs are not rewritten, their modification time is kept.
+pub fn import_directory(
+    path: impl AsRef<Path>,
+    out: impl AsRef<Path>,
+    importers: &AssetImporterRegistry,
+    cache: Option<&Path>,
+) -> Result<AssetImportReport, Box<dyn Error>> {
+    let path = path.as_ref();
+    let out = out.as_ref();
+    fs::create_dir_all(out)?;
+    if let Some(cache) = cache {
+        fs::create_dir_all(cache)?;
+    }
+
+    let mut report = AssetImportReport::default();
+    let mut sources = HashMap::<String, PathBuf>::new();
+    let mut written = HashSet::new();
+    for file in collect_directory_sources(path)? {
+        let bytes = fs::read(&file.path)?;
+        let source = ImportSource {
+            identifier: &file.identifier,
+            format: &file.format,
+            path: &file.path,
+            bytes: &bytes,
+        };
+        let (identifier, format, output) = match importers.importer(&file.format) {
+            Some(importer) => {
+                let identifier = importer.output_identifier(&source);
+                let (output, cached) =
+                    import_file(importer, &source, cache).map_err(|error| AssetImportError {
+                        path: file.path.clone(),
+                        error,
+                    })?;
+                match cached {
+                    true => report.cached.push(identifier.clone()),
+                    false => report.imported.push(identifier.clone()),
+                }
+                (identifier, importer.output_format(&source), output)
+            }
+            None => {
+                report.copied.push(file.identifier.clone());
+                (file.identifier.clone(), file.format.clone(), bytes.clone())
+            }
+        };
+
+        if let Some(other) = sources.insert(identifier.clone(), file.path.clone()) {
+            return Err(Box::new(AssetImportError {
+                path: file.path,
+                error: format!(
+                    "{:?} is imported as the same asset `{}`.",
+                    other, identifier
+                )
+                .into(),
+            }));
+        }
+        let output_path = match format.is_empty() {
+            true => out.join(&identifier),
+            false => out.join(format!("{}.{}", identifier, format)),
+        };
+        if fs::read(&output_path).ok().as_deref() != Some(output.as_slice()) {
+            if let Some(parent) = output_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            write_file_atomic(&output_path, &output)?;
+        }
+        written.insert(output_path);
+    }
+
+    for file in collect_directory_sources(out)? {
+        if !written.contains(&file.path) {
+            fs::remove_file(&file.path)?;
+            report.removed.push(file.identifier);
+        }
+    }
+
+    // The index file is not collected, it is copied so archives of `out` keep the declared dependencies.
+    let out_index = out.join(DEFAULT_INDEX_FILE_NAME);
+    match fs::read(path.join(DEFAULT_INDEX_FILE_NAME)) {
+        Ok(index) => {
+            if fs::read(&out_index).ok() != Some(index.clone()) {
+                write_file_atomic(&out_index, &index)?;
+            }
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            if out_index.is_file() {
+                fs::remove_file(&out_index)?;
+            }
+        }
+        Err(e) => return Err(Box::new(e)),
+    }
+    Ok(report)
+}
+
+/// Imports a file, or loads its output from the cache. Returns true if the output was cached.
+fn import_file(
+    importer: &dyn AssetImporter,
+    source: &ImportSource,
+    cache: Option<&Path>,
+) -> Result<(Vec<u8>, bool), ImportError> {
+    let cache_path = match cache {
+        Some(cache) => Some(cache.join(format!("{:032x}", cache_key(importer, source)?))),
+        None => None,
+    };
+    if let Some(bytes) = cache_path.as_ref().and_then(|p| fs::read(p).ok()) {
+        return Ok((bytes, true));
+    }
+    let output = importer.import(source)?;
+    if let Some(cache_path) = cache_path {
+        write_file_atomic(&cache_path, &output)?;
+    }
+    Ok((output, false))
+}
+
+fn cache_key(importer: &dyn AssetImporter, source: &ImportSource) -> Result<u128, ImportError> {
+    let mut hasher = Xxh3::new();
+    hasher.update(importer.name().as_bytes());
+    hasher.update(&[0]);
+    hasher.update(&importer.version().to_le_bytes());
+    hasher.update(source.format.as_bytes());
+    hasher.update(&[0]);
+    hasher.update(&(source.bytes.len() as u64).to_le_bytes());
+    hasher.update(source.bytes);
+    for dependency in importer.source_dependencies(source)? {
+        let bytes = fs::read(&dependency)?;
+        hasher.update(dependency.to_string_lossy().as_bytes());
+        hasher.update(&[0]);
+        hasher.update(&(bytes.len() as u64).to_le_bytes());
+        hasher.update(&bytes);
+    }
+    Ok(hasher.digest128())
+}
+
+/// Writes to a temporary file first, so concurrent builds never read partially written files.
+fn write_file_atomic(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    let temp = temporary_path(path).ok_or(std::io::ErrorKind::InvalidInput)?;
+    fs::write(&temp, bytes)?;
+    fs::rename(&temp, path)
+}
@@ -0,0 +1,49 @@
+use ::png::{ColorType, Decoder, Transformations};
+
+use super::{
+    texture::{Texture, TextureFormat},
+    AssetImporter, ImportError, ImportSource,
+};
+
+/// Decodes PNG files into `Texture`s with RGBA8 pixels, written as CBOR.
+pub struct PngTextureImporter;
+
+impl AssetImporter for PngTextureImporter {
+    fn name(&self) -> &str {
+        "png_texture"
+    }
+
+    fn version(&self) -> u32 {
+        1
+    }
+
+    fn output_format(&self, _source: &ImportSource) -> String {
+        String::from("cbor")
+    }
+
+    fn import(&self, source: &ImportSource) -> Result<Vec<u8>, ImportError> {
+        let mut decoder = Decoder::new(source.bytes);
+        // Expands palettes and low bit depths, strips 16 bit channels to 8 bit.
+        decoder.set_transformations(Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info()?;
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buffer)?;
+        buffer.truncate(info.buffer_size());
+
+        let pixels = match info.color_type {
+            ColorType::Rgba => buffer,
+            ColorType::Rgb => buffer
+                .chunks_exact(3)
+                .flat_map(|p| [p[0], p[1], p[2], u8::MAX])
+                .collect(),
+            ColorType::GrayscaleAlpha => buffer
+                .chunks_exact(2)
+                .flat_map(|p| [p[0], p[0], p[0], p[1]])
+                .collect(),
+            ColorType::Grayscale => buffer.iter().flat_map(|&p| [p, p, p, u8::MAX]).collect(),
+            ColorType::Indexed => return Err(Box::from("Indexed colors were not expanded.")),
+        };
+        let texture = Texture::new(info.width, info.height, TextureFormat::Rgba8Srgb, pixels);
+        Ok(serde_cbor::to_vec(&texture)?)
+    }
+}
@@ -0,0 +1,45 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum TextureFormat {
+    /// 8 bit per channel, red, green, blue and alpha. sRGB encoded.
+    Rgba8Srgb,
+}
+
+/// Uncompressed texture as written by the PNG importer, load it with `load_asset_as_type::<Texture>`.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct Texture {
+    width: u32,
+    height: u32,
+    format: TextureFormat,
+    /// Rows from top to bottom, without padding.
+    #[serde(with = "serde_bytes")]
+    pixels: Vec<u8>,
+}
+
+impl Texture {
+    pub const fn new(width: u32, height: u32, format: TextureFormat, pixels: Vec<u8>) -> Self {
+        Self {
+            width,
+            height,
+            format,
+            pixels,
+        }
+    }
+
+    pub const fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub const fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub const fn format(&self) -> TextureFormat {
+        self.format
+    }
+
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+}
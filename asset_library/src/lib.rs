@@ -6,7 +6,9 @@ mod test;
 pub(crate) mod basic_functions;
 
 pub mod archive;
+pub mod asset_cache;
 pub mod asset_system;
+pub mod import;
 pub mod vfs;
 
 pub mod format;
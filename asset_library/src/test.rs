@@ -1,8 +1,27 @@
 use crate::{
     archive::*,
-    vfs::{physical_mount_point::*, *},
+    asset_cache::*,
+    asset_system::*,
+    dispatcher::Dispatcher,
+    import::*,
+    vfs::{
+        archive_mount_point::ArchiveMountPoint, error::VfsError,
+        memory_mount_point::MemoryMountPoint, physical_mount_point::*, *,
+    },
+    AssetDescriptor, AssetReference,
+};
+use std::{
+    collections::HashMap,
+    fs::File,
+    num::NonZeroUsize,
+    path::PathBuf,
+    sync::mpsc::channel,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
 };
-use std::{fs::File, path::PathBuf};
 
 #[test]
 fn test_vfs() {
@@ -10,7 +29,9 @@ fn test_vfs() {
     let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
     d.push("test_files/physical");
     let mount = VfsPhysicalMountPoint::new(&"configs", &d).unwrap();
-    let _index = mount.asset_index().as_ref().unwrap();
+    let index = mount.asset_index().unwrap();
+    assert!(index.has_file("test"));
+    assert_eq!(index.file("test").unwrap().format(), "yaml");
     assert!(vfs.mount(mount));
 
     vfs.read_file("configs", "test").unwrap();
@@ -58,3 +79,1917 @@ fn test_archive_builder() {
     assert_eq!(first_blob, random_data);
     assert_eq!(second_blob, random_data);
 }
+
+#[test]
+fn test_physical_mount_point_watch() {
+    let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    d.push("../tmp/watched");
+    let _ = std::fs::remove_dir_all(&d);
+    std::fs::create_dir_all(&d).unwrap();
+    std::fs::write(d.join("config.yaml"), "value: 0").unwrap();
+
+    let (sender, receiver) = channel();
+    let mut mount = VfsPhysicalMountPoint::new(&"Watched", &d).unwrap();
+    mount
+        .watch(Duration::from_millis(10), move |message| {
+            sender.send(message).unwrap();
+        })
+        .unwrap();
+    assert!(mount.is_watched());
+
+    std::fs::write(d.join("config.yaml"), "value: 10").unwrap();
+    let message = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(message.mount, "watched");
+    assert_eq!(message.identifier, "config");
+
+    std::fs::remove_file(d.join("config.yaml")).unwrap();
+    let message = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(message.identifier, "config");
+
+    // Files in sub directories are watched and indexed as well.
+    std::fs::create_dir_all(d.join("meshes")).unwrap();
+    std::fs::write(d.join("meshes/quad.yaml"), "vertices: 4").unwrap();
+    let message = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(message.identifier, "meshes/quad");
+    assert!(mount.has_file("meshes/quad"));
+
+    // Changes of the index file are reported for the files whose descriptors changed.
+    let quad = AssetDescriptor::new("watched".into(), "meshes/quad".into(), "yaml".into())
+        .with_dependencies(vec![AssetReference::new("watched", "config")]);
+    let mut index = std::collections::BTreeMap::new();
+    index.insert("files", vec![quad.clone()]);
+    std::fs::write(d.join("index.yaml"), serde_yaml::to_string(&index).unwrap()).unwrap();
+    let message = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(message.identifier, "meshes/quad");
+    assert_eq!(mount.get_asset_descriptor("meshes/quad"), Some(quad));
+    assert!(receiver.recv_timeout(Duration::from_millis(100)).is_err());
+}
+
+#[test]
+fn test_archive_reader_concurrent_reads() {
+    let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    d.push("../tmp/");
+    std::fs::create_dir_all(d.clone()).unwrap();
+    d.push("reader.mtra");
+
+    let blobs = (0..32)
+        .map(|i| (0..1024 + i).map(|_| rand::random()).collect::<Vec<u8>>())
+        .collect::<Vec<_>>();
+    let mut mount_point = AssetArchiveBuilder::new(File::create(d.clone()).unwrap())
+        .unwrap()
+        .add_mount_point("blobs", 0)
+        .unwrap();
+    for (i, blob) in blobs.iter().enumerate() {
+        let compression = match i % 3 {
+            0 => AssetArchiveCompressionFormat::None,
+            1 => AssetArchiveCompressionFormat::LZ4,
+            _ => AssetArchiveCompressionFormat::ZSTD,
+        };
+        mount_point = mount_point
+            .write_file(format!("blob{}", i), "blob", blob, compression)
+            .unwrap();
+    }
+    mount_point.finish().finish().unwrap();
+
+    let archive = AssetArchive::read_from_file(d).unwrap();
+    let headers = archive.header().mount_points()[0].assets();
+
+    // Uncompressed files are accessible without copying.
+    let stored = archive.reader().uncompressed_file(&headers[0]).unwrap();
+    assert_eq!(stored, Some(blobs[0].as_slice()));
+    assert_eq!(
+        archive.reader().uncompressed_file(&headers[1]).unwrap(),
+        None
+    );
+
+    std::thread::scope(|scope| {
+        for _ in 0..4 {
+            scope.spawn(|| {
+                let mut buffer = Vec::new();
+                for (header, blob) in headers.iter().zip(blobs.iter()) {
+                    archive
+                        .reader()
+                        .read_file_into(header, &mut buffer)
+                        .unwrap();
+                    assert_eq!(&buffer, blob);
+                }
+            });
+        }
+    });
+}
+
+#[test]
+fn test_async_loading() {
+    let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    d.push("../tmp/async_archives");
+    let _ = std::fs::remove_dir_all(&d);
+    std::fs::create_dir_all(&d).unwrap();
+
+    let random_data = (0..65536).map(|_| rand::random()).collect::<Vec<u8>>();
+    AssetArchiveBuilder::new(File::create(d.join("async.harchive")).unwrap())
+        .unwrap()
+        .add_mount_point("async", 0)
+        .unwrap()
+        .write_file(
+            "blob",
+            "bin",
+            &random_data,
+            AssetArchiveCompressionFormat::LZ4,
+        )
+        .unwrap()
+        .write_file(
+            "config",
+            "yaml",
+            b"width: 1024\nheight: 768\n",
+            AssetArchiveCompressionFormat::ZSTD,
+        )
+        .unwrap()
+        .finish()
+        .finish()
+        .unwrap();
+
+    let asset_system = Arc::new(AssetSystem::default());
+    asset_system
+        .load_archives_from_directory(&d, "harchive", &NoKeys)
+        .unwrap();
+    let one = NonZeroUsize::new(1).unwrap();
+    let dispatcher = Arc::new(Dispatcher::new(Some(one), one, Some(one), one).unwrap());
+
+    let blob = asset_system.load_asset_as_blob_async(&dispatcher, "async", "blob");
+    let config = asset_system.load_asset_as_type_async::<HashMap<String, u32>>(
+        &dispatcher,
+        "async",
+        "config",
+    );
+    let missing = asset_system.load_asset_as_blob_async(&dispatcher, "async", "missing");
+
+    let (sender, receiver) = channel();
+    dispatcher.spawn_async(async move {
+        let blob = blob.await.unwrap().unwrap();
+        let config = config.await.unwrap().unwrap();
+        let missing = missing.await.unwrap();
+        sender.send((blob, config, missing.is_err())).unwrap();
+    });
+    let ((blob, descriptor), config, missing_failed) =
+        receiver.recv_timeout(Duration::from_secs(10)).unwrap();
+    assert_eq!(blob, random_data);
+    assert_eq!(descriptor.format(), "bin");
+    assert_eq!(config["width"], 1024);
+    assert_eq!(config["height"], 768);
+    assert!(missing_failed);
+}
+
+#[test]
+fn test_archive_verify() {
+    let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    d.push("../tmp/");
+    std::fs::create_dir_all(d.clone()).unwrap();
+    d.push("verify.mtra");
+
+    let blob = (0..4096).map(|_| rand::random()).collect::<Vec<u8>>();
+    AssetArchiveBuilder::new(File::create(d.clone()).unwrap())
+        .unwrap()
+        .add_mount_point("blobs", 0)
+        .unwrap()
+        .write_file("intact", "blob", &blob, AssetArchiveCompressionFormat::None)
+        .unwrap()
+        // Equal bytes would be deduplicated with the intact file.
+        .write_file(
+            "corrupt0",
+            "blob",
+            &blob[..2048],
+            AssetArchiveCompressionFormat::None,
+        )
+        .unwrap()
+        .write_file(
+            "corrupt1",
+            "blob",
+            &blob,
+            AssetArchiveCompressionFormat::LZ4,
+        )
+        .unwrap()
+        .finish()
+        .finish()
+        .unwrap();
+
+    let archive = AssetArchive::read_from_file(&d).unwrap();
+    assert!(archive.verify().is_ok());
+    let assets = archive.header().mount_points()[0].assets().to_vec();
+    drop(archive);
+
+    // Flip a byte inside both corrupt files.
+    let mut bytes = std::fs::read(&d).unwrap();
+    for asset in &assets[1..] {
+        bytes[*asset.offset() as usize + 10] ^= 0xFF;
+    }
+    std::fs::write(&d, &bytes).unwrap();
+
+    let archive = AssetArchive::read_from_file(&d).unwrap();
+    let corrupt = archive.verify().unwrap_err();
+    assert_eq!(corrupt.len(), 2);
+    assert_eq!(corrupt[0].identifier, "corrupt0");
+    assert_eq!(corrupt[1].identifier, "corrupt1");
+    assert!(matches!(
+        corrupt[0].error,
+        AssetArchiveError::ChecksumMismatch
+    ));
+
+    let archive = AssetArchive::read_from_file_verified(&d).unwrap();
+    assert_eq!(archive.read_file_from(&assets[0]).unwrap(), blob);
+    assert!(matches!(
+        archive.read_file_from(&assets[1]),
+        Err(AssetArchiveError::ChecksumMismatch)
+    ));
+    drop(archive);
+
+    // Reading files by path verifies them the same way.
+    let mut buffer = Vec::new();
+    AssetArchive::read_file_into_with_options(&d, &assets[0], true, &mut buffer).unwrap();
+    assert_eq!(buffer, blob);
+    AssetArchive::read_file_into(&d, &assets[1], &mut buffer).unwrap();
+    assert_eq!(buffer.len(), 2048);
+    assert_ne!(buffer, blob[..2048]);
+    for asset in &assets[1..] {
+        assert!(matches!(
+            AssetArchive::read_file_into_with_options(&d, asset, true, &mut buffer),
+            Err(AssetArchiveError::ChecksumMismatch)
+        ));
+    }
+
+    // Asset systems verify the archives they load if enabled, including assets loaded asynchronously.
+    let is_checksum_mismatch = |result: Result<(), AssetSystemError>| match result {
+        Err(AssetSystemError::Vfs(VfsError::Other(e))) => matches!(
+            e.downcast_ref::<AssetArchiveError>(),
+            Some(AssetArchiveError::ChecksumMismatch)
+        ),
+        _ => false,
+    };
+    let asset_system = Arc::new(AssetSystem::default());
+    asset_system.set_verify_archive_checksums(true);
+    asset_system.load_archive_from_file(&d).unwrap();
+    let mut buffer = Vec::new();
+    assert!(asset_system
+        .load_asset_as_blob_into("blobs", "intact", &mut buffer)
+        .is_ok());
+    let corrupt = asset_system.load_asset_as_blob_into("blobs", "corrupt0", &mut buffer);
+    assert!(is_checksum_mismatch(corrupt.map(|_| ())));
+    let one = NonZeroUsize::new(1).unwrap();
+    let dispatcher = Arc::new(Dispatcher::new(Some(one), one, Some(one), one).unwrap());
+    let corrupt = asset_system.load_asset_as_blob_async(&dispatcher, "blobs", "corrupt1");
+    let (sender, receiver) = channel();
+    dispatcher.spawn_async(async move {
+        sender.send(corrupt.await.unwrap().map(|_| ())).unwrap();
+    });
+    assert!(is_checksum_mismatch(
+        receiver.recv_timeout(Duration::from_secs(10)).unwrap()
+    ));
+    drop(asset_system);
+
+    // Archives of version 1 end with the header sizes only, they are rejected instead of being misread.
+    let trailer = bytes.len() - 32;
+    let mut legacy = bytes[..trailer].to_vec();
+    legacy.extend_from_slice(&bytes[trailer..trailer + 8]);
+    legacy.extend_from_slice(&bytes[trailer + 16..trailer + 24]);
+    assert!(matches!(
+        AssetArchive::read_from_static(Box::leak(legacy.into_boxed_slice())),
+        Err(AssetArchiveError::UnsupportedVersion(1))
+    ));
+    let mut future = bytes.clone();
+    future[trailer + 24] = 3;
+    assert!(matches!(
+        AssetArchive::read_from_static(Box::leak(future.into_boxed_slice())),
+        Err(AssetArchiveError::UnsupportedVersion(3))
+    ));
+    assert!(matches!(
+        AssetArchive::read_from_static(&[0; 64]),
+        Err(AssetArchiveError::InvalidFileRange)
+    ));
+
+    // A corrupt header is detected when the archive is opened.
+    let header_byte = bytes.len() - 40;
+    bytes[header_byte] ^= 0xFF;
+    std::fs::write(&d, &bytes).unwrap();
+    assert!(matches!(
+        AssetArchive::read_from_file(&d),
+        Err(AssetArchiveError::ChecksumMismatch)
+    ));
+}
+
+#[test]
+fn test_archive_directory_patch() {
+    let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    d.push("../tmp/patch");
+    let _ = std::fs::remove_dir_all(&d);
+    let assets = d.join("assets");
+    std::fs::create_dir_all(assets.join("config")).unwrap();
+    std::fs::create_dir_all(assets.join("meshes")).unwrap();
+    std::fs::write(assets.join("config/game.yaml"), "name: game").unwrap();
+    std::fs::write(assets.join("config/vulkan.yaml"), "validation: true").unwrap();
+    std::fs::write(assets.join("meshes/triangle.yaml"), "vertices: 3").unwrap();
+    std::fs::create_dir_all(d.join("archives")).unwrap();
+    crate::archive_directory(
+        &assets,
+        "assets",
+        d.join("archives/base.harchive"),
+        0,
+        AssetArchiveCompressionFormat::LZ4,
+    )
+    .unwrap();
+
+    // Change one file, add one, delete one and delete a whole mount point.
+    std::fs::write(assets.join("config/vulkan.yaml"), "validation: false").unwrap();
+    std::fs::write(assets.join("config/graphics.yaml"), "vsync: true").unwrap();
+    std::fs::remove_file(assets.join("config/game.yaml")).unwrap();
+    std::fs::remove_dir_all(assets.join("meshes")).unwrap();
+
+    let base = AssetArchive::read_from_file(d.join("archives/base.harchive")).unwrap();
+    assert!(crate::archive_directory_patch(
+        &base,
+        &assets,
+        "assets",
+        d.join("invalid.harchive"),
+        0,
+        AssetArchiveCompressionFormat::LZ4,
+    )
+    .is_err());
+    crate::archive_directory_patch(
+        &base,
+        &assets,
+        "assets",
+        d.join("archives/patch.harchive"),
+        1,
+        AssetArchiveCompressionFormat::LZ4,
+    )
+    .unwrap();
+
+    let patch = AssetArchive::read_from_file(d.join("archives/patch.harchive")).unwrap();
+    let assets = &patch.header().mount_points()[0];
+    assert_eq!(assets.mount_point(), "assets");
+    assert_eq!(*assets.version(), 1);
+    let written = assets
+        .assets()
+        .iter()
+        .map(|a| a.asset_identifier())
+        .collect::<Vec<_>>();
+    assert_eq!(written, vec!["config/graphics", "config/vulkan"]);
+    let mut removed = assets.removed_assets().to_vec();
+    removed.sort();
+    assert_eq!(removed, vec!["config/game", "meshes/triangle"]);
+
+    let asset_system = AssetSystem::default();
+    asset_system
+        .load_archives_from_directory(d.join("archives"), "harchive", &NoKeys)
+        .unwrap();
+    let mut buffer = vec![];
+    asset_system
+        .load_asset_as_blob_into("assets", "config/vulkan", &mut buffer)
+        .unwrap();
+    assert_eq!(buffer, b"validation: false");
+    asset_system
+        .load_asset_as_blob_into("assets", "config/graphics", &mut buffer)
+        .unwrap();
+    assert_eq!(buffer, b"vsync: true");
+    assert!(asset_system
+        .load_asset_as_blob_into("assets", "config/game", &mut buffer)
+        .is_err());
+    assert!(asset_system
+        .load_asset_as_blob_into("assets", "meshes/triangle", &mut buffer)
+        .is_err());
+}
+
+#[test]
+fn test_recursive_directory_mount() {
+    let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    d.push("../tmp/recursive");
+    let _ = std::fs::remove_dir_all(&d);
+    let assets = d.join("assets");
+    std::fs::create_dir_all(assets.join("meshes")).unwrap();
+    std::fs::create_dir_all(assets.join("textures/Small")).unwrap();
+    std::fs::write(assets.join("meshes/triangle.yaml"), "mesh").unwrap();
+    std::fs::write(assets.join("textures/triangle.png"), "texture").unwrap();
+    std::fs::write(assets.join("textures/Small/Triangle.png"), "small").unwrap();
+
+    let identifiers = crate::collect_directory_files(&assets)
+        .unwrap()
+        .into_iter()
+        .map(|f| f.identifier)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        identifiers,
+        vec![
+            "meshes/triangle",
+            "textures/small/triangle",
+            "textures/triangle"
+        ]
+    );
+
+    let archive_path = d.join("assets.harchive");
+    crate::archive_directory(
+        &assets,
+        "assets",
+        &archive_path,
+        0,
+        AssetArchiveCompressionFormat::ZSTD,
+    )
+    .unwrap();
+    let archive = AssetArchive::read_from_file(&archive_path).unwrap();
+    assert_eq!(archive.header().mount_points().len(), 1);
+
+    let mut physical = VirtualFileSystem::default();
+    assert!(physical.mount(VfsPhysicalMountPoint::new(&"assets", &assets).unwrap()));
+    let mut archived = VirtualFileSystem::default();
+    for mount in ArchiveMountPoint::from_archive(&archive) {
+        assert!(archived.mount(mount));
+    }
+
+    for vfs in [&physical, &archived] {
+        let (mesh, descriptor) = vfs.read_file("assets", "meshes/triangle").unwrap();
+        assert_eq!(mesh, b"mesh");
+        assert_eq!(descriptor.format(), "yaml");
+        let (texture, _) = vfs.read_file("assets", "textures/triangle").unwrap();
+        assert_eq!(texture, b"texture");
+        let (small, _) = vfs.read_file("assets", "Textures\\Small/Triangle").unwrap();
+        assert_eq!(small, b"small");
+        assert!(vfs.read_file("assets", "triangle").is_err());
+    }
+
+    // The index is built when mounting, new files require a refresh.
+    let mount = VfsPhysicalMountPoint::new(&"assets", &assets).unwrap();
+    std::fs::write(assets.join("meshes/quad.yaml"), "quad").unwrap();
+    assert!(!mount.has_file("meshes/quad"));
+    mount.refresh_index().unwrap();
+    assert!(mount.has_file("meshes/quad"));
+
+    // Temporary files of interrupted writes are not indexed.
+    std::fs::write(assets.join("meshes/.cube.yaml.tmp"), "cube").unwrap();
+    mount.refresh_index().unwrap();
+    assert_eq!(mount.list().len(), 4);
+
+    // Files which share an identifier are rejected instead of hiding each other.
+    std::fs::write(assets.join("meshes/Quad.cbor"), "quad").unwrap();
+    assert!(crate::collect_directory_files(&assets).is_err());
+    assert!(mount.refresh_index().is_err());
+    assert!(mount.has_file("meshes/quad"));
+    assert!(VfsPhysicalMountPoint::new(&"assets", &assets).is_err());
+    assert!(crate::archive_directory(
+        &assets,
+        "assets",
+        &archive_path,
+        0,
+        AssetArchiveCompressionFormat::ZSTD,
+    )
+    .is_err());
+}
+
+#[test]
+fn test_unmount() {
+    let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    d.push("../tmp/unmount");
+    let _ = std::fs::remove_dir_all(&d);
+    let assets = d.join("assets");
+    std::fs::create_dir_all(&assets).unwrap();
+    std::fs::write(assets.join("base.yaml"), "base").unwrap();
+    std::fs::write(assets.join("shared.yaml"), "base").unwrap();
+
+    let dlc = d.join("dlc.harchive");
+    AssetArchiveBuilder::new(File::create(&dlc).unwrap())
+        .unwrap()
+        .add_mount_point("assets", 1)
+        .unwrap()
+        .write_file(
+            "shared",
+            "yaml",
+            b"dlc",
+            AssetArchiveCompressionFormat::None,
+        )
+        .unwrap()
+        .finish()
+        .finish()
+        .unwrap();
+
+    let asset_system = AssetSystem::default();
+    asset_system
+        .load_files_from_directory(&assets, "assets")
+        .unwrap();
+    asset_system.load_archive_from_file(&dlc).unwrap();
+    let directory_info = VfsMountInfo {
+        identifier: "assets".into(),
+        version: 0,
+        source: VfsMountSource::Directory(assets.clone()),
+    };
+    let archive_info = VfsMountInfo {
+        identifier: "assets".into(),
+        version: 1,
+        source: VfsMountSource::Archive(dlc.clone()),
+    };
+    assert_eq!(
+        asset_system.mounted().unwrap(),
+        vec![directory_info.clone(), archive_info.clone()]
+    );
+    assert_eq!(
+        asset_system.resolve("assets", "shared").unwrap(),
+        Some(archive_info.clone())
+    );
+    assert_eq!(
+        asset_system.resolve("assets", "base").unwrap(),
+        Some(directory_info.clone())
+    );
+    assert_eq!(asset_system.resolve("assets", "missing").unwrap(), None);
+
+    asset_system.unmount_archive(&dlc).unwrap();
+    assert!(asset_system.unmount_archive(&dlc).is_err());
+    assert_eq!(
+        asset_system.resolve("assets", "shared").unwrap(),
+        Some(directory_info.clone())
+    );
+
+    // Archives can be mounted again after unmounting them.
+    asset_system.load_archive_from_file(&dlc).unwrap();
+    let mut buffer = vec![];
+    asset_system
+        .load_asset_as_blob_into("assets", "shared", &mut buffer)
+        .unwrap();
+    assert_eq!(buffer, b"dlc");
+
+    asset_system.unmount("assets", 1).unwrap();
+    assert!(asset_system.unmount("assets", 1).is_err());
+    asset_system.unmount("Assets", 0).unwrap();
+    assert!(asset_system.mounted().unwrap().is_empty());
+    assert!(asset_system
+        .load_asset_as_blob_into("assets", "base", &mut buffer)
+        .is_err());
+}
+
+#[test]
+fn test_list_and_glob() {
+    let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    d.push("../tmp/list");
+    let _ = std::fs::remove_dir_all(&d);
+    let assets = d.join("assets");
+    std::fs::create_dir_all(assets.join("shaders")).unwrap();
+    std::fs::create_dir_all(assets.join("meshes/props")).unwrap();
+    std::fs::write(assets.join("shaders/triangle_vert.spv"), "vert").unwrap();
+    std::fs::write(assets.join("shaders/triangle_frag.spv"), "frag").unwrap();
+    std::fs::write(assets.join("shaders/readme.txt"), "readme").unwrap();
+    std::fs::write(assets.join("meshes/triangle.yaml"), "triangle").unwrap();
+    std::fs::write(assets.join("meshes/props/crate.yaml"), "crate").unwrap();
+
+    // Version 1 replaces a shader, adds a mesh and removes the readme.
+    let patch = d.join("patch.harchive");
+    AssetArchiveBuilder::new(File::create(&patch).unwrap())
+        .unwrap()
+        .add_mount_point("assets", 1)
+        .unwrap()
+        .write_file(
+            "shaders/triangle_vert",
+            "spv",
+            b"vert 2",
+            AssetArchiveCompressionFormat::None,
+        )
+        .unwrap()
+        .write_file(
+            "meshes/quad",
+            "yaml",
+            b"quad",
+            AssetArchiveCompressionFormat::None,
+        )
+        .unwrap()
+        .remove_file("shaders/readme")
+        .finish()
+        .finish()
+        .unwrap();
+
+    let asset_system = AssetSystem::default();
+    asset_system
+        .load_files_from_directory(&assets, "assets")
+        .unwrap();
+    asset_system.load_archive_from_file(&patch).unwrap();
+
+    let identifiers = |descriptors: Vec<crate::AssetDescriptor>| {
+        descriptors
+            .iter()
+            .map(|d| d.identifier().to_string())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        identifiers(asset_system.list("assets").unwrap()),
+        vec![
+            "meshes/props/crate",
+            "meshes/quad",
+            "meshes/triangle",
+            "shaders/triangle_frag",
+            "shaders/triangle_vert"
+        ]
+    );
+    assert_eq!(
+        identifiers(asset_system.glob("assets", "shaders/*.spv").unwrap()),
+        vec!["shaders/triangle_frag", "shaders/triangle_vert"]
+    );
+    assert_eq!(
+        identifiers(asset_system.glob("assets", "meshes/*").unwrap()),
+        vec!["meshes/quad", "meshes/triangle"]
+    );
+    assert_eq!(
+        identifiers(asset_system.glob("assets", "meshes/**").unwrap()),
+        vec!["meshes/props/crate", "meshes/quad", "meshes/triangle"]
+    );
+    assert_eq!(
+        identifiers(asset_system.glob("assets", "**/cr?te.YAML").unwrap()),
+        vec!["meshes/props/crate"]
+    );
+    assert_eq!(
+        identifiers(asset_system.list_with_format("assets", "yaml").unwrap()),
+        vec!["meshes/props/crate", "meshes/quad", "meshes/triangle"]
+    );
+    assert!(asset_system.list("missing").is_err());
+
+    // Listed descriptors are served by the highest version.
+    let vert = asset_system.glob("assets", "**/triangle_vert").unwrap();
+    let mut buffer = vec![];
+    asset_system
+        .load_asset_as_blob_into(vert[0].mount(), vert[0].identifier(), &mut buffer)
+        .unwrap();
+    assert_eq!(buffer, b"vert 2");
+}
+
+#[test]
+fn test_custom_decoders() {
+    #[derive(Debug, PartialEq, serde::Deserialize)]
+    struct Mesh {
+        vertices: Vec<u32>,
+    }
+
+    /// Little endian u32 vertex indices.
+    struct BinaryMeshDecoder;
+
+    impl AssetDecoder for BinaryMeshDecoder {
+        type Output = Mesh;
+
+        fn decode(&self, bytes: &[u8]) -> Result<Mesh, DecodeError> {
+            let chunks = bytes.chunks_exact(4);
+            if !chunks.remainder().is_empty() {
+                return Err(Box::from("Invalid mesh size."));
+            }
+            Ok(Mesh {
+                vertices: chunks
+                    .map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+                    .collect(),
+            })
+        }
+    }
+
+    let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    d.push("../tmp/decoders");
+    let _ = std::fs::remove_dir_all(&d);
+    std::fs::create_dir_all(&d).unwrap();
+    let vertices = [0u32, 1, 2];
+    let bytes = vertices
+        .iter()
+        .flat_map(|v| v.to_le_bytes())
+        .collect::<Vec<_>>();
+    std::fs::write(d.join("triangle.mesh"), bytes).unwrap();
+    std::fs::write(d.join("quad.yaml"), "vertices: [0, 1, 2, 3]").unwrap();
+    std::fs::write(d.join("line.cfg"), "{\"vertices\": [0, 1]}").unwrap();
+    std::fs::write(d.join("point.unknown"), "").unwrap();
+
+    let asset_system = AssetSystem::default();
+    asset_system
+        .load_files_from_directory(&d, "meshes")
+        .unwrap();
+
+    assert!(matches!(
+        asset_system.load_asset_decoded::<Mesh>("meshes", "triangle"),
+        Err(AssetSystemError::UnknownAssetFormat)
+    ));
+    asset_system
+        .register_decoder("mesh", BinaryMeshDecoder)
+        .unwrap();
+    asset_system
+        .register_serde_format("cfg", JsonFormat)
+        .unwrap();
+
+    let expected = Mesh {
+        vertices: vec![0, 1, 2],
+    };
+    assert_eq!(
+        asset_system
+            .load_asset_decoded::<Mesh>("meshes", "triangle")
+            .unwrap(),
+        expected
+    );
+    // Raw decoders are used for typed loads as well, if the output type matches.
+    assert_eq!(
+        asset_system
+            .load_asset_as_type::<Mesh, _, _>("meshes", "triangle")
+            .unwrap(),
+        expected
+    );
+    assert!(matches!(
+        asset_system.load_asset_decoded::<Vec<u32>>("meshes", "triangle"),
+        Err(AssetSystemError::InvalidAssetType)
+    ));
+
+    assert_eq!(
+        asset_system
+            .load_asset_as_type::<Mesh, _, _>("meshes", "quad")
+            .unwrap()
+            .vertices,
+        vec![0, 1, 2, 3]
+    );
+    assert_eq!(
+        asset_system
+            .load_asset_as_type::<Mesh, _, _>("meshes", "line")
+            .unwrap()
+            .vertices,
+        vec![0, 1]
+    );
+    assert!(matches!(
+        asset_system.load_asset_as_type::<Mesh, _, _>("meshes", "point"),
+        Err(AssetSystemError::UnknownAssetFormat)
+    ));
+}
+
+#[test]
+fn test_asset_cache() {
+    #[derive(Debug, PartialEq, serde::Deserialize)]
+    struct Config {
+        name: String,
+    }
+
+    let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    d.push("../tmp/asset_cache");
+    let _ = std::fs::remove_dir_all(&d);
+    std::fs::create_dir_all(&d).unwrap();
+    for name in ["a", "b", "c"] {
+        std::fs::write(d.join(format!("{}.yaml", name)), format!("name: {}", name)).unwrap();
+    }
+
+    let asset_system = AssetSystem::default();
+    asset_system
+        .load_files_from_directory(&d, "configs")
+        .unwrap();
+    let cache = Arc::new(AssetCache::new(Arc::new(asset_system), usize::MAX));
+
+    // Hits return the same asset.
+    let a = cache.get::<Config>("configs", "a").unwrap();
+    assert_eq!(a.name, "a");
+    assert!(a.ptr_eq(&cache.get::<Config>("Configs", "A").unwrap()));
+    assert_eq!(
+        cache.stats::<Config>(),
+        AssetCacheStats {
+            hits: 1,
+            misses: 1,
+            evictions: 0
+        }
+    );
+    assert!(cache.get::<Config>("configs", "missing").is_err());
+    assert_eq!(cache.stats::<Config>().misses, 2);
+
+    // Concurrent requests only load the asset once.
+    let handles = (0..8)
+        .map(|_| {
+            let cache = Arc::clone(&cache);
+            std::thread::spawn(move || cache.get::<Config>("configs", "b").unwrap())
+        })
+        .map(|t| t.join().unwrap())
+        .collect::<Vec<_>>();
+    assert!(handles.iter().all(|h| h.ptr_eq(&handles[0])));
+    assert_eq!(cache.stats::<Config>().misses, 3);
+    drop(handles);
+
+    // Unused assets are evicted in least recently used order, `a` is still in use.
+    let weak_b = cache.get::<Config>("configs", "b").unwrap().downgrade();
+    let c = cache.get::<Config>("configs", "c").unwrap();
+    drop(c);
+    cache.set_budget(cache.bytes() - 1);
+    assert_eq!(cache.stats::<Config>().evictions, 1);
+    assert!(weak_b.upgrade().is_none());
+    cache.set_budget(0);
+    assert_eq!(cache.stats::<Config>().evictions, 2);
+    assert!(a.ptr_eq(&cache.get::<Config>("configs", "a").unwrap()));
+    drop(a);
+    cache.trim();
+    assert_eq!(cache.bytes(), 0);
+    assert_eq!(cache.stats::<Config>().evictions, 3);
+
+    // Invalidated assets are loaded again.
+    cache.set_budget(usize::MAX);
+    let a = cache.get::<Config>("configs", "a").unwrap();
+    cache.invalidate("configs", "a");
+    assert_eq!(cache.bytes(), 0);
+    assert!(!a.ptr_eq(&cache.get::<Config>("configs", "a").unwrap()));
+
+    // Assets invalidated while they are loading are not cached, the next request loads the new asset.
+    static GATE: std::sync::Mutex<()> = std::sync::Mutex::new(());
+    static STARTED: AtomicBool = AtomicBool::new(false);
+    struct Gated(Config);
+    impl<'de> serde::Deserialize<'de> for Gated {
+        fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            STARTED.store(true, Ordering::Release);
+            let _gate = GATE.lock().unwrap();
+            Config::deserialize(deserializer).map(Gated)
+        }
+    }
+    let gate = GATE.lock().unwrap();
+    let loading = Arc::clone(&cache);
+    let stale = std::thread::spawn(move || loading.get::<Gated>("configs", "c").unwrap());
+    while !STARTED.load(Ordering::Acquire) {
+        std::thread::yield_now();
+    }
+    std::fs::write(d.join("c.yaml"), "name: changed").unwrap();
+    cache.invalidate("configs", "c");
+    drop(gate);
+    assert_eq!(stale.join().unwrap().0.name, "c");
+    assert_eq!(
+        cache.get::<Gated>("configs", "c").unwrap().0.name,
+        "changed"
+    );
+
+    // A panicking load does not leave the asset pending, later requests load it again instead of blocking.
+    struct Panics;
+    impl<'de> serde::Deserialize<'de> for Panics {
+        fn deserialize<D: serde::Deserializer<'de>>(_: D) -> Result<Self, D::Error> {
+            panic!("Decoder panicked.")
+        }
+    }
+    for _ in 0..2 {
+        let (sender, receiver) = channel::<()>();
+        let panicking = Arc::clone(&cache);
+        std::thread::spawn(move || {
+            let _sender = sender;
+            panicking.get::<Panics>("configs", "b")
+        });
+        assert_eq!(
+            receiver.recv_timeout(Duration::from_secs(10)),
+            Err(std::sync::mpsc::RecvTimeoutError::Disconnected)
+        );
+    }
+    assert_eq!(cache.get::<Config>("configs", "b").unwrap().name, "b");
+}
+
+#[test]
+fn test_asset_dependencies() {
+    let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    d.push("../tmp/dependencies");
+    let _ = std::fs::remove_dir_all(&d);
+    std::fs::create_dir_all(d.join("materials")).unwrap();
+    std::fs::create_dir_all(d.join("shaders")).unwrap();
+    std::fs::create_dir_all(d.join("cycle")).unwrap();
+    for file in [
+        "materials/wood.yaml",
+        "materials/metal.yaml",
+        "shaders/lit.spv",
+        "shaders/common.spv",
+        "textures.yaml",
+        "broken.yaml",
+        "cycle/a.yaml",
+        "cycle/b.yaml",
+    ] {
+        std::fs::write(d.join(file), file).unwrap();
+    }
+    let dependency = |identifier: &str| AssetReference::new("assets", identifier);
+    let descriptor = |identifier: &str, format: &str, dependencies: &[&str]| {
+        AssetDescriptor::new("assets".into(), identifier.into(), format.into())
+            .with_dependencies(dependencies.iter().map(|d| dependency(d)).collect())
+    };
+    let index = vec![
+        descriptor("materials/wood", "yaml", &["shaders/lit", "textures"]),
+        descriptor("materials/metal", "yaml", &["shaders/lit"]),
+        descriptor("shaders/lit", "spv", &["shaders/common"]),
+        descriptor("broken", "yaml", &["missing"]),
+        descriptor("cycle/a", "yaml", &["cycle/b"]),
+        descriptor("cycle/b", "yaml", &["cycle/a"]),
+    ];
+    let mut index_file = std::collections::BTreeMap::new();
+    index_file.insert("files", index);
+    std::fs::write(
+        d.join("index.yaml"),
+        serde_yaml::to_string(&index_file).unwrap(),
+    )
+    .unwrap();
+
+    let archive_path = d.with_extension("harchive");
+    crate::archive_directory(
+        &d,
+        "assets",
+        &archive_path,
+        0,
+        AssetArchiveCompressionFormat::LZ4,
+    )
+    .unwrap();
+    let directory_system = AssetSystem::default();
+    directory_system
+        .load_files_from_directory(&d, "assets")
+        .unwrap();
+    let archive_system = AssetSystem::default();
+    archive_system
+        .load_archive_from_file(&archive_path)
+        .unwrap();
+
+    for asset_system in [directory_system, archive_system] {
+        // The index file itself is not an asset.
+        assert!(asset_system.resolve("assets", "index").unwrap().is_none());
+        assert_eq!(asset_system.list("assets").unwrap().len(), 8);
+        let order = asset_system
+            .dependency_order("assets", "materials/wood")
+            .unwrap()
+            .iter()
+            .map(|d| d.identifier().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            order,
+            [
+                "shaders/common",
+                "shaders/lit",
+                "textures",
+                "materials/wood"
+            ]
+        );
+        let loaded = asset_system
+            .load_asset_with_dependencies("assets", "materials/wood")
+            .unwrap();
+        assert_eq!(loaded.len(), 4);
+        assert_eq!(loaded[0].1, b"shaders/common.spv");
+        assert_eq!(
+            asset_system.dependencies("assets", "shaders/lit").unwrap(),
+            [dependency("shaders/common")]
+        );
+
+        match asset_system.dependency_order("assets", "broken") {
+            Err(AssetSystemError::MissingDependency { asset, dependency }) => {
+                assert_eq!(asset.identifier(), "broken");
+                assert_eq!(dependency.identifier(), "missing");
+            }
+            other => panic!("Expected a missing dependency: {:?}", other),
+        }
+        match asset_system.dependency_order("assets", "cycle/a") {
+            Err(AssetSystemError::DependencyCycle(cycle)) => assert_eq!(
+                cycle,
+                [
+                    dependency("cycle/a"),
+                    dependency("cycle/b"),
+                    dependency("cycle/a")
+                ]
+            ),
+            other => panic!("Expected a dependency cycle: {:?}", other),
+        }
+
+        let identifiers = |assets: Vec<AssetDescriptor>| {
+            assets
+                .iter()
+                .map(|d| d.identifier().to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            identifiers(asset_system.dependents("assets", "shaders/common").unwrap()),
+            ["shaders/lit"]
+        );
+        assert_eq!(
+            identifiers(
+                asset_system
+                    .transitive_dependents("assets", "shaders/common")
+                    .unwrap()
+            ),
+            ["materials/metal", "materials/wood", "shaders/lit"]
+        );
+    }
+}
+
+#[test]
+fn test_encrypted_archive() {
+    let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    d.push("../tmp/encrypted");
+    let _ = std::fs::remove_dir_all(&d);
+    std::fs::create_dir_all(&d).unwrap();
+    let archive_path = d.join("encrypted.harchive");
+
+    let key = AssetArchiveKey::generate();
+    let config = b"name: encrypted".to_vec();
+    let blob = (0..4096).map(|_| rand::random()).collect::<Vec<u8>>();
+    let builder = AssetArchiveBuilder::new(File::create(&archive_path).unwrap())
+        .unwrap()
+        .encrypt_with(key.clone());
+    let uuid = *builder.uuid();
+    builder
+        .add_mount_point("assets", 0)
+        .unwrap()
+        .write_file(
+            "config",
+            "yaml",
+            &config,
+            AssetArchiveCompressionFormat::None,
+        )
+        .unwrap()
+        .write_file("blob", "blob", &blob, AssetArchiveCompressionFormat::ZSTD)
+        .unwrap()
+        .finish()
+        .finish()
+        .unwrap();
+
+    let archive = AssetArchive::read_from_file(&archive_path).unwrap();
+    assert_eq!(*archive.header().uuid(), uuid);
+    let assets = archive.header().mount_points()[0].assets().to_vec();
+    assert!(assets
+        .iter()
+        .all(|a| *a.encryption() != AssetArchiveEncryption::None));
+    // The plain text is not stored and checksums can be verified without the key.
+    assert_ne!(archive.reader().stored_file(&assets[0]).unwrap(), config);
+    assert!(archive.verify().is_ok());
+    assert!(matches!(
+        archive.read_file_from(&assets[0]),
+        Err(AssetArchiveError::MissingKey)
+    ));
+
+    let keys = {
+        let key = key.clone();
+        move |archive: &uuid::Uuid| (*archive == uuid).then(|| key.clone())
+    };
+    let asset_system = Arc::new(AssetSystem::default());
+    asset_system
+        .load_archives_from_directory(&d, "harchive", &keys)
+        .unwrap();
+    let config_value = asset_system
+        .load_asset_as_type::<HashMap<String, String>, _, _>("assets", "config")
+        .unwrap();
+    assert_eq!(config_value["name"], "encrypted");
+    let mut loaded = Vec::new();
+    asset_system
+        .load_asset_as_blob_into("assets", "blob", &mut loaded)
+        .unwrap();
+    assert_eq!(loaded, blob);
+
+    let one = NonZeroUsize::new(1).unwrap();
+    let dispatcher = Arc::new(Dispatcher::new(Some(one), one, Some(one), one).unwrap());
+    let (tx, rx) = channel();
+    let handle = asset_system.load_asset_as_blob_async(&dispatcher, "assets", "blob");
+    dispatcher.spawn_async(async move {
+        tx.send(handle.await.unwrap().unwrap().0).unwrap();
+    });
+    assert_eq!(rx.recv_timeout(Duration::from_secs(10)).unwrap(), blob);
+
+    // A wrong key fails authentication.
+    let wrong_key = AssetArchiveKey::generate();
+    let archive = AssetArchive::read_from_file_with_keys(&archive_path, &move |_: &uuid::Uuid| {
+        Some(wrong_key.clone())
+    })
+    .unwrap();
+    assert!(matches!(
+        archive.read_file_from(&assets[1]),
+        Err(AssetArchiveError::DecryptionFailed)
+    ));
+    drop(archive);
+
+    // Modified files fail authentication, even if the checksum is updated.
+    let archive = AssetArchive::read_from_file_with_keys(&archive_path, &keys).unwrap();
+    assert_eq!(archive.read_file_from(&assets[1]).unwrap(), blob);
+    drop(archive);
+    let mut bytes = std::fs::read(&archive_path).unwrap();
+    bytes[*assets[0].offset() as usize] ^= 0xFF;
+    std::fs::write(&archive_path, &bytes).unwrap();
+    let archive = AssetArchive::read_from_file_with_keys(&archive_path, &keys).unwrap();
+    assert!(matches!(
+        archive.read_file_from(&assets[0]),
+        Err(AssetArchiveError::DecryptionFailed)
+    ));
+    drop(archive);
+    bytes[*assets[0].offset() as usize] ^= 0xFF;
+
+    // Files listed under another mount point or version fail authentication,
+    // e.g. a patch archive whose files were replaced by the files of an older version.
+    let relabel = |mount_point: &str, version: u64| {
+        let trailer = bytes.len() - 32;
+        let read_u64 = |offset: usize| {
+            u64::from_le_bytes(
+                bytes[trailer + offset..trailer + offset + 8]
+                    .try_into()
+                    .unwrap(),
+            )
+        };
+        let header_start = trailer - read_u64(16) as usize;
+        let header =
+            zstd::bulk::decompress(&bytes[header_start..trailer], read_u64(0) as usize).unwrap();
+        let mut header: serde_cbor::Value = serde_cbor::from_slice(&header).unwrap();
+        if let serde_cbor::Value::Map(header) = &mut header {
+            let mount_points = header
+                .get_mut(&serde_cbor::Value::Text("mount_points".into()))
+                .unwrap();
+            if let serde_cbor::Value::Array(mount_points) = mount_points {
+                if let serde_cbor::Value::Map(mount) = &mut mount_points[0] {
+                    mount.insert(
+                        serde_cbor::Value::Text("mount_point".into()),
+                        serde_cbor::Value::Text(mount_point.into()),
+                    );
+                    mount.insert(
+                        serde_cbor::Value::Text("version".into()),
+                        serde_cbor::Value::Integer(version.into()),
+                    );
+                }
+            }
+        }
+        let header = serde_cbor::to_vec(&header).unwrap();
+        let compressed = zstd::bulk::compress(&header, 0).unwrap();
+        let mut forged = bytes[..header_start].to_vec();
+        forged.extend_from_slice(&compressed);
+        forged.extend_from_slice(&(header.len() as u64).to_le_bytes());
+        forged.extend_from_slice(&xxhash_rust::xxh3::xxh3_64(&compressed).to_le_bytes());
+        forged.extend_from_slice(&(compressed.len() as u64).to_le_bytes());
+        forged.extend_from_slice(&bytes[trailer + 24..]);
+        forged
+    };
+    for (mount_point, version, authenticated) in [
+        ("assets", 0, true),
+        ("assets", 1, false),
+        ("other", 0, false),
+    ] {
+        let forged_path = d.join("forged.bin");
+        std::fs::write(&forged_path, relabel(mount_point, version)).unwrap();
+        let archive = AssetArchive::read_from_file_with_keys(&forged_path, &keys).unwrap();
+        let forged = archive.header().mount_points()[0].assets()[1].clone();
+        assert_eq!(
+            archive.read_file_from(&forged).is_ok(),
+            authenticated,
+            "{} {}",
+            mount_point,
+            version
+        );
+    }
+}
+
+#[test]
+fn test_archive_dictionary() {
+    let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    d.push("../tmp/dictionary");
+    let _ = std::fs::remove_dir_all(&d);
+    std::fs::create_dir_all(&d).unwrap();
+
+    // Many small configs sharing most of their structure.
+    let configs = (0..256)
+        .map(|i| {
+            format!(
+                "name: enemy_{}\nhealth: {}\nspeed: {}\nmesh: meshes/enemy_{}\nmaterial: materials/enemy\n",
+                i,
+                100 + i,
+                i % 7,
+                i % 3
+            )
+            .into_bytes()
+        })
+        .collect::<Vec<_>>();
+    let build = |path: &PathBuf, compression_format, dictionary: bool| {
+        let mut mnt_point = AssetArchiveBuilder::new(File::create(path).unwrap())
+            .unwrap()
+            .with_compression_level(19)
+            .add_mount_point("configs", 0)
+            .unwrap();
+        if dictionary {
+            mnt_point = mnt_point.train_dictionary(&configs, 4096).unwrap();
+        }
+        for (i, config) in configs.iter().enumerate() {
+            mnt_point = mnt_point
+                .write_file(format!("enemy_{}", i), "yaml", config, compression_format)
+                .unwrap();
+        }
+        mnt_point.finish().finish().unwrap();
+        std::fs::metadata(path).unwrap().len()
+    };
+    let zstd_size = build(
+        &d.join("zstd.harchive"),
+        AssetArchiveCompressionFormat::ZSTD,
+        false,
+    );
+    let dictionary_path = d.join("dictionary.harchive");
+    let dictionary_size = build(
+        &dictionary_path,
+        AssetArchiveCompressionFormat::ZSTDDictionary,
+        true,
+    );
+    assert!(dictionary_size < zstd_size);
+
+    let archive = AssetArchive::read_from_file_verified(&dictionary_path).unwrap();
+    assert!(archive.header().mount_points()[0].dictionary().is_some());
+    let assets = archive.header().mount_points()[0].assets().to_vec();
+    assert_eq!(archive.read_file_from(&assets[7]).unwrap(), configs[7]);
+    assert_eq!(
+        AssetArchive::read_file(&dictionary_path, &assets[8]).unwrap(),
+        configs[8]
+    );
+    drop(archive);
+
+    let asset_system = Arc::new(AssetSystem::default());
+    asset_system
+        .load_archive_from_file(&dictionary_path)
+        .unwrap();
+    let config = asset_system
+        .load_asset_as_type::<HashMap<String, String>, _, _>("configs", "enemy_42")
+        .unwrap();
+    assert_eq!(config["health"], "142");
+    let one = NonZeroUsize::new(1).unwrap();
+    let dispatcher = Arc::new(Dispatcher::new(Some(one), one, Some(one), one).unwrap());
+    let handle = asset_system.load_asset_as_blob_async(&dispatcher, "configs", "enemy_3");
+    let (sender, receiver) = channel();
+    dispatcher.spawn_async(async move {
+        sender.send(handle.await.unwrap().unwrap().0).unwrap();
+    });
+    assert_eq!(
+        receiver.recv_timeout(Duration::from_secs(10)).unwrap(),
+        configs[3]
+    );
+
+    // Writing with a dictionary requires one to be trained first.
+    let result = AssetArchiveBuilder::new(File::create(d.join("missing.harchive")).unwrap())
+        .unwrap()
+        .add_mount_point("configs", 0)
+        .unwrap()
+        .write_file(
+            "config",
+            "yaml",
+            &configs[0],
+            AssetArchiveCompressionFormat::ZSTDDictionary,
+        );
+    assert!(matches!(
+        result,
+        Err((_, AssetArchiveError::MissingDictionary))
+    ));
+}
+
+#[test]
+fn test_archive_reproducible() {
+    let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    d.push("../tmp/reproducible");
+    let _ = std::fs::remove_dir_all(&d);
+    let source = d.join("source");
+    std::fs::create_dir_all(source.join("configs")).unwrap();
+    for i in 0..64 {
+        std::fs::write(
+            source.join("configs").join(format!("config_{}.yaml", i)),
+            format!("name: config_{}\nvalue: {}\n", i, i * 13),
+        )
+        .unwrap();
+    }
+
+    let build = |name: &str, threads: usize| {
+        let out = d.join(name);
+        crate::archive_directory_with_options(
+            &source,
+            "assets",
+            &out,
+            &crate::ArchiveDirectoryOptions {
+                threads: std::num::NonZeroUsize::new(threads),
+                content_uuid: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        std::fs::read(out).unwrap()
+    };
+    let first = build("first.harchive", 1);
+    let second = build("second.harchive", 4);
+    assert_eq!(first, second);
+
+    let archive = AssetArchive::read_from_file_verified(d.join("first.harchive")).unwrap();
+    assert!(!archive.header().uuid().is_nil());
+    let assets = archive.header().mount_points()[0].assets().to_vec();
+    assert_eq!(assets.len(), 64);
+    assert!(assets
+        .windows(2)
+        .all(|w| w[0].asset_identifier() < w[1].asset_identifier()));
+    let config = assets
+        .iter()
+        .find(|a| a.asset_identifier() == "configs/config_5")
+        .unwrap();
+    assert_eq!(
+        archive.read_file_from(config).unwrap(),
+        b"name: config_5\nvalue: 65\n"
+    );
+
+    // Changed contents produce a different UUID.
+    std::fs::write(source.join("configs/config_5.yaml"), "name: changed\n").unwrap();
+    let changed = build("changed.harchive", 4);
+    assert_ne!(first, changed);
+    let changed = AssetArchive::read_from_file(d.join("changed.harchive")).unwrap();
+    assert_ne!(changed.header().uuid(), archive.header().uuid());
+}
+
+#[test]
+fn test_archive_deduplication() {
+    let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    d.push("../tmp/");
+    std::fs::create_dir_all(d.clone()).unwrap();
+    d.push("deduplication.harchive");
+
+    let shared = (0..4096).map(|_| rand::random()).collect::<Vec<u8>>();
+    let unique = (0..4096).map(|_| rand::random()).collect::<Vec<u8>>();
+    let builder = AssetArchiveBuilder::new(File::create(d.clone()).unwrap())
+        .unwrap()
+        .add_mount_point("textures", 0)
+        .unwrap()
+        .write_file("stone", "bin", &shared, AssetArchiveCompressionFormat::LZ4)
+        .unwrap()
+        .write_file("grass", "bin", &unique, AssetArchiveCompressionFormat::LZ4)
+        .unwrap()
+        .finish()
+        .add_mount_point("props", 0)
+        .unwrap()
+        .write_file("rock", "bin", &shared, AssetArchiveCompressionFormat::LZ4)
+        .unwrap()
+        .write_file(
+            "pebble",
+            "bin",
+            &shared,
+            AssetArchiveCompressionFormat::None,
+        )
+        .unwrap()
+        .finish();
+    let report = builder.report().clone();
+    builder.finish().unwrap();
+
+    // Only equal stored bytes are shared, the uncompressed copy is stored again.
+    assert_eq!(report.files, 4);
+    assert_eq!(
+        report.deduplicated,
+        vec![AssetArchiveDeduplicatedFile {
+            mount_point: String::from("props"),
+            identifier: String::from("rock"),
+            original_mount_point: String::from("textures"),
+            original_identifier: String::from("stone"),
+            bytes: report.deduplicated_bytes(),
+        }]
+    );
+
+    let archive = AssetArchive::read_from_file_verified(&d).unwrap();
+    assert!(archive.verify().is_ok());
+    let mount_points = archive.header().mount_points();
+    let stone = &mount_points[0].assets()[0];
+    let rock = &mount_points[1].assets()[0];
+    assert_eq!(stone.offset(), rock.offset());
+    assert_eq!(report.stored_bytes + *rock.compressed_size(), {
+        let assets = mount_points.iter().flat_map(|m| m.assets());
+        assets.map(|a| *a.compressed_size()).sum::<u64>()
+    });
+    assert_eq!(archive.read_file_from(stone).unwrap(), shared);
+    assert_eq!(archive.read_file_from(rock).unwrap(), shared);
+    assert_eq!(
+        archive
+            .read_file_from(&mount_points[1].assets()[1])
+            .unwrap(),
+        shared
+    );
+    assert_eq!(
+        archive
+            .read_file_from(&mount_points[0].assets()[1])
+            .unwrap(),
+        unique
+    );
+}
+
+#[test]
+fn test_writable_mount_point() {
+    #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+    struct SaveGame {
+        level: String,
+        health: u32,
+        inventory: Vec<String>,
+    }
+
+    let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    d.push("../tmp/user_data");
+    let _ = std::fs::remove_dir_all(&d);
+
+    let asset_system = AssetSystem::default();
+    let mut configs = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    configs.push("test_files/physical");
+    asset_system
+        .load_files_from_directory(&configs, "configs")
+        .unwrap();
+    asset_system.mount_writable_directory(&d, "user").unwrap();
+    let (sender, receiver) = channel();
+    asset_system.add_change_listener(move |message| sender.send(message.clone()).unwrap());
+
+    let save = SaveGame {
+        level: String::from("castle"),
+        health: 80,
+        inventory: vec![String::from("sword"), String::from("key")],
+    };
+    let descriptor = asset_system
+        .save_asset_as_type("user", "saves/Slot1", "yaml", &save)
+        .unwrap();
+    assert_eq!(descriptor.identifier(), "saves/slot1");
+    assert!(d.join("saves/slot1.yaml").is_file());
+    assert_eq!(
+        receiver.recv_timeout(Duration::from_secs(1)).unwrap(),
+        AssetDidChange {
+            mount: String::from("user"),
+            identifier: String::from("saves/slot1"),
+        }
+    );
+    let loaded: SaveGame = asset_system
+        .load_asset_as_type("user", "saves/slot1")
+        .unwrap();
+    assert_eq!(loaded, save);
+
+    // Saving in another format replaces the file.
+    let save = SaveGame { health: 20, ..save };
+    asset_system
+        .save_asset_as_type("user", "saves/slot1", "cbor", &save)
+        .unwrap();
+    assert!(!d.join("saves/slot1.yaml").exists());
+    let loaded: SaveGame = asset_system
+        .load_asset_as_type("user", "saves/slot1")
+        .unwrap();
+    assert_eq!(loaded, save);
+    assert_eq!(asset_system.list("user").unwrap().len(), 1);
+
+    // A directory mounted later finds the saved files.
+    let mount = VfsPhysicalMountPoint::new(&"user", &d).unwrap();
+    assert!(mount.has_file("saves/slot1"));
+    assert!(mount.as_writable().is_none());
+
+    assert!(matches!(
+        asset_system.save_asset_as_type("configs", "save", "yaml", &save),
+        Err(AssetSystemError::Vfs(VfsError::ReadOnly))
+    ));
+    assert!(matches!(
+        asset_system.save_asset_as_blob("user", "saves/../../escape", "bin", &[0]),
+        Err(AssetSystemError::Vfs(VfsError::InvalidIdentifier))
+    ));
+    assert!(matches!(
+        asset_system.save_asset_as_type("user", "saves/slot2", "unknown", &save),
+        Err(AssetSystemError::UnknownAssetFormat)
+    ));
+
+    asset_system.delete_asset("user", "saves/slot1").unwrap();
+    assert!(!d.join("saves/slot1.cbor").exists());
+    assert!(matches!(
+        asset_system.load_asset_as_type::<SaveGame, _, _>("user", "saves/slot1"),
+        Err(AssetSystemError::Vfs(VfsError::FileNotFound))
+    ));
+    assert!(matches!(
+        asset_system.delete_asset("user", "saves/slot1"),
+        Err(AssetSystemError::Vfs(VfsError::FileNotFound))
+    ));
+}
+
+#[test]
+fn test_memory_mount_point() {
+    let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    d.push("test_files/physical");
+    let asset_system = AssetSystem::default();
+    asset_system
+        .load_files_from_directory(&d, "configs")
+        .unwrap();
+
+    // Files can be inserted after mounting, the mounted clone shares them.
+    let staged = MemoryMountPoint::new("configs", 1);
+    asset_system.mount(staged.clone()).unwrap();
+    assert!(matches!(
+        asset_system.mount(MemoryMountPoint::new("configs", 1)),
+        Err(AssetSystemError::NotMounted)
+    ));
+    let physical = asset_system.load_asset_as_blob_into("configs", "test", &mut Vec::new());
+    assert!(physical.is_ok());
+    assert_eq!(
+        asset_system
+            .resolve("configs", "test")
+            .unwrap()
+            .unwrap()
+            .version,
+        0
+    );
+
+    staged.insert("test", "yaml", "staged: true\n");
+    staged.insert_with_dependencies(
+        "generated/Level",
+        "cbor",
+        vec![AssetReference::new("configs", "test")],
+        serde_cbor::to_vec(&vec![1, 2, 3]).unwrap(),
+    );
+    let info = asset_system.resolve("configs", "test").unwrap().unwrap();
+    assert_eq!(info.version, 1);
+    assert_eq!(info.source, VfsMountSource::Memory);
+    let test: HashMap<String, bool> = asset_system.load_asset_as_type("configs", "test").unwrap();
+    assert!(test["staged"]);
+    let level: Vec<u32> = asset_system
+        .load_asset_as_type("configs", "generated/level")
+        .unwrap();
+    assert_eq!(level, vec![1, 2, 3]);
+    let order = asset_system
+        .dependency_order("configs", "generated/level")
+        .unwrap();
+    assert_eq!(order.len(), 2);
+    assert_eq!(
+        asset_system
+            .list("configs")
+            .unwrap()
+            .iter()
+            .map(|d| d.identifier())
+            .collect::<Vec<_>>(),
+        vec!["generated/level", "test"]
+    );
+
+    // Removing the staged file reveals the file of the lower version, marking it removed hides it.
+    assert!(staged.remove("test"));
+    assert_eq!(
+        asset_system
+            .resolve("configs", "test")
+            .unwrap()
+            .unwrap()
+            .version,
+        0
+    );
+    staged.mark_removed("test");
+    assert!(asset_system.resolve("configs", "test").unwrap().is_none());
+    assert_eq!(asset_system.list("configs").unwrap().len(), 1);
+
+    // Saved assets are stored in memory.
+    asset_system
+        .save_asset_as_type("configs", "test", "yaml", &vec![4, 5])
+        .unwrap();
+    assert_eq!(staged.len(), 2);
+    let test: Vec<u32> = asset_system.load_asset_as_type("configs", "test").unwrap();
+    assert_eq!(test, vec![4, 5]);
+    asset_system.delete_asset("configs", "test").unwrap();
+    assert_eq!(
+        asset_system
+            .resolve("configs", "test")
+            .unwrap()
+            .unwrap()
+            .version,
+        0
+    );
+
+    staged.clear();
+    assert!(staged.is_empty());
+    assert_eq!(asset_system.list("configs").unwrap().len(), 1);
+}
+
+#[test]
+fn test_archive_sources() {
+    let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    d.push("../tmp/");
+    std::fs::create_dir_all(d.clone()).unwrap();
+    let archive_path = d.join("sources.harchive");
+    AssetArchiveBuilder::new(File::create(&archive_path).unwrap())
+        .unwrap()
+        .add_mount_point("config", 0)
+        .unwrap()
+        .write_file(
+            "window",
+            "yaml",
+            b"width: 1024\nheight: 768\n",
+            AssetArchiveCompressionFormat::ZSTD,
+        )
+        .unwrap()
+        .write_file(
+            "raw",
+            "bin",
+            &[1, 2, 3, 4],
+            AssetArchiveCompressionFormat::None,
+        )
+        .unwrap()
+        .finish()
+        .finish()
+        .unwrap();
+    let bytes = std::fs::read(&archive_path).unwrap();
+
+    // Embedded archives, as if included using `include_bytes!`.
+    let embedded: &'static [u8] = Box::leak(bytes.clone().into_boxed_slice());
+    let archive = AssetArchive::read_from_static(embedded).unwrap();
+    assert!(archive.path().is_none());
+    let raw = &archive.header().mount_points()[0].assets()[1];
+    assert_eq!(
+        archive.reader().uncompressed_file(raw).unwrap(),
+        Some([1, 2, 3, 4].as_slice())
+    );
+    let asset_system = AssetSystem::default();
+    asset_system.mount_archive(&archive).unwrap();
+    let config: HashMap<String, u32> = asset_system.load_asset_as_type("config", "window").unwrap();
+    assert_eq!(config["width"], 1024);
+    assert_eq!(
+        asset_system.mounted().unwrap()[0].source,
+        VfsMountSource::Other
+    );
+
+    // Any `Read + Seek` source, the stream is read on demand.
+    let archive = AssetArchive::read_from_reader(std::io::Cursor::new(bytes.clone())).unwrap();
+    assert!(archive.verify().is_ok());
+    assert_eq!(archive.reader().uncompressed_file(raw).unwrap(), None);
+    assert_eq!(archive.read_file_from(raw).unwrap(), vec![1, 2, 3, 4]);
+
+    // An archive stored inside of a larger file.
+    let mut packed = vec![0xAB; 100];
+    packed.extend_from_slice(&bytes);
+    packed.extend_from_slice(&[0xCD; 50]);
+    let packed_path = d.join("sources.pack");
+    std::fs::write(&packed_path, &packed).unwrap();
+    let reader = AssetArchiveReader::from_reader_range(
+        File::open(&packed_path).unwrap(),
+        100,
+        bytes.len() as u64,
+    )
+    .unwrap();
+    let archive = AssetArchive::from_reader(reader).unwrap();
+    let asset_system = AssetSystem::default();
+    asset_system.mount_archive(&archive).unwrap();
+    let mut window = Vec::new();
+    asset_system
+        .load_asset_as_blob_into("config", "window", &mut window)
+        .unwrap();
+    assert_eq!(window, b"width: 1024\nheight: 768\n");
+
+    // Ranges outside of the source are rejected when reading.
+    let reader = AssetArchiveReader::from_reader_range(
+        File::open(&packed_path).unwrap(),
+        100,
+        packed.len() as u64,
+    )
+    .unwrap();
+    assert!(matches!(
+        AssetArchive::from_reader(reader),
+        Err(AssetArchiveError::Io(_))
+    ));
+}
+
+#[test]
+fn test_import_directory() {
+    struct UppercaseImporter {
+        version: u32,
+        imports: Arc<AtomicUsize>,
+    }
+    impl AssetImporter for UppercaseImporter {
+        fn name(&self) -> &str {
+            "uppercase"
+        }
+        fn version(&self) -> u32 {
+            self.version
+        }
+        fn output_format(&self, _source: &ImportSource) -> String {
+            String::from("yaml")
+        }
+        fn output_identifier(&self, source: &ImportSource) -> String {
+            format!("{}_upper", source.identifier)
+        }
+        fn import(&self, source: &ImportSource) -> Result<Vec<u8>, ImportError> {
+            self.imports.fetch_add(1, Ordering::SeqCst);
+            Ok(source.bytes.to_ascii_uppercase())
+        }
+    }
+
+    let d = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../tmp/import");
+    let _ = std::fs::remove_dir_all(&d);
+    let (source, out, cache) = (d.join("source"), d.join("out"), d.join("cache"));
+    std::fs::create_dir_all(source.join("config")).unwrap();
+    std::fs::write(source.join("config/game.yaml"), "title: game\n").unwrap();
+    std::fs::write(source.join("notes.txt"), "text: hello\n").unwrap();
+    std::fs::write(source.join("raw.bin"), [1, 2, 3]).unwrap();
+    std::fs::write(source.join("index.yaml"), "files: []\n").unwrap();
+
+    let imports = Arc::new(AtomicUsize::new(0));
+    let registry = |version| {
+        let mut registry = AssetImporterRegistry::default();
+        registry.register(
+            "TXT",
+            UppercaseImporter {
+                version,
+                imports: imports.clone(),
+            },
+        );
+        registry
+    };
+
+    let report = import_directory(&source, &out, &registry(1), Some(&cache)).unwrap();
+    assert_eq!(report.imported, vec!["config/game", "notes_upper"]);
+    assert!(report.cached.is_empty());
+    assert_eq!(report.copied, vec!["raw"]);
+    assert_eq!(imports.load(Ordering::SeqCst), 1);
+    assert_eq!(
+        std::fs::read(out.join("notes_upper.yaml")).unwrap(),
+        b"TEXT: HELLO\n"
+    );
+    assert_eq!(std::fs::read(out.join("raw.bin")).unwrap(), [1, 2, 3]);
+    // The index file is copied, but not imported as an asset.
+    assert_eq!(
+        std::fs::read(out.join("index.yaml")).unwrap(),
+        b"files: []\n"
+    );
+
+    // Unchanged sources are taken from the cache.
+    let report = import_directory(&source, &out, &registry(1), Some(&cache)).unwrap();
+    assert_eq!(report.cached, vec!["config/game", "notes_upper"]);
+    assert!(report.imported.is_empty());
+    assert_eq!(imports.load(Ordering::SeqCst), 1);
+
+    // A new importer version or a changed source invalidates the cache.
+    let report = import_directory(&source, &out, &registry(2), Some(&cache)).unwrap();
+    assert_eq!(report.imported, vec!["notes_upper"]);
+    std::fs::write(source.join("notes.txt"), "text: changed\n").unwrap();
+    import_directory(&source, &out, &registry(2), Some(&cache)).unwrap();
+    assert_eq!(imports.load(Ordering::SeqCst), 3);
+    assert_eq!(
+        std::fs::read(out.join("notes_upper.yaml")).unwrap(),
+        b"TEXT: CHANGED\n"
+    );
+
+    // Outputs of removed sources are deleted.
+    std::fs::remove_file(source.join("raw.bin")).unwrap();
+    let report = import_directory(&source, &out, &registry(2), None).unwrap();
+    assert_eq!(report.removed, vec!["raw"]);
+    assert!(!out.join("raw.bin").exists());
+
+    // The imported directory is archived like any other directory.
+    let archive_path = d.join("import.harchive");
+    crate::archive_directory(
+        &out,
+        "game",
+        &archive_path,
+        0,
+        AssetArchiveCompressionFormat::ZSTD,
+    )
+    .unwrap();
+    let asset_system = AssetSystem::default();
+    asset_system
+        .mount_archive(&AssetArchive::read_from_file(&archive_path).unwrap())
+        .unwrap();
+    let notes: HashMap<String, String> = asset_system
+        .load_asset_as_type("game", "notes_upper")
+        .unwrap();
+    assert_eq!(notes["TEXT"], "CHANGED");
+
+    // Invalid sources and sources imported as the same asset fail the import.
+    std::fs::write(source.join("broken.yaml"), "a: [1, 2\n").unwrap();
+    assert!(import_directory(&source, &out, &registry(2), None).is_err());
+    std::fs::remove_file(source.join("broken.yaml")).unwrap();
+    std::fs::write(source.join("notes_upper.yaml"), "text: other\n").unwrap();
+    assert!(import_directory(&source, &out, &registry(2), None).is_err());
+}
+
+#[test]
+fn test_open_asset() {
+    use std::io::{Read, Seek, SeekFrom};
+
+    let d = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../tmp/");
+    std::fs::create_dir_all(&d).unwrap();
+    let archive_path = d.join("stream.harchive");
+    // Larger than a few LZ4 chunks and zstd blocks, compressible but not repetitive.
+    let level = (0..300_000u32)
+        .map(|i| (i.wrapping_mul(2654435761) >> 28) as u8 ^ (i / 1000) as u8)
+        .collect::<Vec<_>>();
+    let formats = [
+        ("none", AssetArchiveCompressionFormat::None),
+        ("lz4", AssetArchiveCompressionFormat::LZ4),
+        ("zstd", AssetArchiveCompressionFormat::ZSTD),
+        ("lz4_chunked", AssetArchiveCompressionFormat::LZ4Chunked),
+    ];
+    let mut mnt_point = AssetArchiveBuilder::new(File::create(&archive_path).unwrap())
+        .unwrap()
+        .add_mount_point("levels", 0)
+        .unwrap();
+    for (identifier, format) in formats {
+        mnt_point = mnt_point
+            .write_file(identifier, "bin", &level, format)
+            .unwrap();
+    }
+    mnt_point
+        .write_file(
+            "empty",
+            "bin",
+            &[],
+            AssetArchiveCompressionFormat::LZ4Chunked,
+        )
+        .unwrap()
+        .finish()
+        .finish()
+        .unwrap();
+
+    let archive = AssetArchive::read_from_file(&archive_path).unwrap();
+    assert!(archive.verify().is_ok());
+    let asset_system = AssetSystem::default();
+    asset_system.mount_archive(&archive).unwrap();
+    for (identifier, _) in formats {
+        let mut blob = Vec::new();
+        asset_system
+            .load_asset_as_blob_into("levels", identifier, &mut blob)
+            .unwrap();
+        assert_eq!(blob, level);
+
+        let mut stream = asset_system.open_asset("levels", identifier).unwrap();
+        let mut bytes = Vec::new();
+        stream.read_to_end(&mut bytes).unwrap();
+        assert_eq!(bytes, level, "{}", identifier);
+
+        let mut read_at = |pos: SeekFrom, len: usize| {
+            let position = stream.seek(pos).unwrap() as usize;
+            let mut bytes = vec![0; len];
+            stream.read_exact(&mut bytes).unwrap();
+            assert_eq!(bytes, level[position..position + len], "{}", identifier);
+            position
+        };
+        assert_eq!(read_at(SeekFrom::Start(200_000), 100), 200_000);
+        assert_eq!(read_at(SeekFrom::Current(-150_100), 70_000), 50_000);
+        assert_eq!(read_at(SeekFrom::End(-10), 10), 299_990);
+        assert_eq!(read_at(SeekFrom::Start(65_530), 12), 65_530);
+
+        assert_eq!(stream.seek(SeekFrom::End(5)).unwrap(), 300_005);
+        assert_eq!(stream.read(&mut [0; 8]).unwrap(), 0);
+        assert!(stream.seek(SeekFrom::Current(-400_000)).is_err());
+    }
+    let mut empty = Vec::new();
+    asset_system
+        .open_asset("levels", "empty")
+        .unwrap()
+        .read_to_end(&mut empty)
+        .unwrap();
+    assert!(empty.is_empty());
+
+    // Physical and memory mount points stream their files as well.
+    let physical = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("test_files/physical");
+    asset_system
+        .load_files_from_directory(&physical, "configs")
+        .unwrap();
+    let mut bytes = Vec::new();
+    asset_system
+        .open_asset("configs", "test")
+        .unwrap()
+        .read_to_end(&mut bytes)
+        .unwrap();
+    assert_eq!(bytes, std::fs::read(physical.join("test.yaml")).unwrap());
+
+    let memory = MemoryMountPoint::new("generated", 0);
+    memory.insert("noise", "bin", vec![1, 2, 3, 4]);
+    asset_system.mount(memory).unwrap();
+    let mut stream = asset_system.open_asset("generated", "noise").unwrap();
+    stream.seek(SeekFrom::Start(2)).unwrap();
+    let mut bytes = Vec::new();
+    stream.read_to_end(&mut bytes).unwrap();
+    assert_eq!(bytes, [3, 4]);
+    assert!(asset_system.open_asset("generated", "missing").is_err());
+}
+
+#[test]
+fn test_asset_variants() {
+    let asset_system = AssetSystem::default();
+    let base = MemoryMountPoint::new("ui", 0);
+    base.insert("strings", "yaml", "title: Game\n");
+    base.insert("strings@fr", "yaml", "title: Jeu\n");
+    base.insert("logo", "yaml", "text: Logo\n");
+    let patch = MemoryMountPoint::new("ui", 1);
+    patch.insert("strings", "yaml", "title: Patched\n");
+    asset_system.mount(base).unwrap();
+    asset_system.mount(patch.clone()).unwrap();
+    let title = |asset_system: &AssetSystem| {
+        asset_system
+            .load_asset_as_type::<HashMap<String, String>, _, _>("ui", "strings")
+            .unwrap()["title"]
+            .clone()
+    };
+    assert_eq!(title(&asset_system), "Patched");
+
+    let (sender, receiver) = channel();
+    asset_system.add_variants_listener(move |message| sender.send(message.clone()).unwrap());
+
+    // Variants are tried in order before the base identifier, in all versions of the mount point.
+    asset_system
+        .set_variants(["fr-FR", "FR", "", "fr"])
+        .unwrap();
+    assert_eq!(asset_system.variants().unwrap(), vec!["fr-fr", "fr"]);
+    assert_eq!(
+        receiver.try_recv().unwrap(),
+        AssetVariantsDidChange {
+            variants: vec![String::from("fr-fr"), String::from("fr")]
+        }
+    );
+    assert_eq!(title(&asset_system), "Jeu");
+    let info = asset_system.resolve("ui", "strings").unwrap().unwrap();
+    assert_eq!(info.version, 0);
+    patch.insert("strings@fr-fr", "yaml", "title: Jeu (France)\n");
+    assert_eq!(title(&asset_system), "Jeu (France)");
+    assert!(asset_system
+        .load_asset_as_blob_into("ui", "logo", &mut Vec::new())
+        .is_ok());
+
+    // Identifiers naming a variant are not overlaid, removed variants fall back to the next one.
+    let mut buffer = Vec::new();
+    let descriptor = asset_system
+        .load_asset_as_blob_into("ui", "strings@fr", &mut buffer)
+        .unwrap();
+    assert_eq!(descriptor.identifier(), "strings@fr");
+    patch.remove("strings@fr-fr");
+    patch.mark_removed("strings@fr");
+    assert_eq!(title(&asset_system), "Patched");
+
+    // Setting the same variants does not notify the listeners.
+    asset_system.set_variants(["fr-fr", "fr"]).unwrap();
+    assert!(receiver.try_recv().is_err());
+    asset_system.set_variants(Vec::<String>::new()).unwrap();
+    assert!(receiver.try_recv().unwrap().variants.is_empty());
+
+    // Invalidating a variant invalidates the cached base asset.
+    let cache = AssetCache::new(Arc::new(asset_system), usize::MAX);
+    let strings = cache
+        .get::<HashMap<String, String>>("ui", "strings")
+        .unwrap();
+    cache.invalidate("ui", "strings@fr");
+    assert!(!strings.ptr_eq(&cache.get("ui", "strings").unwrap()));
+    cache.get::<HashMap<String, String>>("ui", "logo").unwrap();
+    cache.clear();
+    assert_eq!(cache.bytes(), 0);
+}
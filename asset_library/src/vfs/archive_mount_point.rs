@@ -1,20 +1,57 @@
-use std::path::PathBuf;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
 
 use crate::{
-    archive::{AssetArchive, AssetArchiveMountPointHeader},
+    archive::{
+        AssetArchive, AssetArchiveFileHeader, AssetArchiveMountPointHeader, AssetArchiveReader,
+        AssetStream,
+    },
     AssetDescriptor,
 };
 
-use super::{error::VfsError, VfsMountPoint};
+use super::{error::VfsError, VfsMountPoint, VfsMountSource};
 
 pub struct ArchiveMountPoint {
-    path: PathBuf,
+    reader: Arc<AssetArchiveReader>,
     header: AssetArchiveMountPointHeader,
+    /// Index into the header's assets by identifier.
+    assets: HashMap<String, usize>,
+    removed_assets: HashSet<String>,
 }
 
 impl ArchiveMountPoint {
-    pub fn new(path: PathBuf, header: AssetArchiveMountPointHeader) -> Self {
-        Self { path, header }
+    pub fn new(reader: Arc<AssetArchiveReader>, header: AssetArchiveMountPointHeader) -> Self {
+        let mut assets = HashMap::with_capacity(header.assets().len());
+        for (index, asset) in header.assets().iter().enumerate() {
+            // Keeps the first asset if an identifier occurs more than once.
+            assets
+                .entry(asset.asset_identifier().to_string())
+                .or_insert(index);
+        }
+        let removed_assets = header.removed_assets().iter().cloned().collect();
+        Self {
+            reader,
+            header,
+            assets,
+            removed_assets,
+        }
+    }
+
+    fn find_asset(&self, identifier: &str) -> Option<&AssetArchiveFileHeader> {
+        self.assets
+            .get(identifier)
+            .map(|index| &self.header.assets()[*index])
+    }
+
+    fn descriptor(&self, asset_header: &AssetArchiveFileHeader) -> AssetDescriptor {
+        AssetDescriptor::new(
+            self.header.mount_point().to_string(),
+            asset_header.asset_identifier().to_string(),
+            asset_header.asset_format().to_string(),
+        )
+        .with_dependencies(asset_header.dependencies().to_vec())
     }
 
     pub fn from_archive(archive: &AssetArchive) -> Vec<ArchiveMountPoint> {
@@ -22,7 +59,7 @@ impl ArchiveMountPoint {
             .header()
             .mount_points()
             .iter()
-            .map(|a| ArchiveMountPoint::new(archive.path().into(), a.clone()))
+            .map(|a| ArchiveMountPoint::new(Arc::clone(archive.reader()), a.clone()))
             .collect()
     }
 }
@@ -33,50 +70,74 @@ impl VfsMountPoint for ArchiveMountPoint {
     }
 
     fn has_file(&self, identifier: &str) -> bool {
-        self.header
-            .assets()
-            .iter()
-            .find(|a| a.asset_identifier() == identifier)
-            .is_some()
+        self.assets.contains_key(identifier)
     }
 
     fn get_asset_descriptor(&self, identifier: &str) -> Option<crate::AssetDescriptor> {
-        let asset_header = self
-            .header
-            .assets()
-            .iter()
-            .find(|e| e.asset_identifier() == identifier)?;
-        Some(AssetDescriptor::new(
-            self.header.mount_point().into(),
-            asset_header.asset_identifier().into(),
-            asset_header.asset_format().into(),
-        ))
+        let asset_header = self.find_asset(identifier)?;
+        Some(self.descriptor(asset_header))
+    }
+
+    fn is_removed(&self, identifier: &str) -> bool {
+        self.removed_assets.contains(identifier)
     }
 
     fn version(&self) -> u64 {
         *self.header.version()
     }
 
+    fn list(&self) -> Vec<AssetDescriptor> {
+        self.header
+            .assets()
+            .iter()
+            .map(|a| self.descriptor(a))
+            .collect()
+    }
+
+    fn source(&self) -> VfsMountSource {
+        match self.reader.path() {
+            Some(path) => VfsMountSource::Archive(path.to_path_buf()),
+            None => VfsMountSource::Other,
+        }
+    }
+
     fn load_asset_into(
         &self,
         identifier: &str,
         buffer: &mut Vec<u8>,
     ) -> Result<AssetDescriptor, VfsError> {
-        let asset_header = self
-            .header
-            .assets()
-            .iter()
-            .find(|e| e.asset_identifier() == identifier)
-            .ok_or("Asset not found")
-            .map_err(|_| VfsError::FileNotFound)?;
-        let result = AssetArchive::read_file_into(&self.path, asset_header, buffer);
+        let asset_header = self.find_asset(identifier).ok_or(VfsError::FileNotFound)?;
+        let result = self.reader.read_file_into(asset_header, buffer);
         match result {
-            Ok(_) => Ok(AssetDescriptor::new(
-                self.header.mount_point().to_string(),
-                identifier.to_string(),
-                asset_header.asset_format().to_string(),
-            )),
+            Ok(_) => Ok(self.descriptor(asset_header)),
             Err(e) => Err(VfsError::Other(Box::from(e))),
         }
     }
+
+    fn open_asset(
+        &self,
+        identifier: &str,
+    ) -> Result<(AssetDescriptor, Box<dyn AssetStream>), VfsError> {
+        let asset_header = self.find_asset(identifier).ok_or(VfsError::FileNotFound)?;
+        let stream = self
+            .reader
+            .open_file(asset_header)
+            .map_err(|e| VfsError::Other(Box::from(e)))?;
+        Ok((self.descriptor(asset_header), stream))
+    }
+
+    fn load_stored_asset_into(
+        &self,
+        identifier: &str,
+        buffer: &mut Vec<u8>,
+    ) -> Result<(AssetDescriptor, Option<AssetArchiveFileHeader>), VfsError> {
+        let asset_header = self.find_asset(identifier).ok_or(VfsError::FileNotFound)?;
+        let stored = self
+            .reader
+            .decrypted_file(asset_header)
+            .map_err(|e| VfsError::Other(Box::from(e)))?;
+        buffer.clear();
+        buffer.extend_from_slice(&stored);
+        Ok((self.descriptor(asset_header), Some(asset_header.clone())))
+    }
 }
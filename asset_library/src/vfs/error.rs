@@ -4,7 +4,11 @@ use std::{error::Error, fmt::Display};
 pub enum VfsError {
     MountpointNotFound,
     FileNotFound,
-    Other(Box<dyn Error>),
+    /// No mounted version of the mount point can store files.
+    ReadOnly,
+    /// The identifier can not be used to store a file, e.g. because it leaves the mounted directory.
+    InvalidIdentifier,
+    Other(Box<dyn Error + Send + Sync>),
     Io(std::io::Error),
 }
 
@@ -14,6 +18,8 @@ impl Display for VfsError {
             VfsError::Other(e) => write!(f, "{}", e),
             VfsError::FileNotFound => write!(f, "File not found."),
             VfsError::MountpointNotFound => write!(f, "Invalid mount point."),
+            VfsError::ReadOnly => write!(f, "Mount point is read only."),
+            VfsError::InvalidIdentifier => write!(f, "Invalid file identifier."),
             VfsError::Io(err) => err.fmt(f),
         }
     }
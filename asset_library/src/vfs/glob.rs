@@ -0,0 +1,34 @@
+/// Matches an identifier against a glob pattern.
+/// `*` matches any sequence of characters except `/`, `**` also matches `/`, `?` matches a single character except `/`.
+pub(crate) fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let text = text.chars().collect::<Vec<_>>();
+    matches(&pattern, &text)
+}
+
+fn matches(pattern: &[char], text: &[char]) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        ['*', '*', rest @ ..] => {
+            // `**/` also matches no directory at all.
+            if let ['/', after @ ..] = rest {
+                if matches(after, text) {
+                    return true;
+                }
+            }
+            (0..=text.len()).any(|i| matches(rest, &text[i..]))
+        }
+        ['*', rest @ ..] => {
+            let end = text.iter().position(|c| *c == '/').unwrap_or(text.len());
+            (0..=end).any(|i| matches(rest, &text[i..]))
+        }
+        ['?', rest @ ..] => match text {
+            [c, text @ ..] if *c != '/' => matches(rest, text),
+            _ => false,
+        },
+        [p, rest @ ..] => match text {
+            [c, text @ ..] if c == p => matches(rest, text),
+            _ => false,
+        },
+    }
+}
@@ -0,0 +1,193 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard},
+};
+
+use crate::{AssetDescriptor, AssetReference};
+
+use super::{
+    error::VfsError, normalize_identifier, VfsMountPoint, VfsMountSource, WritableVfsMountPoint,
+};
+
+struct MemoryFile {
+    format: String,
+    dependencies: Vec<AssetReference>,
+    bytes: Vec<u8>,
+}
+
+#[derive(Default)]
+struct MemoryFiles {
+    files: HashMap<String, MemoryFile>,
+    removed: HashSet<String>,
+}
+
+/// Keeps the files of a mount point in memory, e.g. procedurally generated assets or unsaved changes of an editor.
+/// Clones share their files, keep a clone to insert files after the mount point was mounted.
+/// Like every mount point, higher versions of the mount point hide the files of lower versions.
+#[derive(Clone)]
+pub struct MemoryMountPoint {
+    mount_point: String,
+    version: u64,
+    files: Arc<RwLock<MemoryFiles>>,
+}
+
+impl MemoryMountPoint {
+    pub fn new(mount_point: impl AsRef<str>, version: u64) -> Self {
+        Self {
+            mount_point: mount_point.as_ref().to_lowercase(),
+            version,
+            files: Default::default(),
+        }
+    }
+
+    /// Inserts a file, replacing the file with the same identifier.
+    pub fn insert(
+        &self,
+        identifier: impl AsRef<str>,
+        format: impl AsRef<str>,
+        bytes: impl Into<Vec<u8>>,
+    ) {
+        self.insert_with_dependencies(identifier, format, Vec::new(), bytes)
+    }
+
+    /// Inserts a file which requires the given assets, see `AssetSystem::dependency_order`.
+    pub fn insert_with_dependencies(
+        &self,
+        identifier: impl AsRef<str>,
+        format: impl AsRef<str>,
+        dependencies: Vec<AssetReference>,
+        bytes: impl Into<Vec<u8>>,
+    ) {
+        let identifier = normalize_identifier(identifier.as_ref());
+        let mut files = self.write_files();
+        files.removed.remove(&identifier);
+        files.files.insert(
+            identifier,
+            MemoryFile {
+                format: format.as_ref().to_lowercase(),
+                dependencies,
+                bytes: bytes.into(),
+            },
+        );
+    }
+
+    /// Removes a file, returns false if the mount point does not contain it.
+    pub fn remove(&self, identifier: impl AsRef<str>) -> bool {
+        let identifier = normalize_identifier(identifier.as_ref());
+        self.write_files().files.remove(&identifier).is_some()
+    }
+
+    /// Hides a file of lower versions of the mount point, as if it was deleted.
+    /// Inserting the file again reverts this.
+    pub fn mark_removed(&self, identifier: impl AsRef<str>) {
+        let identifier = normalize_identifier(identifier.as_ref());
+        let mut files = self.write_files();
+        files.files.remove(&identifier);
+        files.removed.insert(identifier);
+    }
+
+    /// Removes all files and removal marks.
+    pub fn clear(&self) {
+        let mut files = self.write_files();
+        files.files.clear();
+        files.removed.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.read_files().files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.read_files().files.is_empty()
+    }
+
+    fn descriptor(&self, identifier: &str, file: &MemoryFile) -> AssetDescriptor {
+        AssetDescriptor::new(
+            self.mount_point.clone(),
+            identifier.to_string(),
+            file.format.clone(),
+        )
+        .with_dependencies(file.dependencies.clone())
+    }
+
+    // A panic while holding the lock can not leave the files in an inconsistent state, poisoning is ignored.
+    fn read_files(&self) -> RwLockReadGuard<'_, MemoryFiles> {
+        self.files.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn write_files(&self) -> RwLockWriteGuard<'_, MemoryFiles> {
+        self.files.write().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl VfsMountPoint for MemoryMountPoint {
+    fn identifier(&self) -> &str {
+        &self.mount_point
+    }
+
+    fn has_file(&self, identifier: &str) -> bool {
+        self.read_files().files.contains_key(identifier)
+    }
+
+    fn get_asset_descriptor(&self, identifier: &str) -> Option<AssetDescriptor> {
+        let files = self.read_files();
+        let file = files.files.get(identifier)?;
+        Some(self.descriptor(identifier, file))
+    }
+
+    fn load_asset_into(
+        &self,
+        identifier: &str,
+        buffer: &mut Vec<u8>,
+    ) -> Result<AssetDescriptor, VfsError> {
+        let files = self.read_files();
+        let file = files.files.get(identifier).ok_or(VfsError::FileNotFound)?;
+        buffer.clear();
+        buffer.extend_from_slice(&file.bytes);
+        Ok(self.descriptor(identifier, file))
+    }
+
+    fn is_removed(&self, identifier: &str) -> bool {
+        self.read_files().removed.contains(identifier)
+    }
+
+    fn version(&self) -> u64 {
+        self.version
+    }
+
+    fn list(&self) -> Vec<AssetDescriptor> {
+        self.read_files()
+            .files
+            .iter()
+            .map(|(identifier, file)| self.descriptor(identifier, file))
+            .collect()
+    }
+
+    fn source(&self) -> VfsMountSource {
+        VfsMountSource::Memory
+    }
+
+    fn as_writable(&self) -> Option<&dyn WritableVfsMountPoint> {
+        Some(self)
+    }
+}
+
+impl WritableVfsMountPoint for MemoryMountPoint {
+    fn write_file(
+        &self,
+        identifier: &str,
+        format: &str,
+        bytes: &[u8],
+    ) -> Result<AssetDescriptor, VfsError> {
+        self.insert(identifier, format, bytes);
+        self.get_asset_descriptor(identifier)
+            .ok_or(VfsError::FileNotFound)
+    }
+
+    fn delete_file(&self, identifier: &str) -> Result<(), VfsError> {
+        match self.remove(identifier) {
+            true => Ok(()),
+            false => Err(VfsError::FileNotFound),
+        }
+    }
+}
@@ -1,13 +1,50 @@
 pub mod archive_mount_point;
 pub mod error;
+mod glob;
+pub mod memory_mount_point;
 pub mod physical_mount_point;
+pub mod watcher;
 
-use crate::AssetDescriptor;
+use crate::{
+    archive::{AssetArchiveFileHeader, AssetStream},
+    AssetDescriptor,
+};
 use error::VfsError;
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
 use utils::*;
 
-pub trait VfsMountPoint: Send + 'static {
+/// Where the files of a mount point are stored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VfsMountSource {
+    Directory(PathBuf),
+    Archive(PathBuf),
+    Memory,
+    Other,
+}
+
+/// Describes a mounted mount point, see `VirtualFileSystem::mounted`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VfsMountInfo {
+    pub identifier: String,
+    pub version: u64,
+    pub source: VfsMountSource,
+}
+
+impl VfsMountInfo {
+    fn new(mount_point: &dyn VfsMountPoint) -> Self {
+        Self {
+            identifier: mount_point.identifier().to_string(),
+            version: mount_point.version(),
+            source: mount_point.source(),
+        }
+    }
+}
+
+/// Mount points are shared between threads, the asset system loads assets from several threads at once.
+pub trait VfsMountPoint: Send + Sync + 'static {
     fn identifier(&self) -> &str;
     fn has_file(&self, identifier: &str) -> bool;
     fn get_asset_descriptor(&self, identifier: &str) -> Option<AssetDescriptor>;
@@ -16,22 +53,101 @@ pub trait VfsMountPoint: Send + 'static {
         identifier: &str,
         buffer: &mut Vec<u8>,
     ) -> Result<AssetDescriptor, VfsError>;
+    /// Loads the asset as it is stored, decrypted but without decompressing it.
+    /// Returns the archive file header required for decompression, or `None` if the asset is stored uncompressed.
+    fn load_stored_asset_into(
+        &self,
+        identifier: &str,
+        buffer: &mut Vec<u8>,
+    ) -> Result<(AssetDescriptor, Option<AssetArchiveFileHeader>), VfsError> {
+        self.load_asset_into(identifier, buffer)
+            .map(|descriptor| (descriptor, None))
+    }
+    /// Opens the asset for streaming, see `AssetArchiveReader::open_file` for how archived files are streamed.
+    /// By default the asset is loaded into memory.
+    fn open_asset(
+        &self,
+        identifier: &str,
+    ) -> Result<(AssetDescriptor, Box<dyn AssetStream>), VfsError> {
+        let mut buffer = Vec::new();
+        let descriptor = self.load_asset_into(identifier, &mut buffer)?;
+        Ok((descriptor, Box::new(std::io::Cursor::new(buffer))))
+    }
+    /// Returns true if the mount point deletes the file, hiding it in lower versions of the mount point.
+    fn is_removed(&self, _identifier: &str) -> bool {
+        false
+    }
     fn version(&self) -> u64;
+    /// Returns the descriptors of all files provided by the mount point.
+    fn list(&self) -> Vec<AssetDescriptor>;
+    fn source(&self) -> VfsMountSource {
+        VfsMountSource::Other
+    }
+    /// Returns the mount point as writable mount point, if it can store files.
+    fn as_writable(&self) -> Option<&dyn WritableVfsMountPoint> {
+        None
+    }
+}
+
+/// A mount point which can store files, for example user data and save files.
+/// Writable mount points return themselves from `VfsMountPoint::as_writable`.
+pub trait WritableVfsMountPoint: VfsMountPoint {
+    /// Creates or replaces a file.
+    /// Files are replaced atomically, readers either see the previous or the new contents.
+    fn write_file(
+        &self,
+        identifier: &str,
+        format: &str,
+        bytes: &[u8],
+    ) -> Result<AssetDescriptor, VfsError>;
+    fn delete_file(&self, identifier: &str) -> Result<(), VfsError>;
+}
+
+/// Separates an identifier from its variant, e.g. `ui/strings@fr-fr`.
+pub const VARIANT_SEPARATOR: char = '@';
+
+/// Returns the identifier without its variant, `ui/strings@fr-fr` becomes `ui/strings`.
+pub fn base_identifier(identifier: &str) -> &str {
+    match identifier.split_once(VARIANT_SEPARATOR) {
+        Some((base, _)) => base,
+        None => identifier,
+    }
 }
 
 pub struct VirtualFileSystem {
-    mounts: HashMap<String, Vec<Box<dyn VfsMountPoint + Send>>>,
+    mounts: HashMap<String, Vec<Box<dyn VfsMountPoint>>>,
+    variants: Vec<String>,
 }
 
 impl Default for VirtualFileSystem {
     fn default() -> Self {
         Self {
             mounts: Default::default(),
+            variants: Default::default(),
         }
     }
 }
 
 impl VirtualFileSystem {
+    /// Returns the active variants, most specific first.
+    pub fn variants(&self) -> &[String] {
+        &self.variants
+    }
+
+    /// Sets the variants tried before the base identifier, most specific first, e.g. `fr-FR` and `fr`.
+    /// A lookup of `ui/strings` then tries `ui/strings@fr-fr`, `ui/strings@fr` and `ui/strings`,
+    /// each in all versions of the mount point. Identifiers which name a variant are not overlaid.
+    /// Variants are case insensitive, empty and repeated variants are ignored.
+    pub fn set_variants(&mut self, variants: impl IntoIterator<Item = impl AsRef<str>>) {
+        self.variants.clear();
+        for variant in variants {
+            let variant = variant.as_ref().trim().to_lowercase();
+            if !variant.is_empty() && !self.variants.contains(&variant) {
+                self.variants.push(variant);
+            }
+        }
+    }
+
     /// Mounts a new virtual mountpoint into the virtual file system.
     pub fn mount(&mut self, mountpoint: impl VfsMountPoint) -> bool {
         t_info!(
@@ -50,7 +166,7 @@ impl VirtualFileSystem {
                 }
             },
             None => {
-                let mut v: Vec<Box<dyn VfsMountPoint + Send>> = Vec::with_capacity(4);
+                let mut v: Vec<Box<dyn VfsMountPoint>> = Vec::with_capacity(4);
                 let key = mountpoint.identifier().into();
                 v.push(Box::new(mountpoint));
                 self.mounts.insert(key, v);
@@ -60,6 +176,148 @@ impl VirtualFileSystem {
         true
     }
 
+    /// Unmounts the mount point with the given identifier and version.
+    /// Returns false if no such mount point is mounted.
+    pub fn unmount(&mut self, mount_point: impl AsRef<str>, version: u64) -> bool {
+        let identifier = mount_point.as_ref().to_lowercase();
+        let mounts = match self.mounts.get_mut(&identifier) {
+            Some(v) => v,
+            None => return false,
+        };
+        match mounts.binary_search_by_key(&version, |e| e.version()) {
+            Ok(index) => {
+                mounts.remove(index);
+            }
+            Err(_) => return false,
+        }
+        if mounts.is_empty() {
+            self.mounts.remove(&identifier);
+        }
+        t_info!("Unmounted mountpoint: {} version: {}", identifier, version);
+        true
+    }
+
+    /// Unmounts all mount points which are provided by the archive at `path`.
+    /// Returns the number of unmounted mount points.
+    pub fn unmount_archive(&mut self, path: impl AsRef<Path>) -> usize {
+        let path = path.as_ref();
+        let mut counter = 0;
+        self.mounts.retain(|_, mounts| {
+            mounts.retain(|mount| match mount.source() {
+                VfsMountSource::Archive(archive) if is_same_file(&archive, path) => {
+                    counter += 1;
+                    false
+                }
+                _ => true,
+            });
+            !mounts.is_empty()
+        });
+        t_info!("Unmounted {} mountpoints of archive: {:#?}", counter, path);
+        counter
+    }
+
+    /// Returns all mounted mount points, ordered by identifier and version.
+    pub fn mounted(&self) -> Vec<VfsMountInfo> {
+        let mut mounted = self
+            .mounts
+            .values()
+            .flat_map(|mounts| mounts.iter().map(|m| VfsMountInfo::new(m.as_ref())))
+            .collect::<Vec<_>>();
+        mounted.sort_by(|a, b| (&a.identifier, a.version).cmp(&(&b.identifier, b.version)));
+        mounted
+    }
+
+    /// Returns the mount point which serves the file, or `None` if no mount point provides it.
+    pub fn resolve(
+        &self,
+        mount_point: impl AsRef<str>,
+        file_identifier: impl AsRef<str>,
+    ) -> Option<VfsMountInfo> {
+        let mounts = self.mounts.get(&mount_point.as_ref().to_lowercase())?;
+        let identifier = normalize_identifier(file_identifier.as_ref());
+        self.variant_candidates(&identifier)
+            .into_iter()
+            .find_map(|candidate| {
+                for mount in mounts.iter().rev() {
+                    if mount.is_removed(&candidate) {
+                        return None;
+                    }
+                    if mount.has_file(&candidate) {
+                        return Some(VfsMountInfo::new(mount.as_ref()));
+                    }
+                }
+                None
+            })
+    }
+
+    /// Returns the descriptor of the file served by the highest version of the mount point.
+    pub fn descriptor(
+        &self,
+        mount_point: impl AsRef<str>,
+        file_identifier: impl AsRef<str>,
+    ) -> Result<AssetDescriptor, VfsError> {
+        self.find_in_mounts(mount_point, file_identifier, |mount, identifier| {
+            mount
+                .get_asset_descriptor(identifier)
+                .ok_or(VfsError::FileNotFound)
+        })
+    }
+
+    /// Returns the descriptors of all files of a mount point, ordered by identifier.
+    /// All mounted versions are merged, files of higher versions hide files of lower versions.
+    pub fn list(&self, mount_point: impl AsRef<str>) -> Result<Vec<AssetDescriptor>, VfsError> {
+        let mounts = match self.mounts.get(&mount_point.as_ref().to_lowercase()) {
+            Some(v) => v,
+            None => return Err(VfsError::MountpointNotFound),
+        };
+        let mut identifiers = HashSet::new();
+        let mut files = vec![];
+        for (index, mount) in mounts.iter().enumerate().rev() {
+            let higher = &mounts[index + 1..];
+            for descriptor in mount.list() {
+                if identifiers.contains(descriptor.identifier())
+                    || higher.iter().any(|m| m.is_removed(descriptor.identifier()))
+                {
+                    continue;
+                }
+                identifiers.insert(descriptor.identifier().to_string());
+                files.push(descriptor);
+            }
+        }
+        files.sort_by(|a, b| a.identifier().cmp(b.identifier()));
+        Ok(files)
+    }
+
+    /// Returns the descriptors of all files of a mount point with the given format, see `list`.
+    pub fn list_with_format(
+        &self,
+        mount_point: impl AsRef<str>,
+        format: impl AsRef<str>,
+    ) -> Result<Vec<AssetDescriptor>, VfsError> {
+        let format = format.as_ref().to_lowercase();
+        let mut files = self.list(mount_point)?;
+        files.retain(|f| f.format() == format);
+        Ok(files)
+    }
+
+    /// Returns the descriptors of all files of a mount point matching a glob pattern, see `list`.
+    /// The pattern is matched against the identifier, with and without the format as extension.
+    /// For example `shaders/*.spv` matches all SPIR-V files in the shaders directory, `meshes/**` matches all meshes.
+    pub fn glob(
+        &self,
+        mount_point: impl AsRef<str>,
+        pattern: impl AsRef<str>,
+    ) -> Result<Vec<AssetDescriptor>, VfsError> {
+        let pattern = normalize_identifier(pattern.as_ref());
+        let mut files = self.list(mount_point)?;
+        files.retain(|f| {
+            glob::glob_match(&pattern, f.identifier())
+                || (!f.format().is_empty()
+                    && glob::glob_match(&pattern, &format!("{}.{}", f.identifier(), f.format())))
+        });
+        Ok(files)
+    }
+
     pub fn read_file(
         &self,
         mount_point: impl AsRef<str>,
@@ -75,22 +333,136 @@ impl VirtualFileSystem {
         &self,
         mount_point: impl AsRef<str>,
         file_identifier: impl AsRef<str>,
-        mut buffer: &mut Vec<u8>,
+        buffer: &mut Vec<u8>,
     ) -> Result<AssetDescriptor, VfsError> {
+        self.find_in_mounts(mount_point, file_identifier, |mount, identifier| {
+            mount.load_asset_into(identifier, buffer)
+        })
+    }
+
+    /// Reads a file as it is stored by the mount point which serves it, without decompressing it.
+    pub fn read_stored_file_into(
+        &self,
+        mount_point: impl AsRef<str>,
+        file_identifier: impl AsRef<str>,
+        buffer: &mut Vec<u8>,
+    ) -> Result<(AssetDescriptor, Option<AssetArchiveFileHeader>), VfsError> {
+        self.find_in_mounts(mount_point, file_identifier, |mount, identifier| {
+            mount.load_stored_asset_into(identifier, buffer)
+        })
+    }
+
+    /// Opens a file for streaming from the mount point which serves it, see `VfsMountPoint::open_asset`.
+    pub fn open_file(
+        &self,
+        mount_point: impl AsRef<str>,
+        file_identifier: impl AsRef<str>,
+    ) -> Result<(AssetDescriptor, Box<dyn AssetStream>), VfsError> {
+        self.find_in_mounts(mount_point, file_identifier, |mount, identifier| {
+            mount.open_asset(identifier)
+        })
+    }
+
+    /// Stores a file in the highest writable version of the mount point, see `WritableVfsMountPoint::write_file`.
+    pub fn write_file(
+        &self,
+        mount_point: impl AsRef<str>,
+        file_identifier: impl AsRef<str>,
+        format: impl AsRef<str>,
+        bytes: &[u8],
+    ) -> Result<AssetDescriptor, VfsError> {
+        let identifier = normalize_identifier(file_identifier.as_ref());
+        self.writable_mount(mount_point)?.write_file(
+            &identifier,
+            &format.as_ref().to_lowercase(),
+            bytes,
+        )
+    }
+
+    /// Deletes a file from the highest writable version of the mount point.
+    /// Files provided by read only versions of the mount point are not affected.
+    pub fn delete_file(
+        &self,
+        mount_point: impl AsRef<str>,
+        file_identifier: impl AsRef<str>,
+    ) -> Result<(), VfsError> {
+        let identifier = normalize_identifier(file_identifier.as_ref());
+        self.writable_mount(mount_point)?.delete_file(&identifier)
+    }
+
+    fn writable_mount(
+        &self,
+        mount_point: impl AsRef<str>,
+    ) -> Result<&dyn WritableVfsMountPoint, VfsError> {
+        let mounts = match self.mounts.get(&mount_point.as_ref().to_lowercase()) {
+            Some(v) => v,
+            None => return Err(VfsError::MountpointNotFound),
+        };
+        mounts
+            .iter()
+            .rev()
+            .find_map(|mount| mount.as_writable())
+            .ok_or(VfsError::ReadOnly)
+    }
+
+    /// Returns the identifiers tried for a lookup, see `set_variants`.
+    fn variant_candidates(&self, identifier: &str) -> Vec<String> {
+        if identifier.contains(VARIANT_SEPARATOR) {
+            return vec![identifier.to_string()];
+        }
+        self.variants
+            .iter()
+            .map(|variant| format!("{}{}{}", identifier, VARIANT_SEPARATOR, variant))
+            .chain(std::iter::once(identifier.to_string()))
+            .collect()
+    }
+
+    /// Calls `load` on each version of the mount point, starting at the highest version,
+    /// until one of them provides the file. Each variant of the file is searched before the next one.
+    fn find_in_mounts<R>(
+        &self,
+        mount_point: impl AsRef<str>,
+        file_identifier: impl AsRef<str>,
+        mut load: impl FnMut(&dyn VfsMountPoint, &str) -> Result<R, VfsError>,
+    ) -> Result<R, VfsError> {
         let mounts = match self.mounts.get(&mount_point.as_ref().to_lowercase()) {
             Some(v) => v,
             None => return Err(VfsError::MountpointNotFound),
         };
-        for mount in mounts.iter().rev() {
-            match mount.load_asset_into(&file_identifier.as_ref().to_lowercase(), &mut buffer) {
-                Ok(a) => return Ok(a),
-                Err(VfsError::FileNotFound) => continue,
-                Err(e) => {
-                    t_warn!("Error occurred while loading file: {}", e);
-                    return Err(e);
+        let identifier = normalize_identifier(file_identifier.as_ref());
+        'candidates: for candidate in self.variant_candidates(&identifier) {
+            for mount in mounts.iter().rev() {
+                if mount.is_removed(&candidate) {
+                    continue 'candidates;
+                }
+                match load(mount.as_ref(), &candidate) {
+                    Ok(a) => return Ok(a),
+                    Err(VfsError::FileNotFound) => continue,
+                    Err(e) => {
+                        t_warn!("Error occurred while loading file: {}", e);
+                        return Err(e);
+                    }
                 }
             }
         }
         Err(VfsError::FileNotFound)
     }
 }
+
+/// Identifiers are case insensitive paths using `/` as separator, see `collect_directory_files`.
+fn normalize_identifier(identifier: &str) -> String {
+    identifier
+        .trim_start_matches(['/', '\\'])
+        .replace('\\', "/")
+        .to_lowercase()
+}
+
+fn is_same_file(a: &Path, b: &Path) -> bool {
+    if a == b {
+        return true;
+    }
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
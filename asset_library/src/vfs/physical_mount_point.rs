@@ -1,34 +1,96 @@
 use crate::*;
 use serde::{Deserialize, Serialize};
 use std::{
-    fs::{read_dir, DirEntry, File},
-    io::{BufReader, Read, Seek, SeekFrom},
+    collections::HashMap,
+    fs::{create_dir_all, remove_file, rename, File},
+    io::{BufReader, Read, Seek, SeekFrom, Write},
     path::*,
+    sync::{Arc, RwLock},
+    time::Duration,
 };
 
-use crate::{vfs::*, AssetDescriptor};
+use crate::{
+    archive::AssetStream,
+    asset_system::AssetDidChange,
+    vfs::{
+        watcher::{DirectoryChanges, VfsDirectoryWatcher},
+        *,
+    },
+    AssetDescriptor,
+};
 
-const DEFAULT_INDEX_FILE_NAME: &'static str = "index.yaml";
+pub(crate) const DEFAULT_INDEX_FILE_NAME: &'static str = "index.yaml";
 
+/// Contents of an index file.
 #[derive(Debug, Serialize, Deserialize, Clone)]
+struct AssetIndexFile {
+    version: Option<u64>,
+    files: Vec<AssetDescriptor>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(from = "AssetIndexFile", into = "AssetIndexFile")]
 pub struct AssetIndex {
     version: Option<u64>,
     files: Vec<AssetDescriptor>,
+    /// Index into `files` by identifier.
+    lookup: HashMap<String, usize>,
+}
+
+impl From<AssetIndexFile> for AssetIndex {
+    fn from(file: AssetIndexFile) -> Self {
+        let mut lookup = HashMap::with_capacity(file.files.len());
+        for (index, descriptor) in file.files.iter().enumerate() {
+            lookup
+                .entry(descriptor.identifier().to_string())
+                .or_insert(index);
+        }
+        Self {
+            version: file.version,
+            files: file.files,
+            lookup,
+        }
+    }
+}
+
+impl From<AssetIndex> for AssetIndexFile {
+    fn from(index: AssetIndex) -> Self {
+        Self {
+            version: index.version,
+            files: index.files,
+        }
+    }
 }
 
 impl AssetIndex {
     pub fn has_file(&self, identifier: &str) -> bool {
-        self.files
-            .iter()
-            .find(|d| d.identifier() == identifier)
-            .is_some()
+        self.lookup.contains_key(identifier)
+    }
+
+    pub fn file(&self, identifier: &str) -> Option<&AssetDescriptor> {
+        self.lookup.get(identifier).map(|index| &self.files[*index])
     }
 }
 
+/// Files of the mounted directory by identifier, see `collect_directory_files`.
+type DirectoryIndex = HashMap<String, DirectoryFile>;
+
+/// The index file of the mounted directory, replaced when the file changes.
+type SharedAssetIndex = RwLock<Option<Arc<AssetIndex>>>;
+
+/// Mounts a directory and all of its sub directories.
+/// Files are identified by their path relative to the directory, e.g. `meshes/triangle_2d_ndc`.
+/// The files are indexed when the directory is mounted, watched mount points keep the index up to date.
+/// The version is read from the index file when mounting and kept until the directory is mounted again,
+/// mount points of the same name are ordered by their version.
 pub struct VfsPhysicalMountPoint {
     mount_point: String,
     directory: PathBuf,
-    index: Option<AssetIndex>,
+    version: u64,
+    index: Arc<SharedAssetIndex>,
+    files: Arc<RwLock<DirectoryIndex>>,
+    watcher: Option<VfsDirectoryWatcher>,
+    writable: bool,
 }
 
 impl VfsPhysicalMountPoint {
@@ -36,108 +98,275 @@ impl VfsPhysicalMountPoint {
         mount_point: &impl AsRef<str>,
         directory: &impl AsRef<Path>,
     ) -> Result<Self, std::io::Error> {
-        let mut mount = Self {
-            mount_point: mount_point.as_ref().into(),
+        let files = index_directory(directory.as_ref())?;
+        let index = load_asset_index(directory.as_ref())?;
+        Ok(Self {
+            mount_point: mount_point.as_ref().to_lowercase(),
             directory: directory.as_ref().into(),
-            index: None,
-        };
-        let directory = read_dir(directory)?;
-        for f in directory {
-            if let Ok(f) = f {
-                if f.file_name() == DEFAULT_INDEX_FILE_NAME {
-                    let bytes = load_file_bin(f.path())?;
-                    match serde_yaml::from_slice::<AssetIndex>(&bytes) {
-                        Ok(index) => {
-                            mount.index = index.into();
-                            break;
-                        }
-                        Err(err) => {
-                            t_warn!("{:#?}", err)
-                        }
-                    }
-                }
-            }
-        }
+            version: asset_index_version(&index),
+            index: Arc::new(RwLock::new(index)),
+            files: Arc::new(RwLock::new(files)),
+            watcher: None,
+            writable: false,
+        })
+    }
 
+    /// Mounts a directory which can store files, see `WritableVfsMountPoint`.
+    /// The directory is created if it does not exist, e.g. a user data directory for save files.
+    pub fn new_writable(
+        mount_point: &impl AsRef<str>,
+        directory: &impl AsRef<Path>,
+    ) -> Result<Self, std::io::Error> {
+        create_dir_all(directory)?;
+        let mut mount = Self::new(mount_point, directory)?;
+        mount.writable = true;
         Ok(mount)
     }
 
-    pub fn asset_index(&self) -> &Option<AssetIndex> {
-        &self.index
+    pub fn is_writable(&self) -> bool {
+        self.writable
     }
 
-    fn find_dir_entry(&self, identifier: &str) -> Result<DirEntry, VfsError> {
-        read_dir(&self.directory)?
-            .into_iter()
-            .filter_map(|f| f.ok())
-            .filter(|f| match f.file_type() {
-                Ok(f) => f.is_file(),
-                Err(_) => false,
-            })
-            .find(|e| {
-                if let Some(p) = e.path().file_stem() {
-                    p == identifier
-                } else {
-                    false
+    /// Returns the index file of the directory, if it has one.
+    pub fn asset_index(&self) -> Option<Arc<AssetIndex>> {
+        self.index.read().ok().and_then(|index| index.clone())
+    }
+
+    /// Starts watching the mounted directory for changes.
+    /// `on_change` is called from a background thread for every added, modified or removed file.
+    /// Changes of the index file are reported for the files whose descriptors changed.
+    /// Watching stops when the mount point is dropped.
+    pub fn watch(
+        &mut self,
+        poll_interval: Duration,
+        on_change: impl Fn(AssetDidChange) + Send + 'static,
+    ) -> Result<(), std::io::Error> {
+        let mount_point = self.mount_point.clone();
+        let directory = self.directory.clone();
+        let version = self.version;
+        let index = Arc::clone(&self.index);
+        let files = Arc::clone(&self.files);
+        self.watcher = Some(VfsDirectoryWatcher::new(
+            &self.mount_point,
+            &self.directory,
+            poll_interval,
+            move |changes: DirectoryChanges| {
+                // The directory is indexed once per poll, before reporting the changes so added files can be loaded right away.
+                if let Err(e) = refresh_index(&directory, &files) {
+                    t_warn!("Could not index directory {:#?}: {}", directory, e);
                 }
-            })
+                let mut identifiers = changes.identifiers;
+                if changes.index_changed {
+                    match reload_asset_index(&directory, version, &index) {
+                        Ok(changed) => identifiers.extend(changed),
+                        Err(e) => t_warn!("Could not load the index of {:#?}: {}", directory, e),
+                    }
+                    identifiers.sort();
+                    identifiers.dedup();
+                }
+                for identifier in identifiers {
+                    t_trace!("Asset did change: {} - {}", mount_point, identifier);
+                    on_change(AssetDidChange {
+                        mount: mount_point.clone(),
+                        identifier,
+                    });
+                }
+            },
+        )?);
+        Ok(())
+    }
+
+    /// Rebuilds the index of the mounted directory and loads its index file again.
+    /// Only required for files which were added, removed or changed in the index file after mounting,
+    /// if the mount point is not watched.
+    pub fn refresh_index(&self) -> Result<(), VfsError> {
+        refresh_index(&self.directory, &self.files)?;
+        reload_asset_index(&self.directory, self.version, &self.index)?;
+        Ok(())
+    }
+
+    pub fn is_watched(&self) -> bool {
+        self.watcher.is_some()
+    }
+
+    /// Returns the path a file with the given identifier and format is stored at.
+    fn file_path(&self, identifier: &str, format: &str) -> Result<PathBuf, VfsError> {
+        let components = identifier.split('/').collect::<Vec<_>>();
+        let valid_component =
+            |c: &&str| !c.is_empty() && *c != "." && *c != ".." && !c.contains([':', '\\']);
+        if !components.iter().all(valid_component) || format.contains(['/', '\\', ':', '.']) {
+            return Err(VfsError::InvalidIdentifier);
+        }
+        let mut path = self.directory.join(components.join("/"));
+        if !format.is_empty() {
+            let file_name = format!("{}.{}", components[components.len() - 1], format);
+            path.set_file_name(file_name);
+        }
+        Ok(path)
+    }
+
+    fn write_index(&self) -> Result<std::sync::RwLockWriteGuard<'_, DirectoryIndex>, VfsError> {
+        self.files
+            .write()
+            .map_err(|e| VfsError::Other(Box::from(e.to_string())))
+    }
+
+    /// Returns the path and format of a file using the cached index.
+    fn find_file(&self, identifier: &str) -> Result<(PathBuf, String), VfsError> {
+        let files = self
+            .files
+            .read()
+            .map_err(|e| VfsError::Other(Box::from(e.to_string())))?;
+        files
+            .get(identifier)
+            .map(|f| (f.path.clone(), f.format.clone()))
             .ok_or(VfsError::FileNotFound)
     }
 }
 
+fn index_directory(directory: &Path) -> Result<DirectoryIndex, std::io::Error> {
+    Ok(collect_directory_files(directory)?
+        .into_iter()
+        .map(|f| (f.identifier.clone(), f))
+        .collect())
+}
+
+/// Loads the index file of a directory, a missing or invalid index file is ignored.
+fn load_asset_index(directory: &Path) -> Result<Option<Arc<AssetIndex>>, std::io::Error> {
+    let path = directory.join(DEFAULT_INDEX_FILE_NAME);
+    if !path.is_file() {
+        return Ok(None);
+    }
+    match serde_yaml::from_slice::<AssetIndex>(&load_file_bin(path)?) {
+        Ok(index) => Ok(Some(Arc::new(index))),
+        Err(err) => {
+            t_warn!("{:#?}", err);
+            Ok(None)
+        }
+    }
+}
+
+fn asset_index_version(index: &Option<Arc<AssetIndex>>) -> u64 {
+    index
+        .as_ref()
+        .and_then(|index| index.version)
+        .unwrap_or_default()
+}
+
+/// Replaces the index with the current index file of the directory.
+/// Returns the identifiers of the files which were added to, changed in or removed from the index file.
+fn reload_asset_index(
+    directory: &Path,
+    version: u64,
+    index: &SharedAssetIndex,
+) -> Result<Vec<String>, VfsError> {
+    let current = load_asset_index(directory)?;
+    if asset_index_version(&current) != version {
+        t_warn!(
+            "The version of {:#?} changed to {}, it is used once the directory is mounted again.",
+            directory,
+            asset_index_version(&current)
+        );
+    }
+    let mut index = index
+        .write()
+        .map_err(|e| VfsError::Other(Box::from(e.to_string())))?;
+    let previous = std::mem::replace(&mut *index, current);
+    let changed = |from: &Option<Arc<AssetIndex>>, to: &Option<Arc<AssetIndex>>| {
+        from.iter()
+            .flat_map(|from| from.files.iter())
+            .filter(|descriptor| {
+                to.as_ref().and_then(|to| to.file(descriptor.identifier())) != Some(*descriptor)
+            })
+            .map(|descriptor| descriptor.identifier().to_string())
+            .collect::<Vec<_>>()
+    };
+    let mut identifiers = changed(&previous, &index);
+    identifiers.extend(changed(&index, &previous));
+    identifiers.sort();
+    identifiers.dedup();
+    Ok(identifiers)
+}
+
+fn refresh_index(directory: &Path, files: &RwLock<DirectoryIndex>) -> Result<(), VfsError> {
+    let index = index_directory(directory)?;
+    let mut files = files
+        .write()
+        .map_err(|e| VfsError::Other(Box::from(e.to_string())))?;
+    *files = index;
+    Ok(())
+}
+
 impl VfsMountPoint for VfsPhysicalMountPoint {
     fn identifier(&self) -> &str {
         &self.mount_point
     }
 
     fn has_file(&self, identifier: &str) -> bool {
-        return match &self.index {
+        return match &self.asset_index() {
             Some(index) => {
                 // Uses the index file to load files. All files are by definition uncompressed.
                 if index.has_file(identifier) {
                     return true;
                 } else {
                     // Not in index.yaml, load from direcotry instead.
-                    self.find_dir_entry(identifier).is_ok()
+                    self.find_file(identifier).is_ok()
                 }
             }
             None => {
                 // File structure is used instead.
-                self.find_dir_entry(identifier).is_ok()
+                self.find_file(identifier).is_ok()
             }
         };
     }
 
     fn version(&self) -> u64 {
-        if let Some(index) = &self.index {
-            return index.version.unwrap_or_default();
-        } else {
-            0
+        self.version
+    }
+
+    fn list(&self) -> Vec<AssetDescriptor> {
+        let index = self.asset_index();
+        match self.files.read() {
+            Ok(files) => files
+                .values()
+                .map(|f| {
+                    // Descriptors of the index file declare dependencies.
+                    match index.as_ref().and_then(|i| i.file(&f.identifier)) {
+                        Some(descriptor) => descriptor.clone(),
+                        None => AssetDescriptor::new(
+                            self.mount_point.clone(),
+                            f.identifier.clone(),
+                            f.format.clone(),
+                        ),
+                    }
+                })
+                .collect(),
+            Err(e) => {
+                t_warn!("{}", e);
+                vec![]
+            }
+        }
+    }
+
+    fn source(&self) -> VfsMountSource {
+        VfsMountSource::Directory(self.directory.clone())
+    }
+
+    fn as_writable(&self) -> Option<&dyn WritableVfsMountPoint> {
+        match self.writable {
+            true => Some(self),
+            false => None,
         }
     }
 
     fn get_asset_descriptor(&self, identifier: &str) -> Option<AssetDescriptor> {
         let find = || -> Option<AssetDescriptor> {
-            let fd = self.find_dir_entry(identifier).ok()?;
-            let path = fd.path();
-            let extension = match path.extension() {
-                Some(ext) => ext.to_str(),
-                None => None,
-            };
-            AssetDescriptor::new(
-                self.mount_point.clone(),
-                identifier.to_string(),
-                extension.unwrap_or("").to_string(),
-            )
-            .into()
+            let (_, format) = self.find_file(identifier).ok()?;
+            AssetDescriptor::new(self.mount_point.clone(), identifier.to_string(), format).into()
         };
 
         if let Some(asset_index) = self.asset_index() {
-            if let Some(file) = asset_index
-                .files
-                .iter()
-                .find(|e| e.identifier() == identifier)
-            {
+            if let Some(file) = asset_index.file(identifier) {
                 file.clone().into()
             } else {
                 find()
@@ -152,20 +381,82 @@ impl VfsMountPoint for VfsPhysicalMountPoint {
         identifier: &str,
         buffer: &mut Vec<u8>,
     ) -> Result<AssetDescriptor, VfsError> {
-        let fd = self.find_dir_entry(identifier)?;
-        let file = File::open(fd.path())?;
+        let (path, format) = self.find_file(identifier)?;
+        let file = File::open(path)?;
         let mut buf_reader = BufReader::new(file);
         buf_reader.seek(SeekFrom::Start(0))?;
         buf_reader.read_to_end(buffer)?;
-        let path = fd.path();
-        let extension = match path.extension() {
-            Some(ext) => ext.to_str(),
-            None => None,
-        };
         Ok(AssetDescriptor::new(
             self.mount_point.clone(),
             identifier.to_string(),
-            extension.unwrap_or("").to_string(),
+            format,
         ))
     }
+
+    /// Streams the file directly from disk.
+    fn open_asset(
+        &self,
+        identifier: &str,
+    ) -> Result<(AssetDescriptor, Box<dyn AssetStream>), VfsError> {
+        let (path, format) = self.find_file(identifier)?;
+        let file = BufReader::new(File::open(path)?);
+        Ok((
+            AssetDescriptor::new(self.mount_point.clone(), identifier.to_string(), format),
+            Box::new(file),
+        ))
+    }
+}
+
+impl WritableVfsMountPoint for VfsPhysicalMountPoint {
+    fn write_file(
+        &self,
+        identifier: &str,
+        format: &str,
+        bytes: &[u8],
+    ) -> Result<AssetDescriptor, VfsError> {
+        let path = self.file_path(identifier, format)?;
+        let directory = path.parent().ok_or(VfsError::InvalidIdentifier)?;
+        create_dir_all(directory)?;
+
+        let temporary = temporary_path(&path).ok_or(VfsError::InvalidIdentifier)?;
+        let mut file = File::create(&temporary)?;
+        file.write_all(bytes)?;
+        file.sync_all()?;
+        drop(file);
+        if let Err(e) = rename(&temporary, &path) {
+            let _ = remove_file(&temporary);
+            return Err(e.into());
+        }
+
+        let mut files = self.write_index()?;
+        // Files are identified without their format, the file of the previous format is replaced.
+        if let Some(previous) = files.get(identifier) {
+            if previous.path != path {
+                if let Err(e) = remove_file(&previous.path) {
+                    t_warn!("Could not remove replaced file {:#?}: {}", previous.path, e);
+                }
+            }
+        }
+        files.insert(
+            identifier.to_string(),
+            DirectoryFile {
+                identifier: identifier.to_string(),
+                format: format.to_string(),
+                path,
+            },
+        );
+        Ok(AssetDescriptor::new(
+            self.mount_point.clone(),
+            identifier.to_string(),
+            format.to_string(),
+        ))
+    }
+
+    fn delete_file(&self, identifier: &str) -> Result<(), VfsError> {
+        let mut files = self.write_index()?;
+        let file = files.get(identifier).ok_or(VfsError::FileNotFound)?;
+        remove_file(&file.path)?;
+        files.remove(identifier);
+        Ok(())
+    }
 }
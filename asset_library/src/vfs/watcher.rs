@@ -0,0 +1,131 @@
+use std::{
+    collections::HashMap,
+    fs::metadata,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::JoinHandle,
+    time::{Duration, SystemTime},
+};
+
+use crate::{vfs::physical_mount_point::DEFAULT_INDEX_FILE_NAME, *};
+
+/// Modification time and size of a file, used to detect changes between polls.
+type FileStamp = (Option<SystemTime>, u64);
+
+/// Changes found by one poll of a [`VfsDirectoryWatcher`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct DirectoryChanges {
+    /// Identifiers of the added, modified and removed files, sorted.
+    pub identifiers: Vec<String>,
+    /// True if the index file of the directory was added, modified or removed.
+    pub index_changed: bool,
+}
+
+/// Stamps of all files of a directory and of its index file.
+struct DirectorySnapshot {
+    files: HashMap<String, FileStamp>,
+    index: Option<FileStamp>,
+}
+
+/// Polls a directory for added, modified and removed files on a background thread.
+/// All changes found by a poll are reported at once, polls without changes are not reported.
+/// The thread is stopped when the watcher is dropped.
+pub struct VfsDirectoryWatcher {
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl VfsDirectoryWatcher {
+    pub fn new(
+        mount_point: impl AsRef<str>,
+        directory: impl AsRef<Path>,
+        poll_interval: Duration,
+        on_changes: impl Fn(DirectoryChanges) + Send + 'static,
+    ) -> Result<Self, std::io::Error> {
+        let mount_point = mount_point.as_ref().to_lowercase();
+        let directory = PathBuf::from(directory.as_ref());
+        let mut previous = snapshot(&directory)?;
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = Arc::clone(&stop);
+
+        let thread = std::thread::Builder::new()
+            .name(format!("vfs watcher: {}", mount_point))
+            .spawn(move || {
+                while !thread_stop.load(Ordering::Acquire) {
+                    std::thread::park_timeout(poll_interval);
+                    if thread_stop.load(Ordering::Acquire) {
+                        break;
+                    }
+                    let current = match snapshot(&directory) {
+                        Ok(v) => v,
+                        Err(e) => {
+                            t_warn!("Could not poll directory {:#?}: {}", directory, e);
+                            continue;
+                        }
+                    };
+                    let changes = DirectoryChanges {
+                        identifiers: changed_identifiers(&previous.files, &current.files),
+                        index_changed: previous.index != current.index,
+                    };
+                    previous = current;
+                    if changes.identifiers.is_empty() && !changes.index_changed {
+                        continue;
+                    }
+                    t_trace!("Directory did change: {} - {:?}", mount_point, changes);
+                    on_changes(changes);
+                }
+            })?;
+
+        Ok(Self {
+            stop,
+            thread: Some(thread),
+        })
+    }
+}
+
+impl Drop for VfsDirectoryWatcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Release);
+        if let Some(thread) = self.thread.take() {
+            thread.thread().unpark();
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Maps each file identifier in the directory and its sub directories to its current stamp.
+fn snapshot(directory: &Path) -> Result<DirectorySnapshot, std::io::Error> {
+    let stamp = |path: &Path| metadata(path).ok().map(|m| (m.modified().ok(), m.len()));
+    let mut files = HashMap::new();
+    for file in collect_directory_files(directory)? {
+        if let Some(stamp) = stamp(&file.path) {
+            files.insert(file.identifier, stamp);
+        }
+    }
+    Ok(DirectorySnapshot {
+        files,
+        index: stamp(&directory.join(DEFAULT_INDEX_FILE_NAME)),
+    })
+}
+
+fn changed_identifiers(
+    previous: &HashMap<String, FileStamp>,
+    current: &HashMap<String, FileStamp>,
+) -> Vec<String> {
+    let mut changed = current
+        .iter()
+        .filter(|(identifier, stamp)| previous.get(*identifier) != Some(stamp))
+        .map(|(identifier, _)| identifier.clone())
+        .chain(
+            previous
+                .keys()
+                .filter(|identifier| !current.contains_key(*identifier))
+                .cloned(),
+        )
+        .collect::<Vec<_>>();
+    changed.sort();
+    changed
+}
@@ -82,10 +82,11 @@ impl AssetArchive {
 
         // Hash is fine, so we decompress the header.
         let mut decompressed_header = vec![0u8; decompressed_header_size as usize];
-        decompress_to_buffer(&compressed_header, &mut decompressed_header);
+        decompress_to_buffer(&compressed_header, &mut decompressed_header)?;
 
         // Headers are always saved in cbor format.
-        let header = serde_cbor::de::from_slice::<ArchiveHeader>(&decompressed_header)?;
+        let mut header = serde_cbor::de::from_slice::<ArchiveHeader>(&decompressed_header)?;
+        header.attach_dictionary();
 
         Ok(header)
     }
@@ -124,7 +125,7 @@ impl AssetArchive {
         mut reader: impl AsyncBufReadExt + AsyncSeekExt + Unpin,
         buffer: &mut [u8],
     ) -> Result<(), AssetArchiveError> {
-        if buffer.len() as u64 > file_header.byte_count() {
+        if (buffer.len() as u64) < file_header.byte_count() {
             return Err(AssetArchiveError::BufferTooSmall);
         }
         // Set the reader to the appropriate offset.
@@ -142,8 +143,33 @@ impl AssetArchive {
                     .read_exact(&mut buffer[0..(file_header.byte_count() as usize)])
                     .await?;
             }
+            ArchiveCompressionFormat::ZSTDDictionary => {
+                let dictionary = file_header
+                    .dictionary()
+                    .ok_or(AssetArchiveError::MissingDictionary)?;
+                let mut compressed = vec![0u8; file_header.compressed_byte_count() as usize];
+                reader.read_exact(&mut compressed).await?;
+                Decompressor::with_dictionary(dictionary)?.decompress_to_buffer(
+                    &compressed,
+                    &mut buffer[0..(file_header.byte_count() as usize)],
+                )?;
+            }
         }
 
         Ok(())
     }
+
+    /// Checks the stored (possibly compressed) bytes of a file against its `compressed_hash`.
+    pub async fn verify_file(
+        file_header: &FileHeader,
+        mut reader: impl AsyncBufReadExt + AsyncSeekExt + Unpin,
+    ) -> Result<(), AssetArchiveError> {
+        let mut compressed = vec![0u8; file_header.compressed_byte_count() as usize];
+        reader.seek(SeekFrom::Start(file_header.offset())).await?;
+        reader.read_exact(&mut compressed).await?;
+        if xxh3::xxh3_64(&compressed) != file_header.compressed_hash() {
+            return Err(AssetArchiveError::InvalidFileHash);
+        }
+        Ok(())
+    }
 }
@@ -1,13 +1,15 @@
 use super::{archive::*, error::*, header::*};
 use arrayvec::ArrayString;
+use std::{borrow::Cow, collections::HashMap};
 use tokio::io::AsyncWriteExt;
-use xxhash_rust::xxh3::xxh3_64;
+use xxhash_rust::xxh3::{xxh3_128, xxh3_64};
 
 #[derive(Debug)]
 pub enum ArchiveBuildError {
     Archive(AssetArchiveError),
     IO(tokio::io::Error),
     IdentifierTooLargeError,
+    MissingDictionary,
 }
 
 impl std::error::Error for ArchiveBuildError {}
@@ -16,6 +18,7 @@ impl std::fmt::Display for ArchiveBuildError {
         match self {
             Self::IO(e) => e.fmt(f),
             Self::IdentifierTooLargeError => f.write_str("Identifier was too large!"),
+            Self::MissingDictionary => f.write_str("No dictionary was trained!"),
             ArchiveBuildError::Archive(e) => e.fmt(f),
         }
     }
@@ -32,10 +35,41 @@ impl From<tokio::io::Error> for ArchiveBuildError {
     }
 }
 
+/// Statistics of an archive build, see `ArchiveBuilder::report`.
+#[derive(Debug, Default, Clone)]
+pub struct ArchiveBuildReport {
+    /// Number of written files, including deduplicated ones.
+    pub files: usize,
+    /// Bytes stored for the contents of files, excluding the header.
+    pub stored_bytes: u64,
+    /// Files whose bytes were already stored for another file.
+    pub deduplicated: Vec<DeduplicatedFile>,
+}
+
+impl ArchiveBuildReport {
+    /// Bytes which were not stored thanks to deduplication.
+    pub fn deduplicated_bytes(&self) -> u64 {
+        self.deduplicated.iter().map(|f| f.bytes).sum()
+    }
+}
+
+/// A file which shares the stored bytes of a file written before it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeduplicatedFile {
+    pub identifier: String,
+    pub original_identifier: String,
+    pub bytes: u64,
+}
+
 pub struct ArchiveBuilder<'a, F: AsyncWriteExt + Unpin> {
     files: Vec<FileHeader>,
     offset: u64,
     writer: &'a mut F,
+    compression_level: i32,
+    dictionary: Option<Vec<u8>>,
+    /// Offset and identifier of stored bytes, identified by their hash and length.
+    stored_blobs: HashMap<(u128, u64), (u64, String)>,
+    report: ArchiveBuildReport,
 }
 
 impl<'a, F: AsyncWriteExt + Unpin> ArchiveBuilder<'a, F> {
@@ -45,9 +79,39 @@ impl<'a, F: AsyncWriteExt + Unpin> ArchiveBuilder<'a, F> {
             writer,
             files: vec![],
             offset: std::mem::size_of::<u32>() as u64,
+            compression_level: 0,
+            dictionary: None,
+            stored_blobs: HashMap::new(),
+            report: ArchiveBuildReport::default(),
         })
     }
 
+    /// Returns the number of files and bytes written so far, including the files deduplicated by content.
+    pub fn report(&self) -> &ArchiveBuildReport {
+        &self.report
+    }
+
+    /// Sets the zstd compression level, 0 selects zstd's default level.
+    pub fn set_compression_level(&mut self, level: i32) {
+        self.compression_level = level;
+    }
+
+    /// Trains a zstd dictionary of at most `max_size` bytes over `samples`, usually all files of the archive.
+    /// Files written with `ZSTDDictionary` afterwards are compressed using it.
+    pub fn train_dictionary<S: AsRef<[u8]>>(
+        &mut self,
+        samples: &[S],
+        max_size: usize,
+    ) -> Result<(), ArchiveBuildError> {
+        self.dictionary = Some(zstd::dict::from_samples(samples, max_size)?);
+        Ok(())
+    }
+
+    /// Uses a previously trained dictionary for files written with `ZSTDDictionary`.
+    pub fn set_dictionary(&mut self, dictionary: Vec<u8>) {
+        self.dictionary = Some(dictionary);
+    }
+
     /// Writes a file into the archive.
     pub async fn write_file(
         &mut self,
@@ -65,26 +129,46 @@ impl<'a, F: AsyncWriteExt + Unpin> ArchiveBuilder<'a, F> {
             ArrayString::<{ FileHeader::FILE_HEADER_NAME_LEN }>::from(identifier).unwrap();
         let id = xxh3_64(&identifier.as_bytes());
 
-        let mut compressed_size = blob.len();
-        let compressed_hash;
-        let offset = self.offset;
         // Compress the blob if necessary
-        match compression_format {
-            ArchiveCompressionFormat::None => {
-                // Write blob as is.
-                self.writer.write_all(blob).await?;
-                self.offset += blob.len() as u64;
-                compressed_hash = xxh3_64(&blob);
-            }
+        let stored = match compression_format {
+            ArchiveCompressionFormat::None => Cow::Borrowed(blob),
             ArchiveCompressionFormat::ZSTD => {
-                // Compress it first.
-                let compressed = zstd::bulk::compress(blob, 0)?;
-                compressed_size = compressed.len();
-                self.writer.write_all(&compressed).await?;
-                self.offset += compressed.len() as u64;
-                compressed_hash = xxh3_64(&compressed);
+                Cow::Owned(zstd::bulk::compress(blob, self.compression_level)?)
             }
-        }
+            ArchiveCompressionFormat::ZSTDDictionary => {
+                let dictionary = self
+                    .dictionary
+                    .as_ref()
+                    .ok_or(ArchiveBuildError::MissingDictionary)?;
+                Cow::Owned(
+                    zstd::bulk::Compressor::with_dictionary(self.compression_level, dictionary)?
+                        .compress(blob)?,
+                )
+            }
+        };
+
+        // Bytes which are already stored are shared instead of being written again.
+        let key = (xxh3_128(&stored), stored.len() as u64);
+        let offset = match self.stored_blobs.get(&key) {
+            Some((offset, original_identifier)) => {
+                self.report.deduplicated.push(DeduplicatedFile {
+                    identifier: identifier.to_string(),
+                    original_identifier: original_identifier.clone(),
+                    bytes: stored.len() as u64,
+                });
+                *offset
+            }
+            None => {
+                let offset = self.offset;
+                self.writer.write_all(&stored).await?;
+                self.offset += stored.len() as u64;
+                self.report.stored_bytes += stored.len() as u64;
+                self.stored_blobs
+                    .insert(key, (offset, identifier.to_string()));
+                offset
+            }
+        };
+        self.report.files += 1;
 
         let header = FileHeader::new(
             identifier,
@@ -93,8 +177,8 @@ impl<'a, F: AsyncWriteExt + Unpin> ArchiveBuilder<'a, F> {
             version,
             offset,
             blob.len() as u64,
-            compressed_size as u64,
-            compressed_hash,
+            stored.len() as u64,
+            xxh3_64(&stored),
             compression_format,
         );
         self.files.push(header);
@@ -105,7 +189,7 @@ impl<'a, F: AsyncWriteExt + Unpin> ArchiveBuilder<'a, F> {
     /// On succes returns the borrow writer.
     /// If it fails, the written contents should be considered undefined.
     pub async fn finish(mut self, uuid: uuid::Uuid) -> Result<&'a mut F, ArchiveBuildError> {
-        let header = ArchiveHeader::new(uuid, self.files);
+        let header = ArchiveHeader::new(uuid, self.files).with_dictionary(self.dictionary);
         AssetArchive::write_header(header, &mut self.writer).await?;
         Ok(self.writer)
     }
@@ -1,9 +1,11 @@
 #[derive(Debug)]
 pub enum AssetArchiveError {
     InvalidHeaderHash,
+    InvalidFileHash,
     HeaderDeserializationError(serde_cbor::Error),
     IO(tokio::io::Error),
     BufferTooSmall,
+    MissingDictionary,
 }
 
 impl std::error::Error for AssetArchiveError {}
@@ -11,9 +13,13 @@ impl std::fmt::Display for AssetArchiveError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             AssetArchiveError::InvalidHeaderHash => f.write_str("Invalid header hash detected."),
+            AssetArchiveError::InvalidFileHash => f.write_str("Invalid file hash detected."),
             AssetArchiveError::IO(e) => e.fmt(f),
             AssetArchiveError::HeaderDeserializationError(e) => e.fmt(f),
             AssetArchiveError::BufferTooSmall => f.write_str("The provided buffer was too small."),
+            AssetArchiveError::MissingDictionary => {
+                f.write_str("Compression dictionary is missing.")
+            }
         }
     }
 }
@@ -1,18 +1,45 @@
 use ::serde::{Deserialize, Serialize};
 use arrayvec::ArrayString;
+use std::sync::Arc;
 use uuid::*;
 
-#[derive(Serialize, Deserialize, Hash)]
+#[derive(Debug, Clone, Serialize, Deserialize, Hash)]
 pub struct ArchiveHeader {
     #[serde(rename = "uid")]
     uuid: Uuid,
     #[serde(rename = "fls")]
     files: Vec<FileHeader>,
+    /// Dictionary used by files compressed with `ZSTDDictionary`.
+    #[serde(rename = "dct", default, with = "serde_bytes")]
+    dictionary: Option<Vec<u8>>,
 }
 
 impl ArchiveHeader {
     pub const fn new(uuid: Uuid, files: Vec<FileHeader>) -> Self {
-        Self { uuid, files }
+        Self {
+            uuid,
+            files,
+            dictionary: None,
+        }
+    }
+
+    pub fn with_dictionary(mut self, dictionary: Option<Vec<u8>>) -> Self {
+        self.dictionary = dictionary;
+        self
+    }
+
+    pub fn dictionary(&self) -> Option<&[u8]> {
+        self.dictionary.as_deref()
+    }
+
+    /// Hands the dictionary to the files which require it, so they can be decompressed on their own.
+    pub(crate) fn attach_dictionary(&mut self) {
+        let dictionary = self.dictionary.clone().map(Arc::new);
+        for file in self.files.iter_mut() {
+            if file.compressed_format == ArchiveCompressionFormat::ZSTDDictionary {
+                file.dictionary = dictionary.clone();
+            }
+        }
     }
 
     pub const fn uuid(&self) -> Uuid {
@@ -25,14 +52,16 @@ impl ArchiveHeader {
 }
 
 #[repr(u8)]
-#[derive(Serialize, Deserialize, Hash)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Hash)]
 pub enum ArchiveCompressionFormat {
     None = 0,
     ZSTD = 1,
+    /// ZSTD using the dictionary of the archive, see `ArchiveBuilder::train_dictionary`.
+    ZSTDDictionary = 2,
 }
 
 #[repr(u8)]
-#[derive(Serialize, Deserialize, Hash)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Hash)]
 pub enum AssetSerializationFormat {
     None = 0,
     JSON = 1,
@@ -40,7 +69,30 @@ pub enum AssetSerializationFormat {
     TOML = 3,
 }
 
-#[derive(Serialize, Deserialize, Hash)]
+impl AssetSerializationFormat {
+    /// Maps a file extension or format string, such as `yaml` or `json`, to a serialization format.
+    /// Unknown formats are treated as raw bytes.
+    pub fn from_extension(extension: &str) -> Self {
+        match extension.to_lowercase().as_str() {
+            "json" => Self::JSON,
+            "yaml" | "yml" => Self::YAML,
+            "toml" => Self::TOML,
+            _ => Self::None,
+        }
+    }
+
+    /// Returns the file extension matching the format, or `None` for raw bytes.
+    pub fn extension(&self) -> Option<&'static str> {
+        match self {
+            Self::None => None,
+            Self::JSON => Some("json"),
+            Self::YAML => Some("yaml"),
+            Self::TOML => Some("toml"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Hash)]
 pub struct FileHeader {
     #[serde(rename = "sid")]
     identifier: ArrayString<{ FileHeader::FILE_HEADER_NAME_LEN }>,
@@ -61,6 +113,9 @@ pub struct FileHeader {
     compressed_hash: u64,
     #[serde(rename = "cf")]
     compressed_format: ArchiveCompressionFormat,
+    /// The dictionary of the archive, attached when the header is read.
+    #[serde(skip)]
+    dictionary: Option<Arc<Vec<u8>>>,
 }
 
 impl FileHeader {
@@ -99,6 +154,11 @@ impl FileHeader {
     pub fn compressed_format(&self) -> &ArchiveCompressionFormat {
         &self.compressed_format
     }
+
+    /// Returns the dictionary required to decompress the file, if it was compressed with `ZSTDDictionary`.
+    pub fn dictionary(&self) -> Option<&[u8]> {
+        self.dictionary.as_deref().map(|d| d.as_slice())
+    }
 }
 
 impl FileHeader {
@@ -123,6 +183,7 @@ impl FileHeader {
             compressed_byte_count,
             compressed_hash,
             compressed_format,
+            dictionary: None,
         }
     }
 
@@ -49,3 +49,98 @@ async fn test_builder() {
         .unwrap();
     assert_eq!(random_data, buffer);
 }
+
+#[tokio::test]
+async fn test_builder_dictionary() {
+    let mut cursor = Cursor::new(Vec::<u8>::with_capacity(1024 * 1024));
+    let mut builder = ArchiveBuilder::new(&mut cursor).await.unwrap();
+    builder.set_compression_level(19);
+
+    let files = (0..128)
+        .map(|i| {
+            format!(
+                "{{ \"name\": \"entity_{i}\", \"health\": {}, \"speed\": 1.5 }}",
+                i * 3
+            )
+        })
+        .collect::<Vec<_>>();
+    builder.train_dictionary(&files, 4096).unwrap();
+
+    for (i, file) in files.iter().enumerate() {
+        builder
+            .write_file(
+                &format!("entity_{i}.json"),
+                super::header::AssetSerializationFormat::None,
+                file.as_bytes(),
+                0,
+                super::header::ArchiveCompressionFormat::ZSTDDictionary,
+            )
+            .await
+            .unwrap();
+    }
+    builder.finish(uuid::Uuid::new_v4()).await.unwrap();
+
+    cursor.rewind().unwrap();
+    AssetArchive::read_magic_value(&mut cursor).await.unwrap();
+    let header = AssetArchive::read_header(&mut cursor).await.unwrap();
+    assert!(header.dictionary().is_some());
+    for (file_header, file) in header.files().iter().zip(files.iter()) {
+        let mut buffer = vec![0; file_header.byte_count() as usize];
+        AssetArchive::read_file_into_buffer(file_header, &mut cursor, &mut buffer)
+            .await
+            .unwrap();
+        assert_eq!(file.as_bytes(), buffer);
+    }
+}
+
+#[tokio::test]
+async fn test_builder_deduplication() {
+    let mut cursor = Cursor::new(Vec::<u8>::with_capacity(1024 * 1024));
+    let mut builder = ArchiveBuilder::new(&mut cursor).await.unwrap();
+
+    let shared = (0..4096).map(|_| rand::random()).collect::<Vec<u8>>();
+    let unique = (0..4096).map(|_| rand::random()).collect::<Vec<u8>>();
+    for (identifier, blob) in [("a.bin", &shared), ("b.bin", &unique), ("c.bin", &shared)] {
+        builder
+            .write_file(
+                identifier,
+                super::header::AssetSerializationFormat::None,
+                blob,
+                0,
+                super::header::ArchiveCompressionFormat::ZSTD,
+            )
+            .await
+            .unwrap();
+    }
+    let report = builder.report().clone();
+    assert_eq!(report.files, 3);
+    assert_eq!(report.deduplicated.len(), 1);
+    assert_eq!(report.deduplicated[0].identifier, "c.bin");
+    assert_eq!(report.deduplicated[0].original_identifier, "a.bin");
+    builder.finish(uuid::Uuid::new_v4()).await.unwrap();
+
+    cursor.rewind().unwrap();
+    AssetArchive::read_magic_value(&mut cursor).await.unwrap();
+    let header = AssetArchive::read_header(&mut cursor).await.unwrap();
+    let files = header.files();
+    assert_eq!(files[0].offset(), files[2].offset());
+    assert_ne!(files[0].offset(), files[1].offset());
+    assert_eq!(
+        report.stored_bytes,
+        files[0].compressed_byte_count() + files[1].compressed_byte_count()
+    );
+    assert_eq!(
+        report.deduplicated_bytes(),
+        files[2].compressed_byte_count()
+    );
+    for (file_header, blob) in files.iter().zip([&shared, &unique, &shared]) {
+        AssetArchive::verify_file(file_header, &mut cursor)
+            .await
+            .unwrap();
+        let mut buffer = vec![0; file_header.byte_count() as usize];
+        AssetArchive::read_file_into_buffer(file_header, &mut cursor, &mut buffer)
+            .await
+            .unwrap();
+        assert_eq!(blob, &buffer);
+    }
+}
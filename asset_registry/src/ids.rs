@@ -1,20 +1,33 @@
 use ::serde::{Deserialize, Serialize};
 use uuid::*;
 
-#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct ArchiveId(Uuid);
 
 impl ArchiveId {
     pub fn new(uuid: Uuid) -> Self {
         ArchiveId(uuid)
     }
+
+    pub fn uuid(&self) -> Uuid {
+        self.0
+    }
 }
 
-#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct AssetId(u64);
 
 impl AssetId {
     pub fn new(id: impl AsRef<str>) -> Self {
         Self(xxhash_rust::xxh3::xxh3_64(id.as_ref().as_bytes()))
     }
+
+    /// Constructs an id from an already hashed identifier, such as [`crate::FileHeader::id`].
+    pub const fn from_raw(id: u64) -> Self {
+        Self(id)
+    }
+
+    pub const fn raw(&self) -> u64 {
+        self.0
+    }
 }
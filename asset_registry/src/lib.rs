@@ -7,3 +7,6 @@ pub use archive::*;
 pub use ids::*;
 pub use loader::*;
 pub use registry::*;
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,215 @@
+use std::{
+    error::Error,
+    path::Path,
+    sync::{Arc, RwLock},
+};
+
+use serde::de::DeserializeOwned;
+use tokio::io::BufReader;
+use utils::dispatcher::Dispatcher;
+
+use crate::*;
+
+#[derive(Debug)]
+pub enum AssetLoadError {
+    Archive(AssetArchiveError),
+    IO(tokio::io::Error),
+    InvalidMagicValue,
+    ArchiveAlreadyLoaded,
+    ArchiveNotLoaded,
+    AssetNotFound,
+    UnsupportedFormat,
+    Deserialization(Box<dyn Error + Send + Sync>),
+    PoisonError,
+}
+
+impl std::error::Error for AssetLoadError {}
+impl std::fmt::Display for AssetLoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Archive(e) => e.fmt(f),
+            Self::IO(e) => e.fmt(f),
+            Self::InvalidMagicValue => f.write_str("File is not an asset archive."),
+            Self::ArchiveAlreadyLoaded => f.write_str("Archive was already loaded."),
+            Self::ArchiveNotLoaded => f.write_str("Archive was not loaded."),
+            Self::AssetNotFound => f.write_str("Asset not found."),
+            Self::UnsupportedFormat => f.write_str("Asset format can not be deserialized."),
+            Self::Deserialization(e) => e.fmt(f),
+            Self::PoisonError => f.write_str("Poisoning occured! A thread has paniced."),
+        }
+    }
+}
+
+impl From<AssetArchiveError> for AssetLoadError {
+    fn from(e: AssetArchiveError) -> Self {
+        Self::Archive(e)
+    }
+}
+impl From<tokio::io::Error> for AssetLoadError {
+    fn from(e: tokio::io::Error) -> Self {
+        Self::IO(e)
+    }
+}
+
+/// Loads assets from the archives registered in an [`AssetRegistry`].
+/// Completion is reported either through a callback, which runs on the dispatcher's async runtime,
+/// or by awaiting the `_async` variants.
+#[derive(Clone)]
+pub struct AssetLoader {
+    registry: Arc<RwLock<AssetRegistryState>>,
+    dispatcher: Arc<Dispatcher>,
+}
+
+impl AssetLoader {
+    pub(crate) fn new(
+        registry: Arc<RwLock<AssetRegistryState>>,
+        dispatcher: Arc<Dispatcher>,
+    ) -> Self {
+        Self {
+            registry,
+            dispatcher,
+        }
+    }
+
+    /// Reads the header of the archive at `path` and registers all of its assets.
+    pub async fn load_archive_from_path(
+        &self,
+        path: impl AsRef<Path>,
+    ) -> Result<ArchiveId, AssetLoadError> {
+        let file = tokio::fs::File::open(path.as_ref()).await?;
+        let mut reader = BufReader::new(file);
+        if !AssetArchive::read_magic_value(&mut reader).await? {
+            return Err(AssetLoadError::InvalidMagicValue);
+        }
+        let header = AssetArchive::read_header(&mut reader).await?;
+        let archive_id = ArchiveId::new(header.uuid());
+
+        let mut registry = self
+            .registry
+            .write()
+            .map_err(|_| AssetLoadError::PoisonError)?;
+        if !registry.insert_archive(path.as_ref().into(), header) {
+            return Err(AssetLoadError::ArchiveAlreadyLoaded);
+        }
+        Ok(archive_id)
+    }
+
+    /// Unregisters an archive. Assets it provided are served by the remaining archives, if possible.
+    pub fn unload_archive(&self, archive_id: ArchiveId) -> Result<(), AssetLoadError> {
+        let mut registry = self
+            .registry
+            .write()
+            .map_err(|_| AssetLoadError::PoisonError)?;
+        match registry.remove_archive(archive_id) {
+            Some(_) => Ok(()),
+            None => Err(AssetLoadError::ArchiveNotLoaded),
+        }
+    }
+
+    /// Loads the decompressed bytes of an asset and passes them to `on_complete`.
+    pub fn load_blob(
+        &self,
+        id: AssetId,
+        on_complete: impl FnOnce(Result<Vec<u8>, AssetLoadError>) + Send + 'static,
+    ) {
+        let loader = self.clone();
+        self.dispatcher.spawn_async(async move {
+            on_complete(loader.load_blob_async(id).await);
+        });
+    }
+
+    /// Loads the decompressed bytes of an asset.
+    pub async fn load_blob_async(&self, id: AssetId) -> Result<Vec<u8>, AssetLoadError> {
+        let mut buffer = Vec::new();
+        self.read_blob_into(id, &mut buffer).await?;
+        Ok(buffer)
+    }
+
+    /// Deserializes an asset into `T` and passes it to `on_complete`.
+    /// The deserializer is selected using the asset's [`AssetSerializationFormat`].
+    pub fn load_as_type<T: DeserializeOwned + Send + 'static>(
+        &self,
+        id: AssetId,
+        on_complete: impl FnOnce(Result<T, AssetLoadError>) + Send + 'static,
+    ) {
+        let loader = self.clone();
+        self.dispatcher.spawn_async(async move {
+            on_complete(loader.load_as_type_async(id).await);
+        });
+    }
+
+    /// Deserializes an asset into `T`, allocates an internal byte buffer temporarily.
+    pub async fn load_as_type_async<T: DeserializeOwned>(
+        &self,
+        id: AssetId,
+    ) -> Result<T, AssetLoadError> {
+        let mut buffer = Vec::new();
+        self.load_as_type_into_buffer_async(id, &mut buffer).await
+    }
+
+    /// Deserializes an asset into `T` using `buffer` as intermediate.
+    /// The buffer is handed back to `on_complete` so it can be reused.
+    pub fn load_as_type_into_buffer<T: DeserializeOwned + Send + 'static>(
+        &self,
+        id: AssetId,
+        mut buffer: Vec<u8>,
+        on_complete: impl FnOnce(Result<T, AssetLoadError>, Vec<u8>) + Send + 'static,
+    ) {
+        let loader = self.clone();
+        self.dispatcher.spawn_async(async move {
+            let result = loader.load_as_type_into_buffer_async(id, &mut buffer).await;
+            on_complete(result, buffer);
+        });
+    }
+
+    /// Deserializes an asset into `T` using `buffer` as intermediate.
+    pub async fn load_as_type_into_buffer_async<T: DeserializeOwned>(
+        &self,
+        id: AssetId,
+        buffer: &mut Vec<u8>,
+    ) -> Result<T, AssetLoadError> {
+        let format = self.read_blob_into(id, buffer).await?;
+        deserialize(format, buffer)
+    }
+
+    /// Reads and decompresses an asset into the buffer, the buffer is resized to the asset's size.
+    async fn read_blob_into(
+        &self,
+        id: AssetId,
+        buffer: &mut Vec<u8>,
+    ) -> Result<AssetSerializationFormat, AssetLoadError> {
+        let (path, file_header) = {
+            let registry = self
+                .registry
+                .read()
+                .map_err(|_| AssetLoadError::PoisonError)?;
+            let (archive, file_header) =
+                registry.resolve(id).ok_or(AssetLoadError::AssetNotFound)?;
+            (archive.path().to_path_buf(), file_header.clone())
+        };
+
+        let file = tokio::fs::File::open(path).await?;
+        let mut reader = BufReader::new(file);
+        buffer.resize(file_header.byte_count() as usize, 0);
+        AssetArchive::read_file_into_buffer(&file_header, &mut reader, buffer).await?;
+        Ok(*file_header.format())
+    }
+}
+
+fn deserialize<T: DeserializeOwned>(
+    format: AssetSerializationFormat,
+    bytes: &[u8],
+) -> Result<T, AssetLoadError> {
+    match format {
+        AssetSerializationFormat::JSON => {
+            serde_json::from_slice(bytes).map_err(|e| AssetLoadError::Deserialization(Box::from(e)))
+        }
+        AssetSerializationFormat::YAML => {
+            serde_yaml::from_slice(bytes).map_err(|e| AssetLoadError::Deserialization(Box::from(e)))
+        }
+        AssetSerializationFormat::TOML => {
+            toml::from_slice(bytes).map_err(|e| AssetLoadError::Deserialization(Box::from(e)))
+        }
+        AssetSerializationFormat::None => Err(AssetLoadError::UnsupportedFormat),
+    }
+}
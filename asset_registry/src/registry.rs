@@ -1,58 +1,128 @@
 use std::{
     collections::HashMap,
     path::{Path, PathBuf},
-    sync::{atomic::AtomicBool, Arc, RwLock},
+    sync::{Arc, RwLock},
 };
 
-use serde::{de::DeserializeOwned, Deserialize};
-use uuid::Uuid;
+use utils::dispatcher::Dispatcher;
 
 use crate::*;
 
-pub enum AssetLoadError {}
-
-struct LoadedArchive {
-    archive_header: ArchiveHeader,
+pub(crate) struct LoadedArchive {
+    header: ArchiveHeader,
     path: PathBuf,
 }
+
+impl LoadedArchive {
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
 struct AssetDescriptor {
     version: u16,
-    archive: Uuid,
-    is_loading: AtomicBool,
+    archive: ArchiveId,
+    /// Index of the asset's file header inside the archive header.
+    file_index: usize,
 }
 
-struct AssetRegistryState {
-    loaded_archives: HashMap<Uuid, LoadedArchive, ahash::RandomState>,
-    assets: HashMap<u64, AssetDescriptor, ahash::RandomState>,
+#[derive(Default)]
+pub(crate) struct AssetRegistryState {
+    loaded_archives: HashMap<ArchiveId, LoadedArchive, ahash::RandomState>,
+    assets: HashMap<AssetId, AssetDescriptor, ahash::RandomState>,
 }
 
-pub struct AssetRegistry {
-    state: Arc<AssetRegistryState>,
-}
+impl AssetRegistryState {
+    /// Registers an archive and all of its assets.
+    /// Assets already present with an equal or higher version are left untouched.
+    /// Returns false if an archive with the same id was already registered.
+    pub(crate) fn insert_archive(&mut self, path: PathBuf, header: ArchiveHeader) -> bool {
+        let archive_id = ArchiveId::new(header.uuid());
+        if self.loaded_archives.contains_key(&archive_id) {
+            return false;
+        }
+        Self::register_assets(&mut self.assets, archive_id, &header);
+        self.loaded_archives
+            .insert(archive_id, LoadedArchive { header, path });
+        true
+    }
 
-pub struct AssetLoader {
-    registry: Arc<RwLock<AssetRegistryState>>,
-}
+    /// Removes an archive from the registry.
+    /// Assets that were provided by the archive are resolved again using the remaining archives.
+    pub(crate) fn remove_archive(&mut self, archive_id: ArchiveId) -> Option<LoadedArchive> {
+        let archive = self.loaded_archives.remove(&archive_id)?;
+        self.assets.retain(|_, d| d.archive != archive_id);
+        for (id, loaded) in self.loaded_archives.iter() {
+            Self::register_assets(&mut self.assets, *id, &loaded.header);
+        }
+        Some(archive)
+    }
 
-impl AssetLoader {
-    pub fn load_archive_from_path(&mut self, path: &impl AsRef<Path>) {}
+    /// Returns the archive and the file header which provide the requested asset.
+    pub(crate) fn resolve(&self, id: AssetId) -> Option<(&LoadedArchive, &FileHeader)> {
+        let descriptor = self.assets.get(&id)?;
+        let archive = self.loaded_archives.get(&descriptor.archive)?;
+        let file = archive.header.files().get(descriptor.file_index)?;
+        Some((archive, file))
+    }
 
-    pub fn unload_archive(&mut self, archive_id: ArchiveId) {}
+    pub(crate) fn has_asset(&self, id: AssetId) -> bool {
+        self.assets.contains_key(&id)
+    }
 
-    pub fn load_blob(
-        &self,
-        id: AssetId,
-        on_complete: impl FnOnce(Result<Vec<u8>, AssetLoadError>) + Send,
-    ) {
+    pub(crate) fn archive_ids(&self) -> Vec<ArchiveId> {
+        self.loaded_archives.keys().copied().collect()
     }
 
-    pub fn load_blob_async(&self, id: AssetId) {}
+    fn register_assets(
+        assets: &mut HashMap<AssetId, AssetDescriptor, ahash::RandomState>,
+        archive: ArchiveId,
+        header: &ArchiveHeader,
+    ) {
+        for (file_index, file) in header.files().iter().enumerate() {
+            let descriptor = AssetDescriptor {
+                version: file.version(),
+                archive,
+                file_index,
+            };
+            match assets.get_mut(&AssetId::from_raw(file.id())) {
+                Some(existing) => {
+                    if existing.version < descriptor.version {
+                        *existing = descriptor;
+                    }
+                }
+                None => {
+                    assets.insert(AssetId::from_raw(file.id()), descriptor);
+                }
+            }
+        }
+    }
+}
 
-    pub fn load_as_type<T: DeserializeOwned>(&self, id: AssetId) {}
+/// Keeps track of all loaded archives and the assets they provide.
+/// If multiple archives provide the same asset, the one with the highest version is used.
+#[derive(Default, Clone)]
+pub struct AssetRegistry {
+    state: Arc<RwLock<AssetRegistryState>>,
+}
 
-    pub fn load_as_type_async<T: DeserializeOwned>(&self, id: AssetId) {}
+impl AssetRegistry {
+    /// Creates a loader which shares this registry's state.
+    pub fn loader(&self, dispatcher: Arc<Dispatcher>) -> AssetLoader {
+        AssetLoader::new(Arc::clone(&self.state), dispatcher)
+    }
 
-    pub fn load_as_type_into_buffer(&self) {}
+    pub fn has_asset(&self, id: AssetId) -> bool {
+        match self.state.read() {
+            Ok(state) => state.has_asset(id),
+            Err(_) => false,
+        }
+    }
 
-    pub fn load_as_type_into_buffer_async(&self) {}
+    pub fn loaded_archives(&self) -> Vec<ArchiveId> {
+        match self.state.read() {
+            Ok(state) => state.archive_ids(),
+            Err(_) => vec![],
+        }
+    }
 }
@@ -0,0 +1,152 @@
+use crate::*;
+use serde::Deserialize;
+use std::{io::Cursor, num::NonZeroUsize, path::PathBuf, sync::mpsc::channel, sync::Arc};
+use utils::dispatcher::Dispatcher;
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct TestConfig {
+    name: String,
+    value: u32,
+}
+
+fn write_archive(
+    path: &PathBuf,
+    uuid: uuid::Uuid,
+    files: &[(&str, AssetSerializationFormat, &[u8], u16)],
+) {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    let bytes = runtime.block_on(async {
+        let mut cursor = Cursor::new(Vec::<u8>::new());
+        let mut builder = ArchiveBuilder::new(&mut cursor).await.unwrap();
+        for (identifier, format, blob, version) in files {
+            builder
+                .write_file(
+                    identifier,
+                    *format,
+                    blob,
+                    *version,
+                    ArchiveCompressionFormat::ZSTD,
+                )
+                .await
+                .unwrap();
+        }
+        builder.finish(uuid).await.unwrap();
+        cursor.into_inner()
+    });
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, bytes).unwrap();
+}
+
+fn temp_path(name: &str) -> PathBuf {
+    let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    d.push("../tmp/");
+    d.push(name);
+    d
+}
+
+fn dispatcher() -> Arc<Dispatcher> {
+    let one = NonZeroUsize::new(1).unwrap();
+    Arc::new(Dispatcher::new(Some(one), one, Some(one), one).unwrap())
+}
+
+#[test]
+fn test_loader() {
+    let base = temp_path("loader_base.archive");
+    let patch = temp_path("loader_patch.archive");
+    let random_data = (0..4096).map(|_| rand::random()).collect::<Vec<u8>>();
+    write_archive(
+        &base,
+        uuid::Uuid::new_v4(),
+        &[
+            ("blob", AssetSerializationFormat::None, &random_data, 0),
+            (
+                "config",
+                AssetSerializationFormat::YAML,
+                b"name: base\nvalue: 1\n",
+                0,
+            ),
+        ],
+    );
+    let patch_uuid = uuid::Uuid::new_v4();
+    write_archive(
+        &patch,
+        patch_uuid,
+        &[(
+            "config",
+            AssetSerializationFormat::JSON,
+            br#"{ "name": "patch", "value": 2 }"#,
+            1,
+        )],
+    );
+
+    let registry = AssetRegistry::default();
+    let loader = registry.loader(dispatcher());
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    runtime.block_on(async {
+        loader.load_archive_from_path(&base).await.unwrap();
+        let patch_id = loader.load_archive_from_path(&patch).await.unwrap();
+        assert_eq!(patch_id, ArchiveId::new(patch_uuid));
+        assert!(matches!(
+            loader.load_archive_from_path(&patch).await,
+            Err(AssetLoadError::ArchiveAlreadyLoaded)
+        ));
+
+        let blob = loader.load_blob_async(AssetId::new("blob")).await.unwrap();
+        assert_eq!(blob, random_data);
+
+        // The higher version from the patch archive wins.
+        let config: TestConfig = loader
+            .load_as_type_async(AssetId::new("config"))
+            .await
+            .unwrap();
+        assert_eq!(config.name, "patch");
+
+        loader.unload_archive(patch_id).unwrap();
+        let config: TestConfig = loader
+            .load_as_type_async(AssetId::new("config"))
+            .await
+            .unwrap();
+        assert_eq!(
+            config,
+            TestConfig {
+                name: "base".into(),
+                value: 1
+            }
+        );
+
+        assert!(matches!(
+            loader.load_blob_async(AssetId::new("missing")).await,
+            Err(AssetLoadError::AssetNotFound)
+        ));
+        assert!(matches!(
+            loader
+                .load_as_type_async::<TestConfig>(AssetId::new("blob"))
+                .await,
+            Err(AssetLoadError::UnsupportedFormat)
+        ));
+    });
+
+    // Callback based completion runs on the dispatcher.
+    let (sender, receiver) = channel();
+    loader.load_as_type_into_buffer::<TestConfig>(
+        AssetId::new("config"),
+        Vec::new(),
+        move |result, buffer| {
+            sender.send((result.unwrap(), buffer.len())).unwrap();
+        },
+    );
+    let (config, buffer_len) = receiver.recv().unwrap();
+    assert_eq!(config.value, 1);
+    assert_eq!(buffer_len, b"name: base\nvalue: 1\n".len());
+
+    let (sender, receiver) = channel();
+    loader.load_blob(AssetId::new("blob"), move |result| {
+        sender.send(result.unwrap()).unwrap();
+    });
+    assert_eq!(receiver.recv().unwrap(), random_data);
+}
@@ -15,6 +15,8 @@ pub struct EngineConcurrencySettings {
 /// Information required to construct an instance of [`Engine`].
 pub struct EngineCreateInfo {
     pub asset_system: Option<Box<AssetSystemCreateFn>>,
+    /// Byte budget of the asset cache, `AssetCache::DEFAULT_BUDGET` is used if not provided.
+    pub asset_cache_budget: Option<usize>,
     pub application_info: ApplicationInfo,
     pub update_tick_rate: u32,
     pub max_skipped_frames: u32,
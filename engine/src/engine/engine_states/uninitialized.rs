@@ -43,6 +43,28 @@ impl EngineStateMachine<Uninitialized> {
             None => Default::default(),
         };
         resources.add_resource(asset_system);
+        if let Some(asset_system) = resources.get_resource::<AssetSystem>() {
+            let budget = info
+                .asset_cache_budget
+                .unwrap_or(AssetCache::DEFAULT_BUDGET);
+            resources.add_resource(AssetCache::new(Arc::clone(&asset_system), budget));
+            // Changed assets are loaded again the next time they are requested from the cache.
+            if let Some(cache) = resources.get_resource::<AssetCache>() {
+                let weak_cache = Arc::downgrade(&cache);
+                asset_system.add_change_listener(move |message| {
+                    if let Some(cache) = weak_cache.upgrade() {
+                        cache.invalidate(&message.mount, &message.identifier);
+                    }
+                });
+                // Any cached asset might have been served by a variant which is no longer active.
+                let weak_cache = Arc::downgrade(&cache);
+                asset_system.add_variants_listener(move |_| {
+                    if let Some(cache) = weak_cache.upgrade() {
+                        cache.clear();
+                    }
+                });
+            }
+        }
         resources.add_resource(dispatch_system);
         resources.add_resource(SceneManager::default());
 
@@ -144,7 +166,21 @@ impl<P: PlatformInterface + PlatformInitalizationHandler> Into<EngineStateMachin
             })
             .collect::<Vec<_>>();
 
-        uninit.shared.resources.add_resource(builder.build());
+        let message_bus = builder.build();
+        // Forward asset changes and variant changes of the asset system to the interested stages.
+        if let (Some(asset_system), Some(sender)) = (
+            uninit.shared.resources.get_resource::<AssetSystem>(),
+            message_bus.get_sender::<AssetDidChange>(),
+        ) {
+            asset_system.add_change_listener(move |message| sender.send(message.clone()));
+        }
+        if let (Some(asset_system), Some(sender)) = (
+            uninit.shared.resources.get_resource::<AssetSystem>(),
+            message_bus.get_sender::<AssetVariantsDidChange>(),
+        ) {
+            asset_system.add_variants_listener(move |message| sender.send(message.clone()));
+        }
+        uninit.shared.resources.add_resource(message_bus);
         let mut scene_manager = SceneManager::default();
 
         // Run the platform pre did init function.
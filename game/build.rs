@@ -1,14 +1,59 @@
-use asset_library::archive_directory;
+use asset_library::import::{import_directory, AssetImporterRegistry};
+use asset_library::{archive_directory_with_options, ArchiveDirectoryOptions};
 use std::path::*;
 
 fn main() {
     let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
-    archive_directory(
-        path.clone().join("assets"),
+    let out = PathBuf::from(std::env::var("OUT_DIR").unwrap());
+    #[cfg(feature = "compile_shaders")]
+    compile_shaders(&path, &out);
+
+    let imported = out.join("assets");
+    // Meshes are imported from their sources, the archive only changes with the assets.
+    println!("cargo:rerun-if-changed=assets");
+    import_directory(
+        path.join("assets"),
+        &imported,
+        &AssetImporterRegistry::default(),
+        Some(&out.join("import_cache")),
+    )
+    .unwrap();
+    // The archive is checked in, a content derived UUID keeps it unchanged unless the assets change.
+    archive_directory_with_options(
+        &imported,
         "assets",
-        path.clone().join("asset_archives").join("assets.harchive"),
-        0,
-        asset_library::archive::AssetArchiveCompressionFormat::ZSTD,
+        path.join("asset_archives").join("assets.harchive"),
+        &ArchiveDirectoryOptions {
+            compression_format: asset_library::archive::AssetArchiveCompressionFormat::ZSTD,
+            content_uuid: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+}
+
+/// Compiles `shader_source` into the checked-in SPIR-V files of `assets/shaders`.
+/// Without the `compile_shaders` feature the checked-in files are archived as they are.
+#[cfg(feature = "compile_shaders")]
+fn compile_shaders(path: &Path, out: &Path) {
+    println!("cargo:rerun-if-changed=shader_source");
+    let compiled = out.join("shaders");
+    import_directory(
+        path.join("shader_source"),
+        &compiled,
+        &AssetImporterRegistry::default(),
+        Some(&out.join("import_cache")),
     )
     .unwrap();
+    for file in asset_library::collect_directory_files(&compiled).unwrap() {
+        let spirv = std::fs::read(&file.path).unwrap();
+        let target = path
+            .join("assets")
+            .join("shaders")
+            .join(file.path.file_name().unwrap());
+        // Rewriting unchanged files would rerun the build script on every build.
+        if std::fs::read(&target).ok() != Some(spirv.clone()) {
+            std::fs::write(target, spirv).unwrap();
+        }
+    }
 }
@@ -1,3 +1,4 @@
+use asset_library::archive::NoKeys;
 use engine::engine_stages::RenderStageContainer;
 use engine::{engine_stages::*, *};
 use graphics::*;
@@ -8,8 +9,6 @@ use std::{sync::Arc, vec};
 use utils::*;
 use winit_platform::WinitPlatform;
 
-mod mesh_writing;
-
 fn create_wasm_scripting_stage<'r>(
     input: UpdateStageConstructorInput<'r>,
 ) -> Box<dyn AnyUpdateStage> {
@@ -19,12 +18,14 @@ fn create_wasm_scripting_stage<'r>(
             fatal!("This system requires an asset system to be present!");
         }
     };
-    let mut buffer = vec![];
-    let _ = asset_system
-        .load_asset_as_blob_into("assets.wasm", "init_test", &mut buffer)
-        .unwrap();
     let mut stage = WasmScriptingStage::default();
-    stage.add_engine_init_script(&buffer);
+    for script in asset_system.glob("assets", "wasm/*.wasm").unwrap() {
+        let mut buffer = vec![];
+        asset_system
+            .load_asset_as_blob_into(script.mount(), script.identifier(), &mut buffer)
+            .unwrap();
+        stage.add_engine_init_script(&buffer);
+    }
     stage.into()
 }
 
@@ -90,11 +91,11 @@ fn create_graphics_stage<'r>(input: RenderStageConstructorInput<'r>) -> Box<dyn
     };
 
     let options = asset_system
-        .load_asset_as_type::<GraphicsOptions, _, _>("assets.config", "vulkan")
+        .load_asset_as_type::<GraphicsOptions, _, _>("assets", "config/vulkan")
         .unwrap();
 
     let application_info = asset_system
-        .load_asset_as_type::<ApplicationInfo, _, _>("assets.config", "game")
+        .load_asset_as_type::<ApplicationInfo, _, _>("assets", "config/game")
         .unwrap();
 
     let create_info = GraphicsStageCreateInfo {
@@ -111,13 +112,12 @@ fn create_graphics_stage<'r>(input: RenderStageConstructorInput<'r>) -> Box<dyn
 fn main() {
     setup_default_logger();
 
-    mesh_writing::write_meshes();
     let asset_system = AssetSystem::default();
     asset_system
-        .load_archives_from_directory("./game/asset_archives/", "harchive")
+        .load_archives_from_directory("./game/asset_archives/", "harchive", &NoKeys)
         .unwrap();
     let application_info = asset_system
-        .load_asset_as_type::<ApplicationInfo, _, _>("assets.config", "game")
+        .load_asset_as_type::<ApplicationInfo, _, _>("assets", "config/game")
         .unwrap();
 
     let create_info = EngineCreateInfo {
@@ -132,10 +132,11 @@ fn main() {
         asset_system: Some(Box::new(|| {
             let asset_system = AssetSystem::default();
             asset_system
-                .load_archives_from_directory("./game/asset_archives/", "harchive")
+                .load_archives_from_directory("./game/asset_archives/", "harchive", &NoKeys)
                 .unwrap();
             asset_system
         })),
+        asset_cache_budget: None,
         application_info,
         concurrency_settings: EngineConcurrencySettings {
             max_async_threads: None,
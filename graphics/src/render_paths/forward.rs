@@ -175,10 +175,10 @@ impl ForwardRenderPath {
         let mut frag_blob = vec![];
 
         asset_system
-            .load_asset_as_blob_into("assets.shaders", "triangle_vert", &mut vert_blob)
+            .load_asset_as_blob_into("assets", "shaders/triangle_vert", &mut vert_blob)
             .unwrap();
         asset_system
-            .load_asset_as_blob_into("assets.shaders", "triangle_frag", &mut frag_blob)
+            .load_asset_as_blob_into("assets", "shaders/triangle_frag", &mut frag_blob)
             .unwrap();
 
         let vert_blob = ash::util::read_spv(&mut std::io::Cursor::new(&vert_blob[..])).unwrap();
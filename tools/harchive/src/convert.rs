@@ -0,0 +1,394 @@
+//! Converts `.harchive` archives of asset_library into asset_registry archives.
+//!
+//! Every file of a mount point is written with the identifier `mount_point/identifier` and the
+//! serialization format matching its extension. Formats unknown to asset_registry can only be stored as
+//! raw bytes, which loses their extension. asset_registry archives can neither store dependencies nor hide
+//! removed files, so all of these are rejected unless `ConvertOptions::allow_lossy` is set.
+//! Files keep the version of their mount point.
+
+use asset_library::{archive as v1, AssetReference};
+use asset_registry::{
+    ArchiveBuildError, ArchiveBuildReport, ArchiveBuilder, ArchiveCompressionFormat, AssetArchive,
+    AssetArchiveError, AssetSerializationFormat, FileHeader,
+};
+use std::{collections::HashMap, error::Error, fmt::Display, path::Path};
+use tokio::{
+    fs::File,
+    io::{AsyncBufReadExt, AsyncSeekExt, AsyncWriteExt, BufReader, BufWriter},
+};
+
+#[derive(Debug)]
+pub enum ConvertError {
+    /// The source archive could not be read.
+    Source(v1::AssetArchiveError),
+    Build(ArchiveBuildError),
+    /// The converted archive could not be read.
+    Archive(AssetArchiveError),
+    IO(std::io::Error),
+    /// asset_registry archives store 16 bit versions.
+    VersionTooLarge {
+        mount_point: String,
+        version: u64,
+    },
+    /// Files whose format is unknown to asset_registry and `ConvertOptions::allow_lossy` is not set.
+    LossyFormat(Vec<String>),
+    /// Files removed by a patch mount point and `ConvertOptions::allow_lossy` is not set.
+    RemovedFiles(Vec<String>),
+    /// Files with dependencies and `ConvertOptions::allow_lossy` is not set.
+    DroppedDependencies(Vec<String>),
+    /// Files of several mount points or versions which are converted into the same identifier.
+    DuplicateIdentifier(String),
+    /// The identifier is longer than `FileHeader::FILE_HEADER_NAME_LEN`.
+    IdentifierTooLong(String),
+    /// The converted archive is not an asset_registry archive.
+    InvalidMagicValue,
+    /// The converted archive contains a different number of files than converted.
+    FileCountMismatch {
+        expected: usize,
+        found: usize,
+    },
+    /// A file of the source archive is missing in the converted archive.
+    MissingFile(String),
+    /// A file of the converted archive differs from the source archive.
+    RoundTripMismatch(String),
+}
+
+impl Display for ConvertError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Source(e) => write!(f, "{}", e),
+            Self::Build(e) => write!(f, "{}", e),
+            Self::Archive(e) => write!(f, "{}", e),
+            Self::IO(e) => write!(f, "{}", e),
+            Self::VersionTooLarge {
+                mount_point,
+                version,
+            } => write!(
+                f,
+                "Version {} of mount point {} does not fit into 16 bits.",
+                version, mount_point
+            ),
+            Self::LossyFormat(files) => write!(
+                f,
+                "The formats of {} are unknown to asset_registry and would be lost.",
+                files.join(", ")
+            ),
+            Self::RemovedFiles(files) => write!(
+                f,
+                "asset_registry archives can not remove {}.",
+                files.join(", ")
+            ),
+            Self::DroppedDependencies(files) => write!(
+                f,
+                "The dependencies of {} can not be stored in asset_registry archives.",
+                files.join(", ")
+            ),
+            Self::DuplicateIdentifier(identifier) => {
+                write!(f, "Several files are converted into {}.", identifier)
+            }
+            Self::IdentifierTooLong(identifier) => write!(
+                f,
+                "Identifier {} is longer than {} bytes.",
+                identifier,
+                FileHeader::FILE_HEADER_NAME_LEN
+            ),
+            Self::InvalidMagicValue => {
+                write!(f, "Converted file is not an asset_registry archive.")
+            }
+            Self::FileCountMismatch { expected, found } => write!(
+                f,
+                "Converted archive contains {} files, expected {}.",
+                found, expected
+            ),
+            Self::MissingFile(identifier) => {
+                write!(f, "{} is missing in the converted archive.", identifier)
+            }
+            Self::RoundTripMismatch(identifier) => {
+                write!(f, "{} differs from the source archive.", identifier)
+            }
+        }
+    }
+}
+impl Error for ConvertError {}
+
+impl From<v1::AssetArchiveError> for ConvertError {
+    fn from(e: v1::AssetArchiveError) -> Self {
+        Self::Source(e)
+    }
+}
+impl From<ArchiveBuildError> for ConvertError {
+    fn from(e: ArchiveBuildError) -> Self {
+        Self::Build(e)
+    }
+}
+impl From<AssetArchiveError> for ConvertError {
+    fn from(e: AssetArchiveError) -> Self {
+        Self::Archive(e)
+    }
+}
+impl From<std::io::Error> for ConvertError {
+    fn from(e: std::io::Error) -> Self {
+        Self::IO(e)
+    }
+}
+
+/// Options of `convert_archive`.
+#[derive(Debug, Clone)]
+pub struct ConvertOptions {
+    pub compression_format: ArchiveCompressionFormat,
+    /// zstd compression level, 0 selects zstd's default level.
+    pub compression_level: i32,
+    /// Maximum size of the dictionary trained if `compression_format` is `ZSTDDictionary`.
+    pub dictionary_size: usize,
+    /// Uuid of the converted archive, `None` generates a random one.
+    pub uuid: Option<uuid::Uuid>,
+    /// Stores files of formats unknown to asset_registry as raw bytes, drops removed files and dependencies
+    /// instead of failing.
+    pub allow_lossy: bool,
+}
+
+impl Default for ConvertOptions {
+    fn default() -> Self {
+        Self {
+            compression_format: ArchiveCompressionFormat::ZSTD,
+            compression_level: 0,
+            dictionary_size: 112 * 1024,
+            uuid: None,
+            allow_lossy: false,
+        }
+    }
+}
+
+/// A file written into the converted archive.
+#[derive(Debug, Clone)]
+pub struct ConvertedFile {
+    pub source_mount_point: String,
+    pub source_identifier: String,
+    pub source_format: String,
+    pub source_version: u64,
+    pub identifier: String,
+    pub format: AssetSerializationFormat,
+    /// Dependencies of the source file, asset_registry archives do not store dependencies.
+    pub dropped_dependencies: Vec<AssetReference>,
+}
+
+impl ConvertedFile {
+    /// True if the source format is unknown to asset_registry, the file is stored as raw bytes.
+    pub fn loses_format(&self) -> bool {
+        self.format == AssetSerializationFormat::None && !self.source_format.is_empty()
+    }
+}
+
+/// Summary of a conversion, see `convert_archive`.
+#[derive(Debug, Clone)]
+pub struct ConversionReport {
+    /// Converted files, sorted by identifier.
+    pub files: Vec<ConvertedFile>,
+    /// Files removed by a patch mount point. asset_registry archives can not express removals.
+    pub removed: Vec<String>,
+    /// The compression format used, `ZSTD` if no dictionary could be trained.
+    pub compression_format: ArchiveCompressionFormat,
+    pub build: ArchiveBuildReport,
+}
+
+/// Identifier of a file in the converted archive.
+pub fn converted_identifier(mount_point: &str, identifier: &str) -> String {
+    match mount_point.is_empty() {
+        true => identifier.to_string(),
+        false => format!("{}/{}", mount_point, identifier),
+    }
+}
+
+/// Converts an archive, see the module documentation for how files are mapped.
+/// Encrypted files are decrypted, `source` needs to be opened with its keys.
+/// The written archive is not verified, use `verify_conversion` or `convert_archive_file`.
+pub async fn convert_archive<W: AsyncWriteExt + Unpin>(
+    source: &v1::AssetArchive,
+    writer: &mut W,
+    options: &ConvertOptions,
+) -> Result<ConversionReport, ConvertError> {
+    let mut report = ConversionReport {
+        files: Vec::new(),
+        removed: Vec::new(),
+        compression_format: options.compression_format,
+        build: Default::default(),
+    };
+    let selected = select_files(source, &mut report)?;
+    let lossy = selected
+        .iter()
+        .filter(|(file, _)| file.loses_format())
+        .map(|(file, _)| format!("{} ({})", file.identifier, file.source_format))
+        .collect::<Vec<_>>();
+    let dependencies = selected
+        .iter()
+        .filter(|(file, _)| !file.dropped_dependencies.is_empty())
+        .map(|(file, _)| file.identifier.clone())
+        .collect::<Vec<_>>();
+    if !options.allow_lossy {
+        if !lossy.is_empty() {
+            return Err(ConvertError::LossyFormat(lossy));
+        }
+        if !report.removed.is_empty() {
+            return Err(ConvertError::RemovedFiles(report.removed));
+        }
+        if !dependencies.is_empty() {
+            return Err(ConvertError::DroppedDependencies(dependencies));
+        }
+    }
+
+    let mut builder = ArchiveBuilder::new(writer).await?;
+    builder.set_compression_level(options.compression_level);
+    if options.compression_format == ArchiveCompressionFormat::ZSTDDictionary {
+        let samples = selected
+            .iter()
+            .map(|(_, header)| source.read_file_from(header))
+            .collect::<Result<Vec<_>, _>>()?;
+        // Too few or too small files can not train a dictionary.
+        if builder
+            .train_dictionary(&samples, options.dictionary_size)
+            .is_err()
+        {
+            report.compression_format = ArchiveCompressionFormat::ZSTD;
+        }
+    }
+
+    for (file, header) in selected {
+        let blob = source.read_file_from(header)?;
+        let version = file.source_version as u16;
+        builder
+            .write_file(
+                &file.identifier,
+                file.format,
+                &blob,
+                version,
+                report.compression_format,
+            )
+            .await?;
+        report.files.push(file);
+    }
+
+    report.build = builder.report().clone();
+    let uuid = options.uuid.unwrap_or_else(uuid::Uuid::new_v4);
+    builder.finish(uuid).await?.flush().await?;
+    Ok(report)
+}
+
+/// Checks that the converted archive contains exactly the converted files of `report`,
+/// with their formats and versions, and that every file has the same contents as in `source`.
+pub async fn verify_conversion(
+    source: &v1::AssetArchive,
+    mut converted: impl AsyncBufReadExt + AsyncSeekExt + Unpin,
+    report: &ConversionReport,
+) -> Result<(), ConvertError> {
+    if !AssetArchive::read_magic_value(&mut converted).await? {
+        return Err(ConvertError::InvalidMagicValue);
+    }
+    let header = AssetArchive::read_header(&mut converted).await?;
+    let converted_files = header
+        .files()
+        .iter()
+        .map(|f| (f.identifier().to_string(), f))
+        .collect::<HashMap<_, _>>();
+    let source_files = source
+        .header()
+        .mount_points()
+        .iter()
+        .flat_map(|m| {
+            m.assets()
+                .iter()
+                .map(move |a| ((m.mount_point(), *m.version(), a.asset_identifier()), a))
+        })
+        .collect::<HashMap<_, _>>();
+
+    if converted_files.len() != report.files.len() {
+        return Err(ConvertError::FileCountMismatch {
+            expected: report.files.len(),
+            found: converted_files.len(),
+        });
+    }
+    let mut buffer = Vec::new();
+    for file in report.files.iter() {
+        let missing = || ConvertError::MissingFile(file.identifier.clone());
+        let converted_header = converted_files.get(&file.identifier).ok_or_else(missing)?;
+        let source_header = source_files
+            .get(&(
+                file.source_mount_point.as_str(),
+                file.source_version,
+                file.source_identifier.as_str(),
+            ))
+            .ok_or_else(missing)?;
+        let expected = source.read_file_from(source_header)?;
+        buffer.resize(converted_header.byte_count() as usize, 0);
+        AssetArchive::read_file_into_buffer(converted_header, &mut converted, &mut buffer).await?;
+        if *converted_header.format() != file.format
+            || converted_header.version() as u64 != file.source_version
+            || buffer != expected
+        {
+            return Err(ConvertError::RoundTripMismatch(file.identifier.clone()));
+        }
+    }
+    Ok(())
+}
+
+/// Converts an archive into the file at `output` and verifies the written archive.
+pub async fn convert_archive_file(
+    source: &v1::AssetArchive,
+    output: impl AsRef<Path>,
+    options: &ConvertOptions,
+) -> Result<ConversionReport, ConvertError> {
+    let mut writer = BufWriter::new(File::create(output.as_ref()).await?);
+    let report = convert_archive(source, &mut writer, options).await?;
+    drop(writer);
+    let reader = BufReader::new(File::open(output.as_ref()).await?);
+    verify_conversion(source, reader, &report).await?;
+    Ok(report)
+}
+
+/// Selects the files to convert, sorted by their converted identifier.
+fn select_files<'a>(
+    source: &'a v1::AssetArchive,
+    report: &mut ConversionReport,
+) -> Result<Vec<(ConvertedFile, &'a v1::AssetArchiveFileHeader)>, ConvertError> {
+    let mut selected = Vec::new();
+    for mount_point in source.header().mount_points() {
+        if u16::try_from(*mount_point.version()).is_err() {
+            return Err(ConvertError::VersionTooLarge {
+                mount_point: mount_point.mount_point().to_string(),
+                version: *mount_point.version(),
+            });
+        }
+        for removed in mount_point.removed_assets() {
+            report
+                .removed
+                .push(converted_identifier(mount_point.mount_point(), removed));
+        }
+        for asset in mount_point.assets() {
+            let identifier =
+                converted_identifier(mount_point.mount_point(), asset.asset_identifier());
+            if identifier.len() > FileHeader::FILE_HEADER_NAME_LEN {
+                return Err(ConvertError::IdentifierTooLong(identifier));
+            }
+            let file = ConvertedFile {
+                source_mount_point: mount_point.mount_point().to_string(),
+                source_identifier: asset.asset_identifier().to_string(),
+                source_format: asset.asset_format().to_string(),
+                source_version: *mount_point.version(),
+                format: AssetSerializationFormat::from_extension(asset.asset_format()),
+                dropped_dependencies: asset.dependencies().to_vec(),
+                identifier,
+            };
+            selected.push((file, asset));
+        }
+    }
+    selected.sort_by(|(a, _), (b, _)| a.identifier.cmp(&b.identifier));
+    // Converted files are looked up by identifier, e.g. by `verify_conversion`.
+    if let Some(pair) = selected
+        .windows(2)
+        .find(|pair| pair[0].0.identifier == pair[1].0.identifier)
+    {
+        return Err(ConvertError::DuplicateIdentifier(
+            pair[0].0.identifier.clone(),
+        ));
+    }
+    Ok(selected)
+}
@@ -0,0 +1,53 @@
+//! Maps the identifiers of archived files onto paths below the output directory of the `extract` commands.
+
+use std::{
+    error::Error,
+    fmt::Display,
+    path::{Component, Path, PathBuf},
+};
+
+/// The identifier of an archived file can not be extracted without leaving the output directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidExtractPath(pub String);
+
+impl Display for InvalidExtractPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} can not be extracted, it is not a relative path.",
+            self.0
+        )
+    }
+}
+impl Error for InvalidExtractPath {}
+
+/// Returns the path of an extracted file, `output/identifier.format`.
+/// Identifiers are read from the archive, which might be crafted: every `/` separated component
+/// has to be a plain file name, empty, `.`, `..`, root and prefix components are rejected,
+/// the same way `VfsPhysicalMountPoint` validates identifiers.
+/// The format is appended, identifiers which contain a `.` keep their full name.
+pub fn extract_path(
+    output: &Path,
+    identifier: &str,
+    format: &str,
+) -> Result<PathBuf, InvalidExtractPath> {
+    let invalid = || InvalidExtractPath(identifier.to_string());
+    let components = identifier.split('/').collect::<Vec<_>>();
+    let valid_component = |c: &&str| {
+        !c.contains([':', '\\'])
+            && matches!(
+                Path::new(c).components().collect::<Vec<_>>().as_slice(),
+                [Component::Normal(name)] if name == c
+            )
+    };
+    if !components.iter().all(valid_component) || format.contains(['/', '\\', ':']) {
+        return Err(invalid());
+    }
+    let mut path = output.to_path_buf();
+    path.extend(&components);
+    if !format.is_empty() {
+        let file_name = format!("{}.{}", components[components.len() - 1], format);
+        path.set_file_name(file_name);
+    }
+    Ok(path)
+}
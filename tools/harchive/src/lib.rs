@@ -0,0 +1,6 @@
+//! Conversion between the archive formats, used by the `convert` command.
+pub mod convert;
+pub mod extract;
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,176 @@
+use clap::*;
+use std::error::Error;
+use std::path::PathBuf;
+
+mod v1;
+mod v2;
+
+/// Inspects and builds `.harchive` (asset_library) and asset_registry archives.
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Lists mount points and files with their formats, sizes and compression ratio.
+    List { archive: PathBuf },
+    /// Writes a single file, or all files, of an archive into a directory.
+    /// Files of `.harchive` archives are written into a directory per mount point, `output/mount_point/identifier.format`.
+    Extract {
+        archive: PathBuf,
+        #[clap(short, long, default_value = "./out")]
+        output: PathBuf,
+        /// Only extract files from this mount point. (Not used for asset_registry archives)
+        #[clap(short, long)]
+        mount_point: Option<String>,
+        /// Only extract the file with this identifier.
+        #[clap(short, long)]
+        identifier: Option<String>,
+    },
+    /// Packs a directory and its sub directories into a single mount point.
+    Pack {
+        directory: PathBuf,
+        output: PathBuf,
+        #[clap(short, long, default_value = "assets")]
+        mount_point: String,
+        #[clap(short, long, default_value = "0")]
+        version: u64,
+        /// One of none, lz4, lz4-chunked, zstd or zstd-dict. asset_registry archives do not support lz4.
+        /// zstd-dict trains a dictionary over all packed files, lz4-chunked files can be streamed with seeking.
+        #[clap(short, long, default_value = "zstd")]
+        compression: String,
+        /// zstd compression level, 0 selects zstd's default level.
+        #[clap(short, long, default_value = "0")]
+        level: i32,
+        /// Writes an asset_registry archive instead of a `.harchive`.
+        #[clap(long)]
+        registry: bool,
+        /// Derives the archive's uuid from its contents, packing the same files twice produces identical archives.
+        #[clap(long)]
+        reproducible: bool,
+    },
+    /// Prints the archive's header uuid and versions.
+    Info { archive: PathBuf },
+    /// Checks the checksums of the header and all files.
+    Verify { archive: PathBuf },
+    /// Converts a `.harchive` into an asset_registry archive and checks that every file round-trips.
+    /// Files are identified as `mount_point/identifier` and keep the version of their mount point.
+    Convert {
+        archive: PathBuf,
+        output: PathBuf,
+        /// One of none, zstd or zstd-dict.
+        #[clap(short, long, default_value = "zstd")]
+        compression: String,
+        /// zstd compression level, 0 selects zstd's default level.
+        #[clap(short, long, default_value = "0")]
+        level: i32,
+        /// Uses the uuid of the source archive instead of a random one.
+        #[clap(long)]
+        keep_uuid: bool,
+        /// Stores files of formats unknown to asset_registry, e.g. spv, as raw bytes without their extension,
+        /// and drops removed files and dependencies, which asset_registry archives can not store.
+        #[clap(long)]
+        allow_lossy: bool,
+    },
+}
+
+fn main() {
+    let args = Args::parse();
+    let result = match args.command {
+        Command::Pack {
+            directory,
+            output,
+            mount_point,
+            version,
+            compression,
+            level,
+            registry,
+            reproducible,
+        } => {
+            let options = PackOptions {
+                version,
+                compression,
+                level,
+                reproducible,
+            };
+            match registry {
+                true => v2::pack(directory, output, mount_point, &options),
+                false => v1::pack(directory, output, mount_point, &options),
+            }
+        }
+        Command::List { archive } => match v2::is_registry_archive(&archive) {
+            Ok(true) => v2::list(archive),
+            Ok(false) => v1::list(archive),
+            Err(e) => Err(e),
+        },
+        Command::Extract {
+            archive,
+            output,
+            mount_point,
+            identifier,
+        } => match v2::is_registry_archive(&archive) {
+            Ok(true) => v2::extract(archive, output, identifier),
+            Ok(false) => v1::extract(archive, output, mount_point, identifier),
+            Err(e) => Err(e),
+        },
+        Command::Info { archive } => match v2::is_registry_archive(&archive) {
+            Ok(true) => v2::info(archive),
+            Ok(false) => v1::info(archive),
+            Err(e) => Err(e),
+        },
+        Command::Verify { archive } => match v2::is_registry_archive(&archive) {
+            Ok(true) => v2::verify(archive),
+            Ok(false) => v1::verify(archive),
+            Err(e) => Err(e),
+        },
+        Command::Convert {
+            archive,
+            output,
+            compression,
+            level,
+            keep_uuid,
+            allow_lossy,
+        } => v2::convert(archive, output, &compression, level, keep_uuid, allow_lossy),
+    };
+
+    if let Err(e) = result {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+/// Options of the `pack` command shared by both archive formats.
+pub struct PackOptions {
+    pub version: u64,
+    pub compression: String,
+    pub level: i32,
+    pub reproducible: bool,
+}
+
+/// Compressed size relative to the uncompressed size.
+fn compression_ratio(compressed: u64, uncompressed: u64) -> f64 {
+    match uncompressed {
+        0 => 1.0,
+        _ => compressed as f64 / uncompressed as f64,
+    }
+}
+
+/// Prints the summary of a `pack` command.
+fn print_report(
+    files: usize,
+    stored_bytes: u64,
+    deduplicated_files: usize,
+    deduplicated_bytes: u64,
+) {
+    println!(
+        "Packed {} files into {} bytes, {} duplicate files saved {} bytes.",
+        files, stored_bytes, deduplicated_files, deduplicated_bytes
+    );
+}
+
+fn invalid_argument(message: String) -> Box<dyn Error> {
+    Box::from(message)
+}
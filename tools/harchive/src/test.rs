@@ -0,0 +1,229 @@
+use crate::{convert::*, extract::*};
+use asset_library::{archive::*, AssetReference};
+use asset_registry::{ArchiveCompressionFormat, AssetSerializationFormat};
+use std::{fs::File, path::PathBuf};
+use tokio::io::BufReader;
+
+fn build_source(path: &PathBuf) -> AssetArchive {
+    AssetArchiveBuilder::new(File::create(path).unwrap())
+        .unwrap()
+        .add_mount_point("config", 2)
+        .unwrap()
+        .write_file(
+            "window",
+            "yaml",
+            b"width: 1920\n",
+            AssetArchiveCompressionFormat::ZSTD,
+        )
+        .unwrap()
+        .write_file("title", "txt", b"game", AssetArchiveCompressionFormat::LZ4)
+        .unwrap()
+        .remove_file("old")
+        .finish()
+        .add_mount_point("shaders", 1)
+        .unwrap()
+        .write_file_with_dependencies(
+            "triangle_vert",
+            "spv",
+            &[AssetReference::new("config", "window")],
+            &[3, 2, 0x23, 0x07],
+            AssetArchiveCompressionFormat::ZSTD,
+        )
+        .unwrap()
+        .finish()
+        .finish()
+        .unwrap();
+    AssetArchive::read_from_file(path).unwrap()
+}
+
+#[tokio::test]
+async fn test_convert_archive() {
+    let d = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../tmp/convert");
+    std::fs::create_dir_all(&d).unwrap();
+    let source = build_source(&d.join("source.harchive"));
+
+    let output = d.join("converted.archive");
+    let uuid = *source.header().uuid();
+    let mut options = ConvertOptions {
+        compression_format: ArchiveCompressionFormat::ZSTD,
+        uuid: Some(uuid),
+        ..Default::default()
+    };
+    // txt and spv are unknown to asset_registry, their extensions would be lost.
+    match convert_archive_file(&source, &output, &options).await {
+        Err(ConvertError::LossyFormat(files)) => assert_eq!(
+            files,
+            vec!["config/title (txt)", "shaders/triangle_vert (spv)"]
+        ),
+        r => panic!("unexpected result {:?}", r.map(|_| ())),
+    }
+    options.allow_lossy = true;
+    let report = convert_archive_file(&source, &output, &options)
+        .await
+        .unwrap();
+
+    let identifiers = report
+        .files
+        .iter()
+        .map(|f| f.identifier.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        identifiers,
+        vec!["config/title", "config/window", "shaders/triangle_vert"]
+    );
+    assert_eq!(report.files[1].source_version, 2);
+    assert_eq!(report.files[1].format, AssetSerializationFormat::YAML);
+    assert!(report.files[2].loses_format());
+    assert_eq!(report.files[2].dropped_dependencies.len(), 1);
+    assert_eq!(report.removed, vec!["config/old"]);
+
+    let mut reader = BufReader::new(tokio::fs::File::open(&output).await.unwrap());
+    let header = asset_registry::AssetArchive::read_header(&mut reader)
+        .await
+        .unwrap();
+    assert_eq!(header.uuid(), uuid);
+    let window = &header.files()[1];
+    assert_eq!(window.version(), 2);
+    let mut buffer = vec![0; window.byte_count() as usize];
+    asset_registry::AssetArchive::read_file_into_buffer(window, &mut reader, &mut buffer)
+        .await
+        .unwrap();
+    assert_eq!(buffer, b"width: 1920\n");
+
+    // A converted archive which does not match the source is rejected.
+    let mut report = report;
+    report.files[1].source_identifier = String::from("title");
+    let reader = BufReader::new(tokio::fs::File::open(&output).await.unwrap());
+    assert!(matches!(
+        verify_conversion(&source, reader, &report).await,
+        Err(ConvertError::RoundTripMismatch(_))
+    ));
+
+    // Versions which do not fit into 16 bits can not be converted.
+    let path = d.join("large_version.harchive");
+    AssetArchiveBuilder::new(File::create(&path).unwrap())
+        .unwrap()
+        .add_mount_point("config", u64::from(u16::MAX) + 1)
+        .unwrap()
+        .finish()
+        .finish()
+        .unwrap();
+    let source = AssetArchive::read_from_file(&path).unwrap();
+    assert!(matches!(
+        convert_archive_file(&source, d.join("large_version.archive"), &options).await,
+        Err(ConvertError::VersionTooLarge { .. })
+    ));
+
+    // Removed files and dependencies are lost unless allowed, even if every format is known.
+    options.allow_lossy = false;
+    let path = d.join("patch.harchive");
+    AssetArchiveBuilder::new(File::create(&path).unwrap())
+        .unwrap()
+        .add_mount_point("config", 3)
+        .unwrap()
+        .write_file_with_dependencies(
+            "window",
+            "yaml",
+            &[AssetReference::new("config", "graphics")],
+            b"width: 1920\n",
+            AssetArchiveCompressionFormat::ZSTD,
+        )
+        .unwrap()
+        .remove_file("old")
+        .finish()
+        .finish()
+        .unwrap();
+    let source = AssetArchive::read_from_file(&path).unwrap();
+    match convert_archive_file(&source, d.join("patch.archive"), &options).await {
+        Err(ConvertError::RemovedFiles(files)) => assert_eq!(files, vec!["config/old"]),
+        r => panic!("unexpected result {:?}", r.map(|_| ())),
+    }
+    let path = d.join("dependencies.harchive");
+    AssetArchiveBuilder::new(File::create(&path).unwrap())
+        .unwrap()
+        .add_mount_point("config", 3)
+        .unwrap()
+        .write_file_with_dependencies(
+            "window",
+            "yaml",
+            &[AssetReference::new("config", "graphics")],
+            b"width: 1920\n",
+            AssetArchiveCompressionFormat::ZSTD,
+        )
+        .unwrap()
+        .finish()
+        .finish()
+        .unwrap();
+    let source = AssetArchive::read_from_file(&path).unwrap();
+    match convert_archive_file(&source, d.join("dependencies.archive"), &options).await {
+        Err(ConvertError::DroppedDependencies(files)) => {
+            assert_eq!(files, vec!["config/window"])
+        }
+        r => panic!("unexpected result {:?}", r.map(|_| ())),
+    }
+
+    // Files of different mount points which map to the same identifier are rejected.
+    let path = d.join("duplicate.harchive");
+    AssetArchiveBuilder::new(File::create(&path).unwrap())
+        .unwrap()
+        .add_mount_point("config", 1)
+        .unwrap()
+        .write_file(
+            "graphics/window",
+            "yaml",
+            b"a",
+            AssetArchiveCompressionFormat::None,
+        )
+        .unwrap()
+        .finish()
+        .add_mount_point("config/graphics", 2)
+        .unwrap()
+        .write_file("window", "yaml", b"b", AssetArchiveCompressionFormat::None)
+        .unwrap()
+        .finish()
+        .finish()
+        .unwrap();
+    let source = AssetArchive::read_from_file(&path).unwrap();
+    match convert_archive_file(&source, d.join("duplicate.archive"), &options).await {
+        Err(ConvertError::DuplicateIdentifier(identifier)) => {
+            assert_eq!(identifier, "config/graphics/window")
+        }
+        r => panic!("unexpected result {:?}", r.map(|_| ())),
+    }
+}
+
+#[test]
+fn test_extract_path() {
+    let output = PathBuf::from("out");
+    assert_eq!(
+        extract_path(&output, "config/window", "yaml").unwrap(),
+        output.join("config").join("window.yaml")
+    );
+    // Formats are appended, identifiers containing a `.` are kept.
+    assert_eq!(
+        extract_path(&output, "shaders/triangle.vert", "spv").unwrap(),
+        output.join("shaders").join("triangle.vert.spv")
+    );
+    assert_eq!(
+        extract_path(&output, "readme", "").unwrap(),
+        output.join("readme")
+    );
+    for identifier in [
+        "../escape",
+        "config/../../escape",
+        "/etc/passwd",
+        "config//window",
+        "./window",
+        "config/",
+        "",
+        "c:/windows",
+        "config\\..\\escape",
+    ] {
+        assert!(
+            extract_path(&output, identifier, "yaml").is_err(),
+            "{}",
+            identifier
+        );
+    }
+    assert!(extract_path(&output, "window", "/yaml").is_err());
+}
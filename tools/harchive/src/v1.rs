@@ -0,0 +1,167 @@
+use crate::*;
+use asset_library::archive::*;
+use harchive::{
+    convert::converted_identifier,
+    extract::{extract_path, InvalidExtractPath},
+};
+use std::path::Path;
+
+fn compression_format(compression: &str) -> Result<AssetArchiveCompressionFormat, Box<dyn Error>> {
+    match compression.to_lowercase().as_str() {
+        "none" => Ok(AssetArchiveCompressionFormat::None),
+        "lz4" => Ok(AssetArchiveCompressionFormat::LZ4),
+        "zstd" => Ok(AssetArchiveCompressionFormat::ZSTD),
+        "zstd-dict" => Ok(AssetArchiveCompressionFormat::ZSTDDictionary),
+        "lz4-chunked" => Ok(AssetArchiveCompressionFormat::LZ4Chunked),
+        _ => Err(invalid_argument(format!(
+            "Unknown compression format: {}",
+            compression
+        ))),
+    }
+}
+
+pub fn pack(
+    directory: PathBuf,
+    output: PathBuf,
+    mount_point: String,
+    options: &PackOptions,
+) -> Result<(), Box<dyn Error>> {
+    let report = asset_library::archive_directory_with_options(
+        directory,
+        mount_point,
+        output,
+        &asset_library::ArchiveDirectoryOptions {
+            version: options.version,
+            compression_format: compression_format(&options.compression)?,
+            compression_level: options.level,
+            content_uuid: options.reproducible,
+            ..Default::default()
+        },
+    )?;
+    for file in report.deduplicated.iter() {
+        println!(
+            "{}/{} shares {} bytes with {}/{}",
+            file.mount_point,
+            file.identifier,
+            file.bytes,
+            file.original_mount_point,
+            file.original_identifier
+        );
+    }
+    print_report(
+        report.files,
+        report.stored_bytes,
+        report.deduplicated.len(),
+        report.deduplicated_bytes(),
+    );
+    Ok(())
+}
+
+pub fn list(archive: PathBuf) -> Result<(), Box<dyn Error>> {
+    let archive = AssetArchive::read_from_file(archive)?;
+    for mount_point in archive.header().mount_points() {
+        println!(
+            "{} (version {})",
+            mount_point.mount_point(),
+            mount_point.version()
+        );
+        for asset in mount_point.assets() {
+            println!(
+                "    {:<40} {:<8} {:>12} {:>12} {:>7.3} {:?}",
+                asset.asset_identifier(),
+                asset.asset_format(),
+                asset.compressed_size(),
+                asset.uncompressed_size(),
+                compression_ratio(*asset.compressed_size(), *asset.uncompressed_size()),
+                asset.compression_format()
+            );
+        }
+        for removed in mount_point.removed_assets() {
+            println!("    {:<40} removed", removed);
+        }
+    }
+    Ok(())
+}
+
+pub fn extract(
+    archive: PathBuf,
+    output: PathBuf,
+    mount_point: Option<String>,
+    identifier: Option<String>,
+) -> Result<(), Box<dyn Error>> {
+    let archive = AssetArchive::read_from_file(archive)?;
+    let mut counter = 0;
+    for mount in archive.header().mount_points() {
+        if let Some(mount_point) = &mount_point {
+            if mount.mount_point() != mount_point.to_lowercase() {
+                continue;
+            }
+        }
+        for asset in mount.assets() {
+            if let Some(identifier) = &identifier {
+                if asset.asset_identifier() != identifier.to_lowercase() {
+                    continue;
+                }
+            }
+            let path = output_path(&output, mount.mount_point(), asset)?;
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(&path, archive.read_file_from(asset)?)?;
+            println!("{}", path.display());
+            counter += 1;
+        }
+    }
+    if counter == 0 {
+        return Err(invalid_argument(String::from("No matching files found.")));
+    }
+    Ok(())
+}
+
+/// Every mount point is extracted into its own directory `output/mount_point`, identifiers are paths relative to it.
+/// `pack output/mount_point --mount-point mount_point` packs the files back into the same mount point.
+fn output_path(
+    output: &Path,
+    mount_point: &str,
+    asset: &AssetArchiveFileHeader,
+) -> Result<PathBuf, InvalidExtractPath> {
+    extract_path(
+        output,
+        &converted_identifier(mount_point, asset.asset_identifier()),
+        asset.asset_format(),
+    )
+}
+
+pub fn info(archive: PathBuf) -> Result<(), Box<dyn Error>> {
+    let archive = AssetArchive::read_from_file(archive)?;
+    println!("format: harchive");
+    println!("uuid: {}", archive.header().uuid());
+    for mount_point in archive.header().mount_points() {
+        println!(
+            "mount point: {} version: {} files: {}",
+            mount_point.mount_point(),
+            mount_point.version(),
+            mount_point.assets().len()
+        );
+    }
+    Ok(())
+}
+
+pub fn verify(archive: PathBuf) -> Result<(), Box<dyn Error>> {
+    let archive = AssetArchive::read_from_file(archive)?;
+    match archive.verify() {
+        Ok(()) => {
+            println!("Archive is valid.");
+            Ok(())
+        }
+        Err(corrupt_files) => {
+            for file in corrupt_files.iter() {
+                println!("{}/{}: {}", file.mount_point, file.identifier, file.error);
+            }
+            Err(invalid_argument(format!(
+                "{} corrupt files found.",
+                corrupt_files.len()
+            )))
+        }
+    }
+}
@@ -0,0 +1,267 @@
+use crate::*;
+use asset_registry::*;
+use harchive::{convert::*, extract::extract_path};
+use std::path::Path;
+use tokio::{
+    fs::File,
+    io::{AsyncWriteExt, BufReader, BufWriter},
+    runtime::Runtime,
+};
+use xxhash_rust::xxh3::Xxh3;
+
+fn runtime() -> Result<Runtime, Box<dyn Error>> {
+    Ok(tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?)
+}
+
+async fn open(archive: &Path) -> Result<(BufReader<File>, ArchiveHeader), Box<dyn Error>> {
+    let mut reader = BufReader::new(File::open(archive).await?);
+    if !AssetArchive::read_magic_value(&mut reader).await? {
+        return Err(invalid_argument(String::from(
+            "File is not an asset_registry archive.",
+        )));
+    }
+    let header = AssetArchive::read_header(&mut reader).await?;
+    Ok((reader, header))
+}
+
+/// Returns true if the archive starts with the asset_registry magic value.
+pub fn is_registry_archive(archive: &Path) -> Result<bool, Box<dyn Error>> {
+    runtime()?.block_on(async {
+        let mut reader = BufReader::new(File::open(archive).await?);
+        // Files shorter than the magic value can not be registry archives.
+        Ok(AssetArchive::read_magic_value(&mut reader)
+            .await
+            .unwrap_or(false))
+    })
+}
+
+fn compression_format(compression: &str) -> Result<ArchiveCompressionFormat, Box<dyn Error>> {
+    match compression.to_lowercase().as_str() {
+        "none" => Ok(ArchiveCompressionFormat::None),
+        "zstd" => Ok(ArchiveCompressionFormat::ZSTD),
+        "zstd-dict" => Ok(ArchiveCompressionFormat::ZSTDDictionary),
+        _ => Err(invalid_argument(format!(
+            "Unsupported compression format: {}",
+            compression
+        ))),
+    }
+}
+
+pub fn pack(
+    directory: PathBuf,
+    output: PathBuf,
+    mount_point: String,
+    options: &PackOptions,
+) -> Result<(), Box<dyn Error>> {
+    let mut compression_format = compression_format(&options.compression)?;
+    let version = u16::try_from(options.version)
+        .map_err(|_| invalid_argument(String::from("Version must fit into 16 bits.")))?;
+
+    let files = asset_library::collect_directory_files(directory)?;
+
+    runtime()?.block_on(async {
+        let mut writer = BufWriter::new(File::create(output).await?);
+        let mut builder = ArchiveBuilder::new(&mut writer).await?;
+        builder.set_compression_level(options.level);
+        let mut blobs = Vec::with_capacity(files.len());
+        for file in files.iter() {
+            blobs.push(tokio::fs::read(&file.path).await?);
+        }
+        if compression_format == ArchiveCompressionFormat::ZSTDDictionary {
+            // Too few or too small files can not train a dictionary.
+            if let Err(e) = builder.train_dictionary(&blobs, 112 * 1024) {
+                println!("Could not train a dictionary, using zstd: {}", e);
+                compression_format = ArchiveCompressionFormat::ZSTD;
+            }
+        }
+        for (file, blob) in files.iter().zip(blobs.iter()) {
+            builder
+                .write_file(
+                    &format!("{}/{}", mount_point.to_lowercase(), file.identifier),
+                    AssetSerializationFormat::from_extension(&file.format),
+                    blob,
+                    version,
+                    compression_format,
+                )
+                .await?;
+        }
+        let uuid = match options.reproducible {
+            true => content_uuid(&mount_point, &files, &blobs, options),
+            false => uuid::Uuid::new_v4(),
+        };
+        let report = builder.report().clone();
+        builder.finish(uuid).await?.flush().await?;
+        for file in report.deduplicated.iter() {
+            println!(
+                "{} shares {} bytes with {}",
+                file.identifier, file.bytes, file.original_identifier
+            );
+        }
+        print_report(
+            report.files,
+            report.stored_bytes,
+            report.deduplicated.len(),
+            report.deduplicated_bytes(),
+        );
+        Ok(())
+    })
+}
+
+/// Converts a `.harchive` into an asset_registry archive, see `harchive::convert`.
+pub fn convert(
+    archive: PathBuf,
+    output: PathBuf,
+    compression: &str,
+    level: i32,
+    keep_uuid: bool,
+    allow_lossy: bool,
+) -> Result<(), Box<dyn Error>> {
+    let source = asset_library::archive::AssetArchive::read_from_file(archive)?;
+    let options = ConvertOptions {
+        compression_format: compression_format(compression)?,
+        compression_level: level,
+        uuid: keep_uuid.then(|| *source.header().uuid()),
+        allow_lossy,
+        ..Default::default()
+    };
+    let report = runtime()?.block_on(convert_archive_file(&source, output, &options))?;
+    for file in report.files.iter() {
+        if file.loses_format() {
+            println!(
+                "{}: format {} is stored as raw bytes",
+                file.identifier, file.source_format
+            );
+        }
+        if !file.dropped_dependencies.is_empty() {
+            println!(
+                "{}: {} dependencies are not stored",
+                file.identifier,
+                file.dropped_dependencies.len()
+            );
+        }
+    }
+    for identifier in report.removed.iter() {
+        println!("{}: removal can not be stored, skipped", identifier);
+    }
+    print_report(
+        report.build.files,
+        report.build.stored_bytes,
+        report.build.deduplicated.len(),
+        report.build.deduplicated_bytes(),
+    );
+    println!("Every file round-trips.");
+    Ok(())
+}
+
+/// Derives a uuid from everything which ends up in the archive.
+fn content_uuid(
+    mount_point: &str,
+    files: &[asset_library::DirectoryFile],
+    blobs: &[Vec<u8>],
+    options: &PackOptions,
+) -> uuid::Uuid {
+    let mut hasher = Xxh3::new();
+    hasher.update(mount_point.to_lowercase().as_bytes());
+    hasher.update(&options.version.to_le_bytes());
+    hasher.update(options.compression.to_lowercase().as_bytes());
+    hasher.update(&options.level.to_le_bytes());
+    for (file, blob) in files.iter().zip(blobs) {
+        hasher.update(file.identifier.as_bytes());
+        hasher.update(file.format.as_bytes());
+        hasher.update(&(blob.len() as u64).to_le_bytes());
+        hasher.update(blob);
+    }
+    uuid::Uuid::from_u128(hasher.digest128())
+}
+
+pub fn list(archive: PathBuf) -> Result<(), Box<dyn Error>> {
+    runtime()?.block_on(async {
+        let (_, header) = open(&archive).await?;
+        for file in header.files() {
+            println!(
+                "{:<56} {:<6} v{:<5} {:>12} {:>12} {:>7.3} {:?}",
+                file.identifier().as_str(),
+                file.format().extension().unwrap_or("-"),
+                file.version(),
+                file.compressed_byte_count(),
+                file.byte_count(),
+                compression_ratio(file.compressed_byte_count(), file.byte_count()),
+                file.compressed_format()
+            );
+        }
+        Ok(())
+    })
+}
+
+pub fn extract(
+    archive: PathBuf,
+    output: PathBuf,
+    identifier: Option<String>,
+) -> Result<(), Box<dyn Error>> {
+    runtime()?.block_on(async {
+        let (mut reader, header) = open(&archive).await?;
+        let mut counter = 0;
+        for file in header.files() {
+            if let Some(identifier) = &identifier {
+                if file.identifier().as_str() != identifier {
+                    continue;
+                }
+            }
+            let mut buffer = vec![0u8; file.byte_count() as usize];
+            AssetArchive::read_file_into_buffer(file, &mut reader, &mut buffer).await?;
+            let extension = file.format().extension().unwrap_or_default();
+            let path = extract_path(&output, file.identifier().as_str(), extension)?;
+            if let Some(parent) = path.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+            tokio::fs::write(&path, &buffer).await?;
+            println!("{}", path.display());
+            counter += 1;
+        }
+        if counter == 0 {
+            return Err(invalid_argument(String::from("No matching files found.")));
+        }
+        Ok(())
+    })
+}
+
+pub fn info(archive: PathBuf) -> Result<(), Box<dyn Error>> {
+    runtime()?.block_on(async {
+        let (_, header) = open(&archive).await?;
+        let mut versions = header
+            .files()
+            .iter()
+            .map(|f| f.version())
+            .collect::<Vec<_>>();
+        versions.sort_unstable();
+        versions.dedup();
+        println!("format: asset_registry");
+        println!("uuid: {}", header.uuid());
+        println!("files: {}", header.files().len());
+        println!("versions: {:?}", versions);
+        Ok(())
+    })
+}
+
+pub fn verify(archive: PathBuf) -> Result<(), Box<dyn Error>> {
+    runtime()?.block_on(async {
+        // The header hash is checked when the header is read.
+        let (mut reader, header) = open(&archive).await?;
+        let mut corrupt_files = 0;
+        for file in header.files() {
+            if let Err(e) = AssetArchive::verify_file(file, &mut reader).await {
+                println!("{}: {}", file.identifier().as_str(), e);
+                corrupt_files += 1;
+            }
+        }
+        match corrupt_files {
+            0 => {
+                println!("Archive is valid.");
+                Ok(())
+            }
+            n => Err(invalid_argument(format!("{} corrupt files found.", n))),
+        }
+    })
+}
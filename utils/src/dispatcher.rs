@@ -1,6 +1,6 @@
 use rayon_core::{Scope, ScopeFifo, ThreadPool, ThreadPoolBuilder};
 use std::{future::Future, num::NonZeroUsize};
-use tokio::{runtime::*, task::JoinHandle};
+use tokio::{runtime::*, sync::oneshot, task::JoinHandle};
 
 #[derive(Debug)]
 pub struct Dispatcher {
@@ -109,6 +109,20 @@ impl Dispatcher {
         self.thread_pool.spawn(op)
     }
 
+    /// Spawns `op` on the worker thread pool and returns a receiver which resolves to its result.
+    /// Awaiting the receiver from the async runtime does not block any of its threads.
+    pub fn spawn_with_result<OP, R>(&self, op: OP) -> oneshot::Receiver<R>
+    where
+        OP: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let (sender, receiver) = oneshot::channel();
+        self.thread_pool.spawn(move || {
+            let _ = sender.send(op());
+        });
+        receiver
+    }
+
     #[inline(always)]
     pub fn spawn_fifo<OP>(&self, op: OP)
     where